@@ -0,0 +1 @@
+ALTER TABLE config_data ADD lyrics_source_precedence TEXT DEFAULT 'sidecar';
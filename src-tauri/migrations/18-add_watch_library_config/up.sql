@@ -0,0 +1 @@
+ALTER TABLE config_data ADD watch_library BOOLEAN DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE config_data ADD scan_worker_count INTEGER DEFAULT 0;
@@ -0,0 +1,5 @@
+ALTER TABLE tracks ADD metadata_inferred BOOLEAN NOT NULL DEFAULT 0;
+CREATE INDEX idx_tracks_metadata_inferred ON tracks(metadata_inferred);
+
+-- NULL means the built-in default templates
+ALTER TABLE config_data ADD path_templates TEXT;
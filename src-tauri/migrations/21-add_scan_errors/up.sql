@@ -0,0 +1,10 @@
+CREATE TABLE scan_errors (
+    id INTEGER PRIMARY KEY,
+    file_path TEXT NOT NULL,
+    error_kind TEXT NOT NULL,
+    message TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_scan_errors_file_path ON scan_errors(file_path);
+CREATE INDEX idx_scan_errors_error_kind ON scan_errors(error_kind);
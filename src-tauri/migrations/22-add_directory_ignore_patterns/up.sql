@@ -0,0 +1,2 @@
+-- Newline-separated gitignore-style patterns, relative to the directory
+ALTER TABLE directories ADD ignore_patterns TEXT;
@@ -0,0 +1,2 @@
+-- Comma-separated list; NULL means the built-in defaults
+ALTER TABLE config_data ADD audio_extensions TEXT;
@@ -0,0 +1,2 @@
+-- Start of a CUE sheet virtual track within its audio file, in seconds; NULL for whole-file tracks
+ALTER TABLE tracks ADD start_offset REAL;
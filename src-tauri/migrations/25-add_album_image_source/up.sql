@@ -0,0 +1,6 @@
+-- File the cached artwork in image_path was made from (a track, or a cover image next to it)
+-- and its quick hash; a NULL hash means the artwork still has to be looked up
+ALTER TABLE albums ADD image_source_path TEXT;
+ALTER TABLE albums ADD image_source_hash TEXT;
+
+CREATE INDEX idx_albums_image_source_path ON albums(image_source_path);
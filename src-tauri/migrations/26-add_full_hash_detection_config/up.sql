@@ -0,0 +1,2 @@
+-- Hash whole files instead of their first 64KB to detect moves and duplicates
+ALTER TABLE config_data ADD full_hash_detection BOOLEAN DEFAULT 0;
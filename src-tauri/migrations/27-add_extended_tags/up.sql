@@ -0,0 +1,11 @@
+ALTER TABLE tracks ADD disc_number INTEGER;
+ALTER TABLE tracks ADD year INTEGER;
+ALTER TABLE tracks ADD genre TEXT;
+ALTER TABLE tracks ADD composer TEXT;
+ALTER TABLE tracks ADD musicbrainz_recording_id TEXT;
+ALTER TABLE tracks ADD isrc TEXT;
+
+ALTER TABLE albums ADD year INTEGER;
+ALTER TABLE albums ADD album_artist_sort_name TEXT;
+ALTER TABLE albums ADD musicbrainz_release_id TEXT;
+
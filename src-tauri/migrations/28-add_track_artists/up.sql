@@ -0,0 +1,19 @@
+-- Every artist credited on a track, the primary artist (tracks.artist_id) at position 0
+CREATE TABLE track_artists (
+    track_id INTEGER NOT NULL,
+    artist_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (track_id, artist_id)
+);
+
+CREATE INDEX idx_track_artists_artist_id ON track_artists(artist_id);
+
+INSERT INTO track_artists (track_id, artist_id, position)
+SELECT id, artist_id, 0 FROM tracks;
+
+-- The artist tag as written, e.g. "A feat. B"
+ALTER TABLE tracks ADD artist_credit TEXT;
+
+-- Newline-separated; NULL means the default separators
+ALTER TABLE config_data ADD artist_separators TEXT;
+
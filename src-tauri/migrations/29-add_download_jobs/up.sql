@@ -0,0 +1,21 @@
+-- The mass download job (at most one), kept until it completes or is cancelled so it can
+-- be picked up again after a restart. state is "running" or "paused".
+CREATE TABLE download_jobs (
+    id INTEGER PRIMARY KEY,
+    state TEXT NOT NULL,
+    concurrency INTEGER NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Queued tracks of a download job in queue order.
+-- status: 0 = pending, 1 = downloaded, 2 = failed
+CREATE TABLE download_job_tracks (
+    id INTEGER PRIMARY KEY,
+    job_id INTEGER NOT NULL,
+    track_id INTEGER NOT NULL,
+    status INTEGER NOT NULL DEFAULT 0,
+    message TEXT,
+    UNIQUE (job_id, track_id)
+);
+
+CREATE INDEX idx_download_job_tracks_job_id_status ON download_job_tracks(job_id, status);
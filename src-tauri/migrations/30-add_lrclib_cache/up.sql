@@ -0,0 +1,16 @@
+-- LRCLIB responses per instance, so repeated lookups of the same track don't hit the API.
+-- endpoint: "get" or "search"; cache_key: the normalized request (see lrclib/cache.rs)
+-- status: HTTP status of the response (200, or 404 for tracks without lyrics)
+CREATE TABLE lrclib_cache (
+    id INTEGER PRIMARY KEY,
+    lrclib_instance TEXT NOT NULL,
+    endpoint TEXT NOT NULL,
+    cache_key TEXT NOT NULL,
+    status INTEGER NOT NULL,
+    response TEXT,
+    fetched_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (lrclib_instance, endpoint, cache_key)
+);
+
+-- When a lyrics download last found nothing on LRCLIB; NULL once lyrics were found
+ALTER TABLE tracks ADD lrclib_not_found_at TEXT;
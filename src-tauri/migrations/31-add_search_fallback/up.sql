@@ -0,0 +1,7 @@
+-- Minimum confidence (0 to 1) for applying a search result when /api/get finds nothing
+ALTER TABLE config_data ADD search_fallback_min_confidence REAL NOT NULL DEFAULT 0.8;
+
+-- Best search result of the last download that fell back to searching: its confidence and
+-- how it was scored. NULL when the track was found directly.
+ALTER TABLE tracks ADD lrclib_match_score REAL;
+ALTER TABLE tracks ADD lrclib_match_reason TEXT;
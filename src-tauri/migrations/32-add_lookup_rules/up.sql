@@ -0,0 +1,3 @@
+-- Normalization rules tried in order when /api/get finds nothing, one per line: built-in rule
+-- names or /regex/ patterns removed from titles. NULL means the defaults.
+ALTER TABLE config_data ADD lookup_rules TEXT;
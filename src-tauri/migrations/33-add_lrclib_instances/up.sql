@@ -0,0 +1,15 @@
+-- LRCLIB instances lyrics are looked up on, tried in order of position. The single
+-- config_data.lrclib_instance stays as the instance lyrics are published to.
+CREATE TABLE lrclib_instances (
+    id INTEGER PRIMARY KEY,
+    url TEXT NOT NULL UNIQUE,
+    position INTEGER NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT 1,
+    -- Seconds a request may take, NULL for the client's default
+    timeout_seconds INTEGER
+);
+
+INSERT INTO lrclib_instances (url, position)
+SELECT COALESCE(NULLIF(TRIM(lrclib_instance), ''), 'https://lrclib.net'), 0
+FROM config_data
+LIMIT 1;
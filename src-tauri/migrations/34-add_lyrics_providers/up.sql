@@ -0,0 +1,5 @@
+-- Lyrics providers tried in priority order, one name per line (`lrclib`, `local_folder`).
+-- NULL means the defaults.
+ALTER TABLE config_data ADD lyrics_providers TEXT;
+-- Folder of loose .lrc/.txt/lyricsfile files read by the `local_folder` provider
+ALTER TABLE config_data ADD local_lyrics_folder TEXT;
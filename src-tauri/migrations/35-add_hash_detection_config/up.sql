@@ -0,0 +1,3 @@
+-- Detect moved and modified files by content hash (1) or by mtime and size only (0), for
+-- scans started without an explicit choice and for the library watcher
+ALTER TABLE config_data ADD hash_detection BOOLEAN DEFAULT 1;
@@ -0,0 +1,4 @@
+-- Size and mtime of the artwork source, so an unchanged source is not hashed again on
+-- every artwork pass. NULL (e.g. after upgrading) falls back to comparing the hash once.
+ALTER TABLE albums ADD image_source_size INTEGER;
+ALTER TABLE albums ADD image_source_modified_time INTEGER;
@@ -0,0 +1,3 @@
+-- Forget the modification times once, so the next scan re-reads every file for the extended
+-- tags (v27) and the split artist credits (v28). The app tells the user before that scan.
+UPDATE tracks SET modified_time = NULL;
@@ -0,0 +1,2 @@
+-- Parallel LRCLIB requests of a mass download started without a concurrency (1 to 8)
+ALTER TABLE config_data ADD download_concurrency INTEGER DEFAULT 4;
@@ -0,0 +1,2 @@
+-- Whether the job asks the lyrics providers even for tracks with a cached lookup
+ALTER TABLE download_jobs ADD bypass_cache BOOLEAN NOT NULL DEFAULT 0;
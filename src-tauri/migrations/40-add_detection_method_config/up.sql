@@ -0,0 +1,13 @@
+-- How scans started without an explicit choice and the library watcher detect moved and
+-- modified files: 'hash' (first 64KB), 'full_hash' (whole files) or 'metadata' (mtime and
+-- size only). Replaces hash_detection and full_hash_detection, which could disagree.
+ALTER TABLE config_data ADD detection_method TEXT NOT NULL DEFAULT 'hash';
+
+UPDATE config_data SET detection_method = CASE
+  WHEN hash_detection = 0 THEN 'metadata'
+  WHEN full_hash_detection = 1 THEN 'full_hash'
+  ELSE 'hash'
+END;
+
+ALTER TABLE config_data DROP COLUMN full_hash_detection;
+ALTER TABLE config_data DROP COLUMN hash_detection;
@@ -1,34 +1,58 @@
+use crate::lrclib::lookup_variants::DEFAULT_LOOKUP_RULES;
 use crate::lyricsfile::{lyrics_presence_from_lyricsfile, LyricsPresence};
 use crate::persistent_entities::{
-    PersistentAlbum, PersistentArtist, PersistentConfig, PersistentTrack,
+    PersistentAlbum, PersistentArtist, PersistentConfig, PersistentDownloadJob,
+    PersistentLrclibInstance, PersistentScanError, PersistentTrack,
 };
-use crate::scanner::models::DbTrack;
+use crate::providers::DEFAULT_LYRICS_PROVIDERS;
+use crate::scanner::models::{DbTrack, ScanError};
+use crate::scanner::artist_credit::DEFAULT_ARTIST_SEPARATORS;
+use crate::scanner::path_template::DEFAULT_PATH_TEMPLATES;
+use crate::scanner::scan::DEFAULT_AUDIO_EXTENSIONS;
 use crate::utils::prepare_input;
 use anyhow::Result;
 use include_dir::{include_dir, Dir};
 use indoc::indoc;
-use rusqlite::{named_params, params, Connection, OptionalExtension};
+use rusqlite::{named_params, params, Connection, OptionalExtension, TransactionBehavior};
 use rusqlite_migration::Migrations;
+use std::collections::HashMap;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tauri::{AppHandle, Manager};
 
 static MIGRATIONS_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/migrations");
 
-/// Initializes the database connection, creating the .sqlite file if needed, and upgrading the
-/// database if it's out of date.
-pub fn initialize_database(app_handle: &AppHandle) -> Result<Connection, rusqlite::Error> {
+/// How long a connection waits for the write lock held by the other connection
+/// (e.g. a scan batch) before failing with `SQLITE_BUSY`
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn database_path(app_handle: &AppHandle) -> PathBuf {
     let app_dir = app_handle
         .path()
         .app_data_dir()
         .expect("The app data directory should exist.");
     fs::create_dir_all(&app_dir).expect("The app data directory should be created.");
-    let sqlite_path = app_dir.join("db.sqlite3");
-
-    println!("Database file path: {}", sqlite_path.display());
+    app_dir.join("db.sqlite3")
+}
 
-    let mut db = Connection::open(sqlite_path)?;
+fn open_connection(sqlite_path: &Path) -> Result<Connection, rusqlite::Error> {
+    let db = Connection::open(sqlite_path)?;
 
     db.pragma_update(None, "journal_mode", "WAL")?;
+    db.busy_timeout(BUSY_TIMEOUT)?;
+
+    Ok(db)
+}
+
+/// Initializes the database connection, creating the .sqlite file if needed, and upgrading the
+/// database if it's out of date.
+pub fn initialize_database(app_handle: &AppHandle) -> Result<Connection, rusqlite::Error> {
+    let sqlite_path = database_path(app_handle);
+
+    println!("Database file path: {}", sqlite_path.display());
+
+    let mut db = open_connection(&sqlite_path)?;
 
     let current_version: i64 = db
         .query_row("PRAGMA user_version;", [], |row| row.get(0))
@@ -58,6 +82,20 @@ pub fn initialize_database(app_handle: &AppHandle) -> Result<Connection, rusqlit
     Ok(db)
 }
 
+/// Open the second connection used by library scans and the watcher, so long jobs write
+/// through it while the UI keeps reading through the main one (WAL readers don't wait for
+/// the writer). Must be called after `initialize_database`.
+pub fn open_writer_connection(app_handle: &AppHandle) -> Result<Connection, rusqlite::Error> {
+    open_connection(&database_path(app_handle))
+}
+
+/// Begin a transaction that takes the write lock right away. A deferred transaction that
+/// reads first fails with `SQLITE_BUSY` (without waiting) when the other connection writes
+/// before it upgrades to a write.
+pub fn write_transaction(conn: &mut Connection) -> rusqlite::Result<rusqlite::Transaction<'_>> {
+    conn.transaction_with_behavior(TransactionBehavior::Immediate)
+}
+
 /// Check whether FTS5 virtual tables were created successfully.
 /// This is a safety net in case SQLite was compiled without FTS5 support.
 pub fn fts5_enabled(db: &Connection) -> bool {
@@ -132,15 +170,53 @@ pub fn get_directories(db: &Connection) -> Result<Vec<String>> {
 }
 
 pub fn set_directories(directories: Vec<String>, db: &Connection) -> Result<()> {
-    db.execute("DELETE FROM directories WHERE 1", ())?;
+    // Keep the rows (and ignore patterns) of directories that are still selected
+    let existing_directories = get_directories(db)?;
+    let mut delete_statement = db.prepare("DELETE FROM directories WHERE path = @path")?;
+    for directory in existing_directories.iter() {
+        if !directories.contains(directory) {
+            delete_statement.execute(named_params! { "@path": directory })?;
+        }
+    }
+
     let mut statement = db.prepare("INSERT INTO directories (path) VALUES (@path)")?;
     for directory in directories.iter() {
-        statement.execute(named_params! { "@path": directory })?;
+        if !existing_directories.contains(directory) {
+            statement.execute(named_params! { "@path": directory })?;
+        }
     }
 
     Ok(())
 }
 
+/// Library directories mapped to their gitignore-style ignore patterns (empty if none)
+pub fn get_directory_ignore_patterns(db: &Connection) -> Result<HashMap<String, String>> {
+    let mut statement = db.prepare("SELECT path, ignore_patterns FROM directories")?;
+    let mut rows = statement.query([])?;
+    let mut directory_patterns: HashMap<String, String> = HashMap::new();
+    while let Some(row) = rows.next()? {
+        let path: String = row.get("path")?;
+        let ignore_patterns: Option<String> = row.get("ignore_patterns")?;
+
+        directory_patterns.insert(path, ignore_patterns.unwrap_or_default());
+    }
+
+    Ok(directory_patterns)
+}
+
+pub fn set_directory_ignore_patterns(
+    directory: &str,
+    ignore_patterns: &str,
+    db: &Connection,
+) -> Result<()> {
+    db.execute(
+        "UPDATE directories SET ignore_patterns = @ignore_patterns WHERE path = @path",
+        named_params! { "@ignore_patterns": ignore_patterns, "@path": directory },
+    )?;
+
+    Ok(())
+}
+
 pub fn get_init(db: &Connection) -> Result<bool> {
     let mut statement = db.prepare("SELECT init FROM library_data LIMIT 1")?;
     let init: bool = statement.query_row([], |r| r.get(0))?;
@@ -153,6 +229,15 @@ pub fn set_init(init: bool, db: &Connection) -> Result<()> {
     Ok(())
 }
 
+/// Check whether tracks are waiting for their tags to be re-read by the next scan, as
+/// migration v37 requests after the extended tags and artist credits were added
+pub fn get_tags_reread_pending(db: &Connection) -> Result<bool> {
+    let mut statement =
+        db.prepare("SELECT EXISTS(SELECT 1 FROM tracks WHERE modified_time IS NULL)")?;
+    let pending: bool = statement.query_row([], |r| r.get(0))?;
+    Ok(pending)
+}
+
 pub fn get_config(db: &Connection) -> Result<PersistentConfig> {
     let mut statement = db.prepare(indoc! {"
       SELECT
@@ -162,7 +247,19 @@ pub fn get_config(db: &Connection) -> Result<PersistentConfig> {
         try_embed_lyrics,
         theme_mode,
         lrclib_instance,
-        volume
+        volume,
+        lyrics_source_precedence,
+        watch_library,
+        scan_worker_count,
+        path_templates,
+        audio_extensions,
+        artist_separators,
+        search_fallback_min_confidence,
+        lookup_rules,
+        lyrics_providers,
+        local_lyrics_folder,
+        download_concurrency,
+        detection_method
       FROM config_data
       LIMIT 1
     "})?;
@@ -175,6 +272,30 @@ pub fn get_config(db: &Connection) -> Result<PersistentConfig> {
             theme_mode: r.get("theme_mode")?,
             lrclib_instance: r.get("lrclib_instance")?,
             volume: r.get("volume")?,
+            lyrics_source_precedence: r.get("lyrics_source_precedence")?,
+            watch_library: r.get("watch_library")?,
+            scan_worker_count: r.get("scan_worker_count")?,
+            path_templates: r
+                .get::<_, Option<String>>("path_templates")?
+                .unwrap_or_else(|| DEFAULT_PATH_TEMPLATES.to_owned()),
+            audio_extensions: r
+                .get::<_, Option<String>>("audio_extensions")?
+                .unwrap_or_else(|| DEFAULT_AUDIO_EXTENSIONS.to_owned()),
+            artist_separators: r
+                .get::<_, Option<String>>("artist_separators")?
+                .unwrap_or_else(|| DEFAULT_ARTIST_SEPARATORS.to_owned()),
+            search_fallback_min_confidence: r.get("search_fallback_min_confidence")?,
+            lookup_rules: r
+                .get::<_, Option<String>>("lookup_rules")?
+                .unwrap_or_else(|| DEFAULT_LOOKUP_RULES.to_owned()),
+            lyrics_providers: r
+                .get::<_, Option<String>>("lyrics_providers")?
+                .unwrap_or_else(|| DEFAULT_LYRICS_PROVIDERS.to_owned()),
+            local_lyrics_folder: r
+                .get::<_, Option<String>>("local_lyrics_folder")?
+                .unwrap_or_default(),
+            download_concurrency: r.get("download_concurrency")?,
+            detection_method: r.get("detection_method")?,
         })
     })?;
     Ok(row)
@@ -188,6 +309,18 @@ pub fn set_config(
     theme_mode: &str,
     lrclib_instance: &str,
     volume: f64,
+    lyrics_source_precedence: &str,
+    watch_library: bool,
+    scan_worker_count: u32,
+    path_templates: &str,
+    audio_extensions: &str,
+    artist_separators: &str,
+    search_fallback_min_confidence: f64,
+    lookup_rules: &str,
+    lyrics_providers: &str,
+    local_lyrics_folder: &str,
+    download_concurrency: u32,
+    detection_method: &str,
     db: &Connection,
 ) -> Result<()> {
     let mut statement = db.prepare(indoc! {"
@@ -199,10 +332,23 @@ pub fn set_config(
         try_embed_lyrics = ?,
         theme_mode = ?,
         lrclib_instance = ?,
-        volume = ?
+        volume = ?,
+        lyrics_source_precedence = ?,
+        watch_library = ?,
+        scan_worker_count = ?,
+        path_templates = ?,
+        audio_extensions = ?,
+        artist_separators = ?,
+        search_fallback_min_confidence = ?,
+        lookup_rules = ?,
+        lyrics_providers = ?,
+        local_lyrics_folder = ?,
+        download_concurrency = ?,
+        detection_method = ?
       WHERE 1
     "})?;
-    statement.execute((
+    // More values than rusqlite implements `Params` for as a tuple
+    statement.execute(params![
         skip_tracks_with_synced_lyrics,
         skip_tracks_with_plain_lyrics,
         show_line_count,
@@ -210,7 +356,19 @@ pub fn set_config(
         theme_mode,
         lrclib_instance,
         volume,
-    ))?;
+        lyrics_source_precedence,
+        watch_library,
+        scan_worker_count,
+        path_templates,
+        audio_extensions,
+        artist_separators,
+        search_fallback_min_confidence,
+        lookup_rules,
+        lyrics_providers,
+        local_lyrics_folder,
+        download_concurrency,
+        detection_method,
+    ])?;
     Ok(())
 }
 
@@ -264,6 +422,19 @@ pub fn get_track_by_id(id: i64, db: &Connection) -> Result<PersistentTrack> {
       tracks.album_id,
       tracks.duration,
       tracks.track_number,
+      tracks.metadata_inferred,
+      tracks.start_offset,
+      tracks.scan_status,
+      tracks.disc_number,
+      tracks.year,
+      tracks.genre,
+      tracks.composer,
+      tracks.musicbrainz_recording_id,
+      tracks.isrc,
+      tracks.artist_credit,
+      tracks.lrclib_not_found_at,
+      tracks.lrclib_match_score,
+      tracks.lrclib_match_reason,
       albums.image_path,
       lyricsfiles.id AS lyricsfile_id,
       lyricsfiles.lyricsfile,
@@ -298,6 +469,19 @@ pub fn get_track_by_id(id: i64, db: &Connection) -> Result<PersistentTrack> {
             lyricsfile_id: row.get("lyricsfile_id")?,
             image_path: row.get("image_path")?,
             instrumental: is_instrumental,
+            metadata_inferred: row.get("metadata_inferred")?,
+            start_offset: row.get("start_offset")?,
+            offline: row.get::<_, i32>("scan_status")? == SCAN_STATUS_OFFLINE,
+            disc_number: row.get("disc_number")?,
+            year: row.get("year")?,
+            genre: row.get("genre")?,
+            composer: row.get("composer")?,
+            musicbrainz_recording_id: row.get("musicbrainz_recording_id")?,
+            isrc: row.get("isrc")?,
+            artist_credit: row.get("artist_credit")?,
+            lrclib_not_found_at: row.get("lrclib_not_found_at")?,
+            lrclib_match_score: row.get("lrclib_match_score")?,
+            lrclib_match_reason: row.get("lrclib_match_reason")?,
         })
     })?;
     Ok(row)
@@ -589,7 +773,7 @@ pub fn get_tracks(db: &Connection) -> Result<Vec<PersistentTrack>> {
       SELECT
           tracks.id, tracks.file_path, tracks.file_name, tracks.title,
           artists.name AS artist_name, tracks.artist_id,
-          albums.name AS album_name, albums.album_artist_name, tracks.album_id, tracks.duration, tracks.track_number,
+          albums.name AS album_name, albums.album_artist_name, tracks.album_id, tracks.duration, tracks.track_number, tracks.metadata_inferred, tracks.start_offset, tracks.scan_status, tracks.disc_number, tracks.year, tracks.genre, tracks.composer, tracks.musicbrainz_recording_id, tracks.isrc, tracks.artist_credit, tracks.lrclib_not_found_at, tracks.lrclib_match_score, tracks.lrclib_match_reason,
           albums.image_path, lyricsfiles.id AS lyricsfile_id, lyricsfiles.lyricsfile, COALESCE(lyricsfiles.instrumental, 0) AS instrumental
       FROM tracks
       JOIN albums ON tracks.album_id = albums.id
@@ -622,6 +806,19 @@ pub fn get_tracks(db: &Connection) -> Result<Vec<PersistentTrack>> {
             lyricsfile_id: row.get("lyricsfile_id")?,
             image_path: row.get("image_path")?,
             instrumental: is_instrumental,
+            metadata_inferred: row.get("metadata_inferred")?,
+            start_offset: row.get("start_offset")?,
+            offline: row.get::<_, i32>("scan_status")? == SCAN_STATUS_OFFLINE,
+            disc_number: row.get("disc_number")?,
+            year: row.get("year")?,
+            genre: row.get("genre")?,
+            composer: row.get("composer")?,
+            musicbrainz_recording_id: row.get("musicbrainz_recording_id")?,
+            isrc: row.get("isrc")?,
+            artist_credit: row.get("artist_credit")?,
+            lrclib_not_found_at: row.get("lrclib_not_found_at")?,
+            lrclib_match_score: row.get("lrclib_match_score")?,
+            lrclib_match_reason: row.get("lrclib_match_reason")?,
         };
 
         tracks.push(track);
@@ -782,8 +979,8 @@ pub fn get_search_track_ids(
 
 pub fn get_albums(db: &Connection) -> Result<Vec<PersistentAlbum>> {
     let mut statement = db.prepare(indoc! {"
-      SELECT albums.id, albums.name, albums.album_artist_name AS album_artist_name, albums.album_artist_name,
-          COUNT(tracks.id) AS tracks_count
+      SELECT albums.id, albums.name, albums.image_path, albums.album_artist_name AS album_artist_name, albums.album_artist_name,
+          albums.year, albums.album_artist_sort_name, albums.musicbrainz_release_id, COUNT(tracks.id) AS tracks_count
       FROM albums
       JOIN tracks ON tracks.album_id = albums.id
       GROUP BY albums.id, albums.name, albums.album_artist_name
@@ -799,6 +996,9 @@ pub fn get_albums(db: &Connection) -> Result<Vec<PersistentAlbum>> {
             image_path: row.get("image_path")?,
             artist_name: row.get("album_artist_name")?,
             album_artist_name: row.get("album_artist_name")?,
+            album_artist_sort_name: row.get("album_artist_sort_name")?,
+            year: row.get("year")?,
+            musicbrainz_release_id: row.get("musicbrainz_release_id")?,
             tracks_count: row.get("tracks_count")?,
         };
 
@@ -813,7 +1013,11 @@ pub fn get_album_by_id(id: i64, db: &Connection) -> Result<PersistentAlbum> {
     SELECT
       albums.id,
       albums.name,
+      albums.image_path,
       albums.album_artist_name,
+      albums.year,
+      albums.album_artist_sort_name,
+      albums.musicbrainz_release_id,
       COUNT(tracks.id) AS tracks_count
     FROM albums
     JOIN tracks ON tracks.album_id = albums.id
@@ -828,9 +1032,12 @@ pub fn get_album_by_id(id: i64, db: &Connection) -> Result<PersistentAlbum> {
         Ok(PersistentAlbum {
             id: row.get("id")?,
             name: row.get("name")?,
-            image_path: None,
+            image_path: row.get("image_path")?,
             artist_name: row.get("album_artist_name")?,
             album_artist_name: row.get("album_artist_name")?,
+            album_artist_sort_name: row.get("album_artist_sort_name")?,
+            year: row.get("year")?,
+            musicbrainz_release_id: row.get("musicbrainz_release_id")?,
             tracks_count: row.get("tracks_count")?,
         })
     })?;
@@ -882,9 +1089,9 @@ pub fn get_search_album_ids(query_str: &String, db: &Connection) -> Result<Vec<i
 
 pub fn get_artists(db: &Connection) -> Result<Vec<PersistentArtist>> {
     let mut statement = db.prepare(indoc! {"
-    SELECT artists.id, artists.name AS name, COUNT(tracks.id) AS tracks_count
+    SELECT artists.id, artists.name AS name, COUNT(track_artists.track_id) AS tracks_count
     FROM artists
-    JOIN tracks ON tracks.artist_id = artists.id
+    JOIN track_artists ON track_artists.artist_id = artists.id
     GROUP BY artists.id, artists.name
     ORDER BY artists.name_lower ASC
   "})?;
@@ -909,9 +1116,9 @@ pub fn get_artist_by_id(id: i64, db: &Connection) -> Result<PersistentArtist> {
     let mut statement = db.prepare(indoc! {"
     SELECT artists.id,
       artists.name AS name,
-      COUNT(tracks.id) AS tracks_count
+      COUNT(track_artists.track_id) AS tracks_count
     FROM artists
-    JOIN tracks ON tracks.artist_id = artists.id
+    JOIN track_artists ON track_artists.artist_id = artists.id
     WHERE artists.id = ?
     GROUP BY artists.id, artists.name
     LIMIT 1
@@ -984,6 +1191,19 @@ pub fn get_album_tracks(album_id: i64, db: &Connection) -> Result<Vec<Persistent
       tracks.album_id,
       tracks.duration,
       tracks.track_number,
+      tracks.metadata_inferred,
+      tracks.start_offset,
+      tracks.scan_status,
+      tracks.disc_number,
+      tracks.year,
+      tracks.genre,
+      tracks.composer,
+      tracks.musicbrainz_recording_id,
+      tracks.isrc,
+      tracks.artist_credit,
+      tracks.lrclib_not_found_at,
+      tracks.lrclib_match_score,
+      tracks.lrclib_match_reason,
       albums.image_path,
       lyricsfiles.id AS lyricsfile_id,
       lyricsfiles.lyricsfile,
@@ -993,7 +1213,7 @@ pub fn get_album_tracks(album_id: i64, db: &Connection) -> Result<Vec<Persistent
     JOIN artists ON tracks.artist_id = artists.id
     LEFT JOIN lyricsfiles ON lyricsfiles.track_id = tracks.id
     WHERE tracks.album_id = ?
-    ORDER BY tracks.track_number ASC
+    ORDER BY COALESCE(tracks.disc_number, 1) ASC, tracks.track_number ASC
   "})?;
     let mut rows = statement.query([album_id])?;
     let mut tracks: Vec<PersistentTrack> = Vec::new();
@@ -1019,6 +1239,19 @@ pub fn get_album_tracks(album_id: i64, db: &Connection) -> Result<Vec<Persistent
             lyricsfile_id: row.get("lyricsfile_id")?,
             image_path: row.get("image_path")?,
             instrumental: is_instrumental,
+            metadata_inferred: row.get("metadata_inferred")?,
+            start_offset: row.get("start_offset")?,
+            offline: row.get::<_, i32>("scan_status")? == SCAN_STATUS_OFFLINE,
+            disc_number: row.get("disc_number")?,
+            year: row.get("year")?,
+            genre: row.get("genre")?,
+            composer: row.get("composer")?,
+            musicbrainz_recording_id: row.get("musicbrainz_recording_id")?,
+            isrc: row.get("isrc")?,
+            artist_credit: row.get("artist_credit")?,
+            lrclib_not_found_at: row.get("lrclib_not_found_at")?,
+            lrclib_match_score: row.get("lrclib_match_score")?,
+            lrclib_match_reason: row.get("lrclib_match_reason")?,
         };
 
         tracks.push(track);
@@ -1050,7 +1283,7 @@ pub fn get_album_track_ids(
     };
 
     let full_query = format!(
-        "{}{} ORDER BY tracks.track_number ASC",
+        "{}{} ORDER BY COALESCE(tracks.disc_number, 1) ASC, tracks.track_number ASC",
         base_query, lyrics_conditions
     );
 
@@ -1068,14 +1301,15 @@ pub fn get_album_track_ids(
 pub fn get_artist_tracks(artist_id: i64, db: &Connection) -> Result<Vec<PersistentTrack>> {
     let mut statement = db.prepare(indoc! {"
       SELECT tracks.id, tracks.file_path, tracks.file_name, tracks.title, artists.name AS artist_name,
-        tracks.artist_id, albums.name AS album_name, albums.album_artist_name, tracks.album_id, tracks.duration, tracks.track_number,
+        tracks.artist_id, albums.name AS album_name, albums.album_artist_name, tracks.album_id, tracks.duration, tracks.track_number, tracks.metadata_inferred, tracks.start_offset, tracks.scan_status, tracks.disc_number, tracks.year, tracks.genre, tracks.composer, tracks.musicbrainz_recording_id, tracks.isrc, tracks.artist_credit, tracks.lrclib_not_found_at, tracks.lrclib_match_score, tracks.lrclib_match_reason,
         albums.image_path, lyricsfiles.id AS lyricsfile_id, lyricsfiles.lyricsfile, COALESCE(lyricsfiles.instrumental, 0) AS instrumental
       FROM tracks
       JOIN albums ON tracks.album_id = albums.id
       JOIN artists ON tracks.artist_id = artists.id
+      JOIN track_artists ON track_artists.track_id = tracks.id
       LEFT JOIN lyricsfiles ON lyricsfiles.track_id = tracks.id
-      WHERE tracks.artist_id = ?
-      ORDER BY albums.name_lower ASC, tracks.track_number ASC
+      WHERE track_artists.artist_id = ?
+      ORDER BY albums.name_lower ASC, COALESCE(tracks.disc_number, 1) ASC, tracks.track_number ASC
   "})?;
     let mut rows = statement.query([artist_id])?;
     let mut tracks: Vec<PersistentTrack> = Vec::new();
@@ -1101,6 +1335,19 @@ pub fn get_artist_tracks(artist_id: i64, db: &Connection) -> Result<Vec<Persiste
             lyricsfile_id: row.get("lyricsfile_id")?,
             image_path: row.get("image_path")?,
             instrumental: is_instrumental,
+            metadata_inferred: row.get("metadata_inferred")?,
+            start_offset: row.get("start_offset")?,
+            offline: row.get::<_, i32>("scan_status")? == SCAN_STATUS_OFFLINE,
+            disc_number: row.get("disc_number")?,
+            year: row.get("year")?,
+            genre: row.get("genre")?,
+            composer: row.get("composer")?,
+            musicbrainz_recording_id: row.get("musicbrainz_recording_id")?,
+            isrc: row.get("isrc")?,
+            artist_credit: row.get("artist_credit")?,
+            lrclib_not_found_at: row.get("lrclib_not_found_at")?,
+            lrclib_match_score: row.get("lrclib_match_score")?,
+            lrclib_match_reason: row.get("lrclib_match_reason")?,
         };
 
         tracks.push(track);
@@ -1120,8 +1367,9 @@ pub fn get_artist_track_ids(
       FROM tracks
       JOIN albums ON tracks.album_id = albums.id
       JOIN artists ON tracks.artist_id = artists.id
+      JOIN track_artists ON track_artists.track_id = tracks.id
       LEFT JOIN lyricsfiles ON lyricsfiles.track_id = tracks.id
-      WHERE tracks.artist_id = ?"};
+      WHERE track_artists.artist_id = ?"};
 
     let lyrics_conditions = match (without_plain_lyrics, without_synced_lyrics) {
         (true, true) => {
@@ -1133,7 +1381,7 @@ pub fn get_artist_track_ids(
     };
 
     let full_query = format!(
-        "{}{} ORDER BY albums.name_lower ASC, tracks.track_number ASC",
+        "{}{} ORDER BY albums.name_lower ASC, COALESCE(tracks.disc_number, 1) ASC, tracks.track_number ASC",
         base_query, lyrics_conditions
     );
 
@@ -1149,12 +1397,14 @@ pub fn get_artist_track_ids(
 }
 
 pub fn clean_library(db: &Connection) -> Result<()> {
+    db.execute("DELETE FROM track_artists", ())?;
     db.execute("DELETE FROM tracks WHERE 1", ())?;
     db.execute("DELETE FROM albums WHERE 1", ())?;
     db.execute("DELETE FROM artists WHERE 1", ())?;
     db.execute("DELETE FROM tracks_fts", ())?;
     db.execute("DELETE FROM albums_fts", ())?;
     db.execute("DELETE FROM artists_fts", ())?;
+    db.execute("DELETE FROM scan_errors", ())?;
     Ok(())
 }
 
@@ -1177,17 +1427,7 @@ pub fn insert_track_fts_tx(
 }
 
 pub fn delete_tracks_fts_by_ids_tx(ids: &[i64], tx: &rusqlite::Transaction) -> Result<()> {
-    if ids.is_empty() {
-        return Ok(());
-    }
-    let placeholders: Vec<String> = (0..ids.len()).map(|i| format!("?{}", i + 1)).collect();
-    let query = format!(
-        "DELETE FROM tracks_fts WHERE track_id IN ({})",
-        placeholders.join(", ")
-    );
-    let mut statement = tx.prepare(&query)?;
-    let params: Vec<rusqlite::types::Value> = ids.iter().map(|&id| id.into()).collect();
-    statement.execute(rusqlite::params_from_iter(params.iter()))?;
+    delete_by_ids("tracks_fts", "track_id", ids, tx)?;
     Ok(())
 }
 
@@ -1205,17 +1445,7 @@ pub fn insert_album_fts_tx(
 }
 
 pub fn delete_albums_fts_by_ids_tx(ids: &[i64], tx: &rusqlite::Transaction) -> Result<()> {
-    if ids.is_empty() {
-        return Ok(());
-    }
-    let placeholders: Vec<String> = (0..ids.len()).map(|i| format!("?{}", i + 1)).collect();
-    let query = format!(
-        "DELETE FROM albums_fts WHERE album_id IN ({})",
-        placeholders.join(", ")
-    );
-    let mut statement = tx.prepare(&query)?;
-    let params: Vec<rusqlite::types::Value> = ids.iter().map(|&id| id.into()).collect();
-    statement.execute(rusqlite::params_from_iter(params.iter()))?;
+    delete_by_ids("albums_fts", "album_id", ids, tx)?;
     Ok(())
 }
 
@@ -1232,24 +1462,14 @@ pub fn insert_artist_fts_tx(
 }
 
 pub fn delete_artists_fts_by_ids_tx(ids: &[i64], tx: &rusqlite::Transaction) -> Result<()> {
-    if ids.is_empty() {
-        return Ok(());
-    }
-    let placeholders: Vec<String> = (0..ids.len()).map(|i| format!("?{}", i + 1)).collect();
-    let query = format!(
-        "DELETE FROM artists_fts WHERE artist_id IN ({})",
-        placeholders.join(", ")
-    );
-    let mut statement = tx.prepare(&query)?;
-    let params: Vec<rusqlite::types::Value> = ids.iter().map(|&id| id.into()).collect();
-    statement.execute(rusqlite::params_from_iter(params.iter()))?;
+    delete_by_ids("artists_fts", "artist_id", ids, tx)?;
     Ok(())
 }
 
 /// Get all tracks with their fingerprint data for comparison
 pub fn get_tracks_with_fingerprints(db: &Connection) -> Result<Vec<DbTrack>> {
     let mut statement =
-        db.prepare("SELECT id, file_path, file_size, modified_time, content_hash FROM tracks")?;
+        db.prepare("SELECT id, file_path, file_size, modified_time, content_hash, start_offset FROM tracks")?;
     let mut rows = statement.query([])?;
     let mut tracks = Vec::new();
 
@@ -1260,6 +1480,7 @@ pub fn get_tracks_with_fingerprints(db: &Connection) -> Result<Vec<DbTrack>> {
             file_size: row.get("file_size")?,
             modified_time: row.get("modified_time")?,
             content_hash: row.get("content_hash")?,
+            start_offset: row.get("start_offset")?,
         });
     }
 
@@ -1268,35 +1489,35 @@ pub fn get_tracks_with_fingerprints(db: &Connection) -> Result<Vec<DbTrack>> {
 
 /// Delete tracks by their IDs (batch operation)
 pub fn delete_tracks_by_ids(ids: &[i64], conn: &Connection) -> Result<()> {
-    if ids.is_empty() {
-        return Ok(());
-    }
+    delete_by_ids("tracks_fts", "track_id", ids, conn)?;
+    delete_by_ids("track_artists", "track_id", ids, conn)?;
+    delete_by_ids("tracks", "id", ids, conn)?;
 
-    // Delete from FTS index first
-    {
-        let placeholders: Vec<String> = (0..ids.len()).map(|i| format!("?{}", i + 1)).collect();
-        let query = format!(
-            "DELETE FROM tracks_fts WHERE track_id IN ({})",
-            placeholders.join(", ")
-        );
-        let mut statement = conn.prepare(&query)?;
-        let params: Vec<rusqlite::types::Value> = ids.iter().map(|&id| id.into()).collect();
-        statement.execute(rusqlite::params_from_iter(params.iter()))?;
-    }
+    Ok(())
+}
 
-    // Delete tracks
-    {
-        let placeholders: Vec<String> = (0..ids.len()).map(|i| format!("?{}", i + 1)).collect();
+/// Most IDs bound in one `IN (...)` list, well below SQLite's limit of variables per
+/// statement (`SQLITE_MAX_VARIABLE_NUMBER`, only 999 in older builds)
+const MAX_IDS_PER_STATEMENT: usize = 500;
+
+/// `DELETE FROM table WHERE column IN (ids)`, in chunks so any number of IDs can be deleted.
+/// Returns the number of deleted rows.
+fn delete_by_ids(table: &str, column: &str, ids: &[i64], db: &Connection) -> Result<usize> {
+    let mut deleted_count = 0;
+
+    for chunk in ids.chunks(MAX_IDS_PER_STATEMENT) {
+        let placeholders: Vec<String> = (0..chunk.len()).map(|i| format!("?{}", i + 1)).collect();
         let query = format!(
-            "DELETE FROM tracks WHERE id IN ({})",
+            "DELETE FROM {} WHERE {} IN ({})",
+            table,
+            column,
             placeholders.join(", ")
         );
-        let mut statement = conn.prepare(&query)?;
-        let params: Vec<rusqlite::types::Value> = ids.iter().map(|&id| id.into()).collect();
-        statement.execute(rusqlite::params_from_iter(params.iter()))?;
+        let mut statement = db.prepare_cached(&query)?;
+        deleted_count += statement.execute(rusqlite::params_from_iter(chunk.iter()))?;
     }
 
-    Ok(())
+    Ok(deleted_count)
 }
 
 /// Update a track's file path (for move/rename detection)
@@ -1325,6 +1546,9 @@ pub fn update_track_path(
 
 const SCAN_STATUS_PENDING: i32 = 0;
 const SCAN_STATUS_PROCESSED: i32 = 1;
+/// Track below a library directory that was unavailable (e.g. an unmounted drive) during
+/// the last scan. It is kept as is until the directory is scanned again.
+const SCAN_STATUS_OFFLINE: i32 = 2;
 
 /// Track info for scan operations
 #[derive(Debug)]
@@ -1333,40 +1557,67 @@ pub struct ScanTrackInfo {
     pub file_path: String,
 }
 
-/// Find track by fingerprint (mtime + size) - for scan operations
-pub fn find_track_by_fingerprint_tx(
+/// Find tracks by fingerprint (mtime + size) - for scan operations.
+/// Returns one track per file, as copies of a file can share a fingerprint.
+pub fn find_tracks_by_fingerprint(
     modified_time: i64,
     file_size: i64,
-    tx: &rusqlite::Transaction,
-) -> Result<Option<ScanTrackInfo>> {
-    let mut stmt = tx.prepare(
-        "SELECT id, file_path FROM tracks WHERE modified_time = ? AND file_size = ? LIMIT 1",
-    )?;
+    db: &Connection,
+) -> Result<Vec<ScanTrackInfo>> {
+    let mut stmt = db.prepare(indoc! {"
+      SELECT MIN(id) AS id, file_path FROM tracks
+      WHERE modified_time = ? AND file_size = ? AND scan_status != ?
+      GROUP BY file_path
+      ORDER BY id
+    "})?;
 
-    let result = stmt
-        .query_row([modified_time, file_size], |row| {
-            Ok(ScanTrackInfo {
-                id: row.get(0)?,
-                file_path: row.get(1)?,
-            })
+    let rows = stmt.query_map(params![modified_time, file_size, SCAN_STATUS_OFFLINE], |row| {
+        Ok(ScanTrackInfo {
+            id: row.get(0)?,
+            file_path: row.get(1)?,
         })
-        .optional()?;
+    })?;
 
-    Ok(result)
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
 }
 
-/// Find track by content hash - for scan operations
-pub fn find_track_by_hash_tx(
-    hash: &str,
-    tx: &rusqlite::Transaction,
-) -> Result<Option<ScanTrackInfo>> {
-    let mut stmt = tx.prepare("SELECT id, file_path FROM tracks WHERE content_hash = ? LIMIT 1")?;
+/// Find tracks by content hash - for scan operations.
+/// Returns one track per file, as copies of a file share their hash.
+pub fn find_tracks_by_hash(hash: &str, db: &Connection) -> Result<Vec<ScanTrackInfo>> {
+    let mut stmt = db.prepare(indoc! {"
+      SELECT MIN(id) AS id, file_path FROM tracks
+      WHERE content_hash = ? AND scan_status != ?
+      GROUP BY file_path
+      ORDER BY id
+    "})?;
+
+    let rows = stmt.query_map(params![hash, SCAN_STATUS_OFFLINE], |row| {
+        Ok(ScanTrackInfo {
+            id: row.get(0)?,
+            file_path: row.get(1)?,
+        })
+    })?;
+
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Find a track by its file path - for detecting in-place modifications during scan.
+/// Virtual tracks of a CUE sheet share their file's path and fingerprint; the first one
+/// (inserted first) is returned, as its salted hash is the one the scanner can recompute.
+pub fn find_track_by_path(path: &str, db: &Connection) -> Result<Option<DbTrack>> {
+    let mut stmt = db.prepare(
+        "SELECT id, file_path, file_size, modified_time, content_hash, start_offset FROM tracks WHERE file_path = ? ORDER BY id LIMIT 1",
+    )?;
 
     let result = stmt
-        .query_row([hash], |row| {
-            Ok(ScanTrackInfo {
-                id: row.get(0)?,
-                file_path: row.get(1)?,
+        .query_row([path], |row| {
+            Ok(DbTrack {
+                id: row.get("id")?,
+                file_path: row.get("file_path")?,
+                file_size: row.get("file_size")?,
+                modified_time: row.get("modified_time")?,
+                content_hash: row.get("content_hash")?,
+                start_offset: row.get("start_offset")?,
             })
         })
         .optional()?;
@@ -1374,18 +1625,18 @@ pub fn find_track_by_hash_tx(
     Ok(result)
 }
 
-/// Mark track as processed during scan
-pub fn mark_track_processed_tx(track_id: i64, tx: &rusqlite::Transaction) -> Result<()> {
+/// Mark all tracks of a file as processed during scan
+pub fn mark_file_tracks_processed_tx(file_path: &str, tx: &rusqlite::Transaction) -> Result<()> {
     tx.execute(
-        "UPDATE tracks SET scan_status = ? WHERE id = ?",
-        [SCAN_STATUS_PROCESSED, track_id as i32],
+        "UPDATE tracks SET scan_status = ? WHERE file_path = ?",
+        (SCAN_STATUS_PROCESSED, file_path),
     )?;
     Ok(())
 }
 
-/// Update track path after move (fingerprint already matches)
+/// Update the path of all tracks of a file after a move (fingerprint already matches)
 pub fn update_track_path_tx(
-    track_id: i64,
+    old_path: &str,
     new_path: &str,
     tx: &rusqlite::Transaction,
 ) -> Result<()> {
@@ -1395,20 +1646,21 @@ pub fn update_track_path_tx(
         .unwrap_or_default();
 
     tx.execute(
-        "UPDATE tracks SET file_path = ?, file_name = ?, scan_status = ? WHERE id = ?",
-        (new_path, file_name, SCAN_STATUS_PROCESSED, track_id),
+        "UPDATE tracks SET file_path = ?, file_name = ?, scan_status = ? WHERE file_path = ?",
+        (new_path, file_name, SCAN_STATUS_PROCESSED, old_path),
     )?;
+    update_album_image_source_path_tx(old_path, new_path, tx)?;
 
     Ok(())
 }
 
-/// Update track path and fingerprint after move
+/// Update the path and fingerprint of all tracks of a file after a move. The content hash
+/// matched, so it is kept (virtual tracks of a CUE sheet each have their own).
 pub fn update_track_path_and_fingerprint_tx(
-    track_id: i64,
+    old_path: &str,
     new_path: &str,
     file_size: i64,
     modified_time: i64,
-    content_hash: &str,
     tx: &rusqlite::Transaction,
 ) -> Result<()> {
     let file_name = std::path::Path::new(new_path)
@@ -1417,9 +1669,61 @@ pub fn update_track_path_and_fingerprint_tx(
         .unwrap_or_default();
 
     tx.execute(
-        "UPDATE tracks SET file_path = ?, file_name = ?, file_size = ?, modified_time = ?, content_hash = ?, scan_status = ? WHERE id = ?",
-        (new_path, file_name, file_size, modified_time, content_hash, SCAN_STATUS_PROCESSED, track_id),
+        "UPDATE tracks SET file_path = ?, file_name = ?, file_size = ?, modified_time = ?, scan_status = ? WHERE file_path = ?",
+        (new_path, file_name, file_size, modified_time, SCAN_STATUS_PROCESSED, old_path),
+    )?;
+    update_album_image_source_path_tx(old_path, new_path, tx)?;
+
+    Ok(())
+}
+
+/// Keep the artwork source of albums pointing at a moved track
+fn update_album_image_source_path_tx(
+    old_path: &str,
+    new_path: &str,
+    tx: &rusqlite::Transaction,
+) -> Result<()> {
+    tx.execute(
+        "UPDATE albums SET image_source_path = ? WHERE image_source_path = ?",
+        (new_path, old_path),
+    )?;
+    Ok(())
+}
+
+/// Make albums whose artwork came from a modified track look it up again on the next
+/// artwork pass. The cached thumbnail is kept until it is replaced.
+pub fn reset_album_artwork_by_source_tx(
+    source_path: &str,
+    tx: &rusqlite::Transaction,
+) -> Result<()> {
+    tx.execute(
+        "UPDATE albums SET image_source_hash = NULL WHERE image_source_path = ?",
+        [source_path],
+    )?;
+    Ok(())
+}
+
+/// Delete all tracks of a file so they can be re-inserted, e.g. when its CUE sheet changed.
+/// Their lyricsfiles are detached rather than deleted, so re-inserted tracks with matching
+/// metadata pick them up again via `find_orphaned_lyricsfile_tx`.
+pub fn delete_tracks_by_file_path_tx(file_path: &str, tx: &rusqlite::Transaction) -> Result<()> {
+    let track_ids: Vec<i64> = {
+        let mut stmt = tx.prepare("SELECT id FROM tracks WHERE file_path = ?")?;
+        let rows = stmt.query_map([file_path], |r| r.get(0))?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+
+    delete_tracks_fts_by_ids_tx(&track_ids, tx)?;
+
+    tx.execute(
+        "UPDATE lyricsfiles SET track_id = NULL WHERE track_id IN (SELECT id FROM tracks WHERE file_path = ?)",
+        [file_path],
+    )?;
+    tx.execute(
+        "DELETE FROM track_artists WHERE track_id IN (SELECT id FROM tracks WHERE file_path = ?)",
+        [file_path],
     )?;
+    tx.execute("DELETE FROM tracks WHERE file_path = ?", [file_path])?;
 
     Ok(())
 }
@@ -1439,7 +1743,7 @@ pub fn insert_track_from_metadata_tx(
     use crate::utils::prepare_input;
 
     tx.execute(
-        "INSERT INTO tracks (file_path, file_name, title, title_lower, album_id, artist_id, duration, track_number, file_size, modified_time, content_hash, scan_status) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        "INSERT INTO tracks (file_path, file_name, title, title_lower, album_id, artist_id, artist_credit, duration, track_number, metadata_inferred, start_offset, disc_number, year, genre, composer, musicbrainz_recording_id, isrc, file_size, modified_time, content_hash, scan_status) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
         params![
             &metadata.file_path,
             &metadata.file_name,
@@ -1447,8 +1751,17 @@ pub fn insert_track_from_metadata_tx(
             prepare_input(&metadata.title),
             album_id,
             artist_id,
+            &metadata.artist,
             metadata.duration,
             metadata.track_number,
+            metadata.metadata_inferred,
+            metadata.start_offset,
+            metadata.extended.disc_number,
+            metadata.extended.year,
+            &metadata.extended.genre,
+            &metadata.extended.composer,
+            &metadata.extended.musicbrainz_recording_id,
+            &metadata.extended.isrc,
             file_size,
             modified_time,
             content_hash,
@@ -1459,9 +1772,125 @@ pub fn insert_track_from_metadata_tx(
     Ok(tx.last_insert_rowid())
 }
 
+/// Update a track whose file was modified in place (re-read tags and new fingerprint)
+pub fn update_track_from_metadata_tx(
+    track_id: i64,
+    metadata: &crate::scanner::metadata::TrackMetadata,
+    file_size: i64,
+    modified_time: i64,
+    content_hash: &str,
+    artist_id: i64,
+    album_id: i64,
+    tx: &rusqlite::Transaction,
+) -> Result<()> {
+    tx.execute(
+        indoc! {"
+          UPDATE tracks
+          SET
+            title = ?,
+            title_lower = ?,
+            album_id = ?,
+            artist_id = ?,
+            artist_credit = ?,
+            duration = ?,
+            track_number = ?,
+            metadata_inferred = ?,
+            disc_number = ?,
+            year = ?,
+            genre = ?,
+            composer = ?,
+            musicbrainz_recording_id = ?,
+            isrc = ?,
+            file_size = ?,
+            modified_time = ?,
+            content_hash = ?,
+            scan_status = ?
+          WHERE id = ?
+        "},
+        params![
+            &metadata.title,
+            prepare_input(&metadata.title),
+            album_id,
+            artist_id,
+            &metadata.artist,
+            metadata.duration,
+            metadata.track_number,
+            metadata.metadata_inferred,
+            metadata.extended.disc_number,
+            metadata.extended.year,
+            &metadata.extended.genre,
+            &metadata.extended.composer,
+            &metadata.extended.musicbrainz_recording_id,
+            &metadata.extended.isrc,
+            file_size,
+            modified_time,
+            content_hash,
+            SCAN_STATUS_PROCESSED,
+            track_id,
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Replace the credited artists of a track, primary artist first
+pub fn set_track_artists_tx(
+    track_id: i64,
+    artist_ids: &[i64],
+    tx: &rusqlite::Transaction,
+) -> Result<()> {
+    tx.execute("DELETE FROM track_artists WHERE track_id = ?", [track_id])?;
+
+    let mut statement =
+        tx.prepare("INSERT INTO track_artists (track_id, artist_id, position) VALUES (?, ?, ?)")?;
+    for (position, artist_id) in artist_ids.iter().enumerate() {
+        statement.execute((track_id, artist_id, position as i64))?;
+    }
+
+    Ok(())
+}
+
+/// Refresh the denormalized track metadata of a track's lyricsfile (content is left untouched)
+pub fn update_lyricsfile_track_metadata_tx(
+    track_id: i64,
+    track_title: &str,
+    track_album_name: &str,
+    track_artist_name: &str,
+    track_duration: f64,
+    tx: &rusqlite::Transaction,
+) -> Result<()> {
+    tx.execute(
+        indoc! {"
+          UPDATE lyricsfiles
+          SET
+            track_title = ?,
+            track_title_lower = ?,
+            track_album_name = ?,
+            track_album_name_lower = ?,
+            track_artist_name = ?,
+            track_artist_name_lower = ?,
+            track_duration = ?,
+            updated_at = CURRENT_TIMESTAMP
+          WHERE track_id = ?
+        "},
+        params![
+            track_title,
+            prepare_input(track_title),
+            track_album_name,
+            prepare_input(track_album_name),
+            track_artist_name,
+            prepare_input(track_artist_name),
+            track_duration,
+            track_id,
+        ],
+    )?;
+
+    Ok(())
+}
+
 /// Delete tracks that weren't processed during scan and clean up orphaned albums/artists
 pub fn delete_unprocessed_tracks(conn: &mut Connection) -> Result<usize> {
-    let tx = conn.transaction()?;
+    let tx = write_transaction(conn)?;
 
     // Collect IDs of tracks that will be deleted for FTS cleanup
     let pending_track_ids: Vec<i64> = {
@@ -1481,6 +1910,57 @@ pub fn delete_unprocessed_tracks(conn: &mut Connection) -> Result<usize> {
         [SCAN_STATUS_PENDING],
     )?;
 
+    delete_orphaned_albums_and_artists_tx(&tx)?;
+
+    tx.commit()?;
+
+    Ok(deleted_count)
+}
+
+/// Delete tracks at the given paths, or below them when a path is a directory.
+/// Used by the filesystem watcher for removed files and folders.
+pub fn delete_tracks_by_paths(paths: &[String], conn: &mut Connection) -> Result<usize> {
+    let tx = write_transaction(conn)?;
+
+    let mut track_ids: Vec<i64> = Vec::new();
+    {
+        let mut stmt = tx.prepare(indoc! {"
+          SELECT id FROM tracks
+          WHERE file_path = ?1 OR substr(file_path, 1, length(?2)) = ?2
+        "})?;
+
+        for path in paths {
+            let dir_prefix = format!("{}{}", path, std::path::MAIN_SEPARATOR);
+            let rows = stmt.query_map(params![path, dir_prefix], |r| r.get(0))?;
+            for id in rows {
+                track_ids.push(id?);
+            }
+        }
+    }
+
+    if track_ids.is_empty() {
+        return Ok(0);
+    }
+
+    delete_tracks_fts_by_ids_tx(&track_ids, &tx)?;
+
+    let deleted_count = delete_by_ids("tracks", "id", &track_ids, &tx)?;
+
+    delete_orphaned_albums_and_artists_tx(&tx)?;
+
+    tx.commit()?;
+
+    Ok(deleted_count)
+}
+
+/// Delete albums and artists that no longer have any tracks, keeping FTS indexes in sync
+fn delete_orphaned_albums_and_artists_tx(tx: &rusqlite::Transaction) -> Result<()> {
+    // Drop the artist credits of deleted tracks; artists only credited elsewhere are kept
+    tx.execute(
+        "DELETE FROM track_artists WHERE track_id NOT IN (SELECT id FROM tracks)",
+        [],
+    )?;
+
     // Collect orphaned album/artist IDs before deleting them
     let orphaned_album_ids: Vec<i64> = {
         let mut stmt = tx.prepare("SELECT id FROM albums WHERE id NOT IN (SELECT DISTINCT album_id FROM tracks)")?;
@@ -1489,7 +1969,7 @@ pub fn delete_unprocessed_tracks(conn: &mut Connection) -> Result<usize> {
     };
 
     let orphaned_artist_ids: Vec<i64> = {
-        let mut stmt = tx.prepare("SELECT id FROM artists WHERE id NOT IN (SELECT DISTINCT artist_id FROM tracks)")?;
+        let mut stmt = tx.prepare("SELECT id FROM artists WHERE id NOT IN (SELECT DISTINCT artist_id FROM track_artists)")?;
         let rows = stmt.query_map([], |r| r.get(0))?;
         rows.collect::<Result<Vec<_>, _>>()?
     };
@@ -1501,21 +1981,19 @@ pub fn delete_unprocessed_tracks(conn: &mut Connection) -> Result<usize> {
     )?;
 
     tx.execute(
-        "DELETE FROM artists WHERE id NOT IN (SELECT DISTINCT artist_id FROM tracks)",
+        "DELETE FROM artists WHERE id NOT IN (SELECT DISTINCT artist_id FROM track_artists)",
         [],
     )?;
 
     // Sync FTS indexes
     if !orphaned_album_ids.is_empty() {
-        delete_albums_fts_by_ids_tx(&orphaned_album_ids, &tx)?;
+        delete_albums_fts_by_ids_tx(&orphaned_album_ids, tx)?;
     }
     if !orphaned_artist_ids.is_empty() {
-        delete_artists_fts_by_ids_tx(&orphaned_artist_ids, &tx)?;
+        delete_artists_fts_by_ids_tx(&orphaned_artist_ids, tx)?;
     }
 
-    tx.commit()?;
-
-    Ok(deleted_count)
+    Ok(())
 }
 
 /// Mark all tracks as pending before scan
@@ -1524,6 +2002,61 @@ pub fn mark_all_tracks_pending(conn: &mut Connection) -> Result<()> {
     Ok(())
 }
 
+/// Mark the tracks at the given paths, or below them when a path is a directory, as pending
+pub fn mark_tracks_pending_by_paths(paths: &[String], conn: &mut Connection) -> Result<()> {
+    set_scan_status_by_paths(SCAN_STATUS_PENDING, paths, conn)?;
+    Ok(())
+}
+
+/// Mark the tracks below library directories that are unavailable as offline, so they are
+/// kept instead of being deleted with the tracks of missing files
+pub fn mark_tracks_offline_by_paths(paths: &[String], conn: &mut Connection) -> Result<usize> {
+    set_scan_status_by_paths(SCAN_STATUS_OFFLINE, paths, conn)
+}
+
+/// IDs of the offline tracks, whose files cannot be read or written until their library
+/// directory is available again
+pub fn get_offline_track_ids(conn: &Connection) -> Result<Vec<i64>> {
+    let mut statement = conn.prepare("SELECT id FROM tracks WHERE scan_status = ?")?;
+    let track_ids = statement
+        .query_map([SCAN_STATUS_OFFLINE], |row| row.get(0))?
+        .collect::<Result<Vec<i64>, _>>()?;
+
+    Ok(track_ids)
+}
+
+fn set_scan_status_by_paths(
+    scan_status: i32,
+    paths: &[String],
+    conn: &mut Connection,
+) -> Result<usize> {
+    let tx = write_transaction(conn)?;
+    let mut updated_count = 0;
+    {
+        let mut statement = tx.prepare(indoc! {"
+          UPDATE tracks SET scan_status = ?1
+          WHERE file_path = ?2 OR substr(file_path, 1, length(?3)) = ?3
+        "})?;
+
+        for path in paths {
+            let dir_prefix = format!("{}{}", path, std::path::MAIN_SEPARATOR);
+            updated_count += statement.execute(params![scan_status, path, dir_prefix])?;
+        }
+    }
+    tx.commit()?;
+
+    Ok(updated_count)
+}
+
+/// Mark tracks a cancelled scan left pending as processed again, so they are kept
+pub fn restore_pending_tracks(conn: &mut Connection) -> Result<()> {
+    conn.execute(
+        "UPDATE tracks SET scan_status = ? WHERE scan_status = ?",
+        [SCAN_STATUS_PROCESSED, SCAN_STATUS_PENDING],
+    )?;
+    Ok(())
+}
+
 // Transaction-based versions of artist/album functions for scan operations
 
 /// Find artist by name (transaction version)
@@ -1553,6 +2086,99 @@ pub fn find_album_tx(
     Ok(id)
 }
 
+/// An album whose artwork has to be looked up or re-checked
+#[derive(Debug)]
+pub struct AlbumArtworkCandidate {
+    pub album_id: i64,
+    /// A track of the album to read embedded artwork from (or find a cover image next to)
+    pub track_path: String,
+    pub image_source_path: Option<String>,
+    pub image_source_hash: Option<String>,
+    pub image_source_size: Option<i64>,
+    pub image_source_modified_time: Option<i64>,
+}
+
+/// Get albums that still need artwork, plus albums whose artwork source is not one of
+/// their tracks (a cover image, or a track that is gone) and must be re-checked
+pub fn get_album_artwork_candidates(db: &Connection) -> Result<Vec<AlbumArtworkCandidate>> {
+    let mut statement = db.prepare(indoc! {"
+      SELECT
+        albums.id,
+        MIN(tracks.file_path) AS track_path,
+        albums.image_source_path,
+        albums.image_source_hash,
+        albums.image_source_size,
+        albums.image_source_modified_time
+      FROM albums
+      JOIN tracks ON tracks.album_id = albums.id
+      WHERE albums.image_source_hash IS NULL
+        OR NOT EXISTS (
+          SELECT 1 FROM tracks AS source_tracks
+          WHERE source_tracks.file_path = albums.image_source_path
+        )
+      GROUP BY albums.id
+    "})?;
+    let rows = statement.query_map([], |row| {
+        Ok(AlbumArtworkCandidate {
+            album_id: row.get("id")?,
+            track_path: row.get("track_path")?,
+            image_source_path: row.get("image_source_path")?,
+            image_source_hash: row.get("image_source_hash")?,
+            image_source_size: row.get("image_source_size")?,
+            image_source_modified_time: row.get("image_source_modified_time")?,
+        })
+    })?;
+
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Store the cached artwork of an album (None if it has none) and the file it came from,
+/// with the file's quick hash, size and mtime
+pub fn set_album_artwork_tx(
+    album_id: i64,
+    image_path: Option<&str>,
+    source_path: &str,
+    source_hash: &str,
+    source_size: i64,
+    source_modified_time: i64,
+    tx: &rusqlite::Transaction,
+) -> Result<()> {
+    tx.execute(
+        "UPDATE albums SET image_path = ?, image_source_path = ?, image_source_hash = ?, image_source_size = ?, image_source_modified_time = ? WHERE id = ?",
+        params![
+            image_path,
+            source_path,
+            source_hash,
+            source_size,
+            source_modified_time,
+            album_id
+        ],
+    )?;
+    Ok(())
+}
+
+/// Store the size and mtime of an album's artwork source whose content did not change
+pub fn set_album_artwork_source_fingerprint_tx(
+    album_id: i64,
+    source_size: i64,
+    source_modified_time: i64,
+    tx: &rusqlite::Transaction,
+) -> Result<()> {
+    tx.execute(
+        "UPDATE albums SET image_source_size = ?, image_source_modified_time = ? WHERE id = ?",
+        (source_size, source_modified_time, album_id),
+    )?;
+    Ok(())
+}
+
+/// Get the cached artwork paths still referenced by albums
+pub fn get_album_image_paths(db: &Connection) -> Result<Vec<String>> {
+    let mut statement = db.prepare("SELECT image_path FROM albums WHERE image_path IS NOT NULL")?;
+    let rows = statement.query_map([], |row| row.get(0))?;
+
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
 /// Add new album (transaction version)
 pub fn add_album_tx(
     name: &str,
@@ -1570,6 +2196,33 @@ pub fn add_album_tx(
     Ok(row_id)
 }
 
+/// Store the album-level tags read from one of the album's tracks. The last written track
+/// wins, so a tag removed from the files is cleared on the album as well.
+pub fn update_album_tags_tx(
+    album_id: i64,
+    extended: &crate::scanner::metadata::ExtendedTags,
+    tx: &rusqlite::Transaction,
+) -> Result<()> {
+    tx.execute(
+        indoc! {"
+          UPDATE albums
+          SET
+            year = ?,
+            album_artist_sort_name = ?,
+            musicbrainz_release_id = ?
+          WHERE id = ?
+        "},
+        params![
+            extended.year,
+            &extended.album_artist_sort,
+            &extended.musicbrainz_release_id,
+            album_id,
+        ],
+    )?;
+
+    Ok(())
+}
+
 /// Get track IDs that have lyrics (for mass export)
 pub fn get_track_ids_with_lyrics(db: &Connection) -> Result<Vec<i64>> {
     let mut statement = db.prepare(indoc! {"
@@ -1578,7 +2231,7 @@ pub fn get_track_ids_with_lyrics(db: &Connection) -> Result<Vec<i64>> {
       JOIN lyricsfiles ON lyricsfiles.track_id = tracks.id
       WHERE lyricsfiles.has_plain_lyrics = 1
          OR lyricsfiles.has_synced_lyrics = 1
-      ORDER BY tracks.artist_id ASC, tracks.album_id ASC, tracks.track_number ASC NULLS LAST
+      ORDER BY tracks.artist_id ASC, tracks.album_id ASC, COALESCE(tracks.disc_number, 1) ASC, tracks.track_number ASC NULLS LAST
     "})?;
 
     let mut rows = statement.query([])?;
@@ -1591,6 +2244,168 @@ pub fn get_track_ids_with_lyrics(db: &Connection) -> Result<Vec<i64>> {
     Ok(track_ids)
 }
 
+/// Track IDs grouped by content hash, for every hash shared by more than one file.
+/// Virtual tracks of a CUE sheet have hashes salted with their index and start, so copies
+/// of a split file are grouped track by track.
+pub fn get_track_ids_by_shared_hash(db: &Connection) -> Result<Vec<Vec<i64>>> {
+    let mut statement = db.prepare(indoc! {"
+      SELECT id, content_hash
+      FROM tracks
+      WHERE content_hash IN (
+        SELECT content_hash
+        FROM tracks
+        WHERE content_hash IS NOT NULL
+        GROUP BY content_hash
+        HAVING COUNT(DISTINCT file_path) > 1
+      )
+      ORDER BY content_hash, id
+    "})?;
+
+    let mut rows = statement.query([])?;
+    let mut groups: Vec<(String, Vec<i64>)> = Vec::new();
+
+    while let Some(row) = rows.next()? {
+        let id: i64 = row.get("id")?;
+        let content_hash: String = row.get("content_hash")?;
+
+        match groups.last_mut() {
+            Some((hash, track_ids)) if *hash == content_hash => track_ids.push(id),
+            _ => groups.push((content_hash, vec![id])),
+        }
+    }
+
+    Ok(groups.into_iter().map(|(_, track_ids)| track_ids).collect())
+}
+
+/// Track IDs and durations grouped by normalized title and artist name, for every
+/// title and artist shared by more than one track. Tracks are ordered by duration.
+pub fn get_tracks_by_shared_title_and_artist(db: &Connection) -> Result<Vec<Vec<(i64, f64)>>> {
+    let mut statement = db.prepare(indoc! {"
+      SELECT tracks.id, tracks.title_lower, artists.name_lower AS artist_name_lower, tracks.duration
+      FROM tracks
+      JOIN artists ON tracks.artist_id = artists.id
+      JOIN (
+        SELECT tracks.title_lower, artists.name_lower
+        FROM tracks
+        JOIN artists ON tracks.artist_id = artists.id
+        GROUP BY tracks.title_lower, artists.name_lower
+        HAVING COUNT(*) > 1
+      ) AS shared
+        ON shared.title_lower = tracks.title_lower AND shared.name_lower = artists.name_lower
+      ORDER BY tracks.title_lower, artists.name_lower, tracks.duration, tracks.id
+    "})?;
+
+    let mut rows = statement.query([])?;
+    let mut groups: Vec<Vec<(i64, f64)>> = Vec::new();
+    let mut group_key: Option<(String, String)> = None;
+
+    while let Some(row) = rows.next()? {
+        let key: (String, String) = (row.get("title_lower")?, row.get("artist_name_lower")?);
+        let track = (row.get("id")?, row.get("duration")?);
+
+        match groups.last_mut() {
+            Some(tracks) if group_key.as_ref() == Some(&key) => tracks.push(track),
+            _ => {
+                groups.push(vec![track]);
+                group_key = Some(key);
+            }
+        }
+    }
+
+    Ok(groups)
+}
+
+/// IDs of tracks whose title/album/artist were (partly) inferred from their file path
+pub fn get_metadata_inferred_track_ids(db: &Connection) -> Result<Vec<i64>> {
+    let mut statement = db.prepare(indoc! {"
+      SELECT id
+      FROM tracks
+      WHERE metadata_inferred = 1
+      ORDER BY file_path ASC
+    "})?;
+
+    let mut rows = statement.query([])?;
+    let mut track_ids: Vec<i64> = Vec::new();
+
+    while let Some(row) = rows.next()? {
+        track_ids.push(row.get("id")?);
+    }
+
+    Ok(track_ids)
+}
+
+/// Record a scan error; scans pass their batch transaction
+pub fn insert_scan_error(scan_error: &ScanError, db: &Connection) -> Result<()> {
+    let mut statement = db.prepare(indoc! {"
+      INSERT INTO scan_errors (file_path, error_kind, message)
+      VALUES (?, ?, ?)
+    "})?;
+    statement.execute((
+        &scan_error.file_path,
+        scan_error.kind.as_str(),
+        &scan_error.message,
+    ))?;
+    Ok(())
+}
+
+/// Errors recorded by scans, optionally filtered by kind and a file path/message substring
+pub fn get_scan_errors(
+    error_kind: Option<&str>,
+    search_query: Option<&str>,
+    db: &Connection,
+) -> Result<Vec<PersistentScanError>> {
+    let search_pattern = search_query
+        .filter(|query| !query.trim().is_empty())
+        .map(|query| format!("%{}%", query.trim()));
+
+    let mut statement = db.prepare(indoc! {"
+      SELECT id, file_path, error_kind, message, created_at
+      FROM scan_errors
+      WHERE (?1 IS NULL OR error_kind = ?1)
+        AND (?2 IS NULL OR file_path LIKE ?2 OR message LIKE ?2)
+      ORDER BY file_path ASC, id ASC
+    "})?;
+
+    let mut rows = statement.query(params![error_kind, search_pattern])?;
+    let mut scan_errors: Vec<PersistentScanError> = Vec::new();
+
+    while let Some(row) = rows.next()? {
+        scan_errors.push(PersistentScanError {
+            id: row.get("id")?,
+            file_path: row.get("file_path")?,
+            error_kind: row.get("error_kind")?,
+            message: row.get("message")?,
+            created_at: row.get("created_at")?,
+        });
+    }
+
+    Ok(scan_errors)
+}
+
+/// Delete recorded scan errors of one kind, or all of them when `error_kind` is None
+pub fn clear_scan_errors(error_kind: Option<&str>, db: &Connection) -> Result<usize> {
+    let deleted_count = db.execute(
+        "DELETE FROM scan_errors WHERE ?1 IS NULL OR error_kind = ?1",
+        params![error_kind],
+    )?;
+    Ok(deleted_count)
+}
+
+/// Delete scan errors recorded for the given paths or any file below them
+pub fn delete_scan_errors_by_paths(paths: &[String], db: &Connection) -> Result<()> {
+    let mut statement = db.prepare(indoc! {"
+      DELETE FROM scan_errors
+      WHERE file_path = ?1 OR substr(file_path, 1, length(?2)) = ?2
+    "})?;
+
+    for path in paths {
+        let dir_prefix = format!("{}{}", path, std::path::MAIN_SEPARATOR);
+        statement.execute(params![path, dir_prefix])?;
+    }
+
+    Ok(())
+}
+
 /// Find orphaned lyricsfiles by track metadata (for reattachment during scan)
 /// Returns the lyricsfile_id if found, None otherwise
 /// Matches on normalized title, artist, album, and duration within ±2 seconds
@@ -1688,6 +2503,19 @@ pub fn find_tracks_by_metadata(
             tracks.album_id,
             tracks.duration,
             tracks.track_number,
+            tracks.metadata_inferred,
+            tracks.start_offset,
+            tracks.scan_status,
+            tracks.disc_number,
+            tracks.year,
+            tracks.genre,
+            tracks.composer,
+            tracks.musicbrainz_recording_id,
+            tracks.isrc,
+            tracks.artist_credit,
+            tracks.lrclib_not_found_at,
+            tracks.lrclib_match_score,
+            tracks.lrclib_match_reason,
             albums.image_path,
             lyricsfiles.id AS lyricsfile_id,
             lyricsfiles.lyricsfile,
@@ -1731,6 +2559,19 @@ pub fn find_tracks_by_metadata(
             lyricsfile_id: row.get("lyricsfile_id")?,
             image_path: row.get("image_path")?,
             instrumental: is_instrumental,
+            metadata_inferred: row.get("metadata_inferred")?,
+            start_offset: row.get("start_offset")?,
+            offline: row.get::<_, i32>("scan_status")? == SCAN_STATUS_OFFLINE,
+            disc_number: row.get("disc_number")?,
+            year: row.get("year")?,
+            genre: row.get("genre")?,
+            composer: row.get("composer")?,
+            musicbrainz_recording_id: row.get("musicbrainz_recording_id")?,
+            isrc: row.get("isrc")?,
+            artist_credit: row.get("artist_credit")?,
+            lrclib_not_found_at: row.get("lrclib_not_found_at")?,
+            lrclib_match_score: row.get("lrclib_match_score")?,
+            lrclib_match_reason: row.get("lrclib_match_reason")?,
         };
 
         tracks.push(track);
@@ -1738,3 +2579,274 @@ pub fn find_tracks_by_metadata(
 
     Ok(tracks)
 }
+
+// ============================================================================
+// Mass download jobs
+// ============================================================================
+
+pub const DOWNLOAD_STATUS_PENDING: i32 = 0;
+pub const DOWNLOAD_STATUS_DOWNLOADED: i32 = 1;
+pub const DOWNLOAD_STATUS_FAILED: i32 = 2;
+
+/// The unfinished mass download job with its track counts, if there is one
+pub fn get_download_job(db: &Connection) -> Result<Option<PersistentDownloadJob>> {
+    let mut statement = db.prepare(indoc! {"
+      SELECT
+        download_jobs.id,
+        download_jobs.state,
+        download_jobs.concurrency,
+        download_jobs.bypass_cache,
+        COUNT(download_job_tracks.id) AS total,
+        COALESCE(SUM(download_job_tracks.status = ?1), 0) AS downloaded,
+        COALESCE(SUM(download_job_tracks.status = ?2), 0) AS failed
+      FROM download_jobs
+      LEFT JOIN download_job_tracks ON download_job_tracks.job_id = download_jobs.id
+      GROUP BY download_jobs.id
+      ORDER BY download_jobs.id ASC
+      LIMIT 1
+    "})?;
+
+    let job = statement
+        .query_row([DOWNLOAD_STATUS_DOWNLOADED, DOWNLOAD_STATUS_FAILED], |row| {
+            Ok(PersistentDownloadJob {
+                id: row.get("id")?,
+                state: row.get("state")?,
+                concurrency: row.get("concurrency")?,
+                bypass_cache: row.get("bypass_cache")?,
+                total: row.get("total")?,
+                downloaded: row.get("downloaded")?,
+                failed: row.get("failed")?,
+            })
+        })
+        .optional()?;
+    Ok(job)
+}
+
+pub fn add_download_job(
+    state: &str,
+    concurrency: i64,
+    bypass_cache: bool,
+    db: &Connection,
+) -> Result<i64> {
+    let mut statement = db
+        .prepare("INSERT INTO download_jobs (state, concurrency, bypass_cache) VALUES (?, ?, ?)")?;
+    let job_id = statement.insert((state, concurrency, bypass_cache))?;
+    Ok(job_id)
+}
+
+pub fn set_download_job_state(job_id: i64, state: &str, db: &Connection) -> Result<()> {
+    db.execute(
+        "UPDATE download_jobs SET state = ? WHERE id = ?",
+        (state, job_id),
+    )?;
+    Ok(())
+}
+
+pub fn delete_download_job(job_id: i64, db: &mut Connection) -> Result<()> {
+    let tx = db.transaction()?;
+    tx.execute("DELETE FROM download_job_tracks WHERE job_id = ?", [job_id])?;
+    tx.execute("DELETE FROM download_jobs WHERE id = ?", [job_id])?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Queue tracks for a download job, returning the IDs of those that were not queued yet
+pub fn add_download_job_tracks(
+    job_id: i64,
+    track_ids: &[i64],
+    db: &mut Connection,
+) -> Result<Vec<i64>> {
+    let tx = db.transaction()?;
+    let mut added_track_ids = Vec::new();
+    {
+        let mut statement = tx.prepare(
+            "INSERT OR IGNORE INTO download_job_tracks (job_id, track_id, status) VALUES (?, ?, ?)",
+        )?;
+        for &track_id in track_ids {
+            if statement.execute((job_id, track_id, DOWNLOAD_STATUS_PENDING))? > 0 {
+                added_track_ids.push(track_id);
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(added_track_ids)
+}
+
+/// Track IDs of a download job that were not downloaded yet, in queue order
+pub fn get_pending_download_job_track_ids(job_id: i64, db: &Connection) -> Result<Vec<i64>> {
+    let mut statement = db.prepare(
+        "SELECT track_id FROM download_job_tracks WHERE job_id = ? AND status = ? ORDER BY id ASC",
+    )?;
+    let rows = statement.query_map((job_id, DOWNLOAD_STATUS_PENDING), |row| row.get(0))?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+pub fn set_download_job_track_status(
+    job_id: i64,
+    track_id: i64,
+    status: i32,
+    message: &str,
+    db: &Connection,
+) -> Result<()> {
+    db.execute(
+        "UPDATE download_job_tracks SET status = ?, message = ? WHERE job_id = ? AND track_id = ?",
+        (status, message, job_id, track_id),
+    )?;
+    Ok(())
+}
+
+// ============================================================================
+// LRCLIB instances
+// ============================================================================
+
+/// All configured instances in the order they are tried
+pub fn get_lrclib_instances(db: &Connection) -> Result<Vec<PersistentLrclibInstance>> {
+    let mut statement = db.prepare(
+        "SELECT url, enabled, timeout_seconds FROM lrclib_instances ORDER BY position ASC",
+    )?;
+    let instances = statement
+        .query_map([], |row| {
+            Ok(PersistentLrclibInstance {
+                url: row.get("url")?,
+                enabled: row.get("enabled")?,
+                timeout_seconds: row.get("timeout_seconds")?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(instances)
+}
+
+/// Replace the configured instances, keeping their order
+pub fn set_lrclib_instances(
+    instances: &[PersistentLrclibInstance],
+    db: &mut Connection,
+) -> Result<()> {
+    let tx = db.transaction()?;
+    tx.execute("DELETE FROM lrclib_instances", [])?;
+    {
+        let mut statement = tx.prepare(
+            "INSERT INTO lrclib_instances (url, position, enabled, timeout_seconds) VALUES (?, ?, ?, ?)",
+        )?;
+        for (position, instance) in instances.iter().enumerate() {
+            statement.execute((
+                &instance.url,
+                position as i64,
+                instance.enabled,
+                instance.timeout_seconds,
+            ))?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Record the instance the lyrics of a track were downloaded from (`None` for other sources)
+pub fn set_track_lyricsfile_lrclib_instance(
+    track_id: i64,
+    lrclib_instance: Option<&str>,
+    db: &Connection,
+) -> Result<()> {
+    db.execute(
+        "UPDATE lyricsfiles SET lrclib_instance = ? WHERE track_id = ?",
+        (lrclib_instance, track_id),
+    )?;
+    Ok(())
+}
+
+// ============================================================================
+// LRCLIB response cache
+// ============================================================================
+
+pub struct LrclibCacheEntry {
+    pub status: u16,
+    pub response: Option<String>,
+    pub fetched_at: String,
+    pub age_seconds: i64,
+}
+
+/// Cached response of a request, however old it is
+pub fn get_lrclib_cache_entry(
+    lrclib_instance: &str,
+    endpoint: &str,
+    cache_key: &str,
+    db: &Connection,
+) -> Result<Option<LrclibCacheEntry>> {
+    let mut statement = db.prepare(indoc! {"
+        SELECT
+            status,
+            response,
+            fetched_at,
+            CAST(strftime('%s', 'now') - strftime('%s', fetched_at) AS INTEGER) AS age_seconds
+        FROM lrclib_cache
+        WHERE lrclib_instance = ? AND endpoint = ? AND cache_key = ?
+    "})?;
+    let entry = statement
+        .query_row((lrclib_instance, endpoint, cache_key), |row| {
+            Ok(LrclibCacheEntry {
+                status: row.get("status")?,
+                response: row.get("response")?,
+                fetched_at: row.get("fetched_at")?,
+                age_seconds: row.get("age_seconds")?,
+            })
+        })
+        .optional()?;
+    Ok(entry)
+}
+
+/// Store a response, replacing an older one of the same request. Returns its `fetched_at`.
+pub fn set_lrclib_cache_entry(
+    lrclib_instance: &str,
+    endpoint: &str,
+    cache_key: &str,
+    status: u16,
+    response: Option<&str>,
+    db: &Connection,
+) -> Result<String> {
+    let fetched_at = db.query_row(
+        indoc! {"
+            INSERT INTO lrclib_cache (lrclib_instance, endpoint, cache_key, status, response)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (lrclib_instance, endpoint, cache_key) DO UPDATE SET
+                status = excluded.status,
+                response = excluded.response,
+                fetched_at = CURRENT_TIMESTAMP
+            RETURNING fetched_at
+        "},
+        (lrclib_instance, endpoint, cache_key, status, response),
+        |row| row.get(0),
+    )?;
+    Ok(fetched_at)
+}
+
+/// Delete all cached responses, returning how many there were
+pub fn purge_lrclib_cache(db: &Connection) -> Result<usize> {
+    let deleted = db.execute("DELETE FROM lrclib_cache", [])?;
+    Ok(deleted)
+}
+
+/// Record when a lyrics download last found nothing for a track (`None` once it found lyrics)
+pub fn set_track_lrclib_not_found_at(
+    track_id: i64,
+    not_found_at: Option<&str>,
+    db: &Connection,
+) -> Result<()> {
+    db.execute(
+        "UPDATE tracks SET lrclib_not_found_at = ? WHERE id = ?",
+        (not_found_at, track_id),
+    )?;
+    Ok(())
+}
+
+/// Record the best search result a download fell back to (`None` when found directly)
+pub fn set_track_lrclib_match(
+    track_id: i64,
+    score: Option<f64>,
+    reason: Option<&str>,
+    db: &Connection,
+) -> Result<()> {
+    db.execute(
+        "UPDATE tracks SET lrclib_match_score = ?, lrclib_match_reason = ? WHERE id = ?",
+        (score, reason, track_id),
+    )?;
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use crate::lyricsfile::ParsedLyricsfile;
-use crate::parser::lrc::parse_lrc;
+use crate::parser::lrc::{parse_lrc, shift_timestamps};
 use crate::persistent_entities::PersistentTrack;
+use crate::scanner::cue::virtual_track_path;
 use anyhow::{Context, Result};
 use lofty::config::WriteOptions;
 use lofty::file::AudioFile;
@@ -80,6 +81,19 @@ pub fn build_sidecar_path(track_path: &str, extension: &str) -> Result<PathBuf,
     Ok(parent_path.join(format!("{}.{}", file_stem, extension)))
 }
 
+/// Path the sidecar files of a track are named after. Virtual tracks of a CUE sheet share
+/// one audio file, so their sidecars get the track number: `<stem>.<NN>.lrc`.
+fn sidecar_track_path(track: &PersistentTrack) -> String {
+    match (track.start_offset, track.track_number) {
+        (Some(_), Some(track_number)) => {
+            virtual_track_path(Path::new(&track.file_path), track_number as u32)
+                .to_string_lossy()
+                .to_string()
+        }
+        _ => track.file_path.clone(),
+    }
+}
+
 /// Generate plain text lyrics content from parsed lyricsfile
 pub fn generate_txt_content(parsed: &ParsedLyricsfile) -> Option<String> {
     if parsed.is_instrumental {
@@ -131,10 +145,11 @@ fn export_txt(
         }
     };
 
-    let txt_path = build_sidecar_path(&track.file_path, "txt")?;
+    let track_path = sidecar_track_path(track);
+    let txt_path = build_sidecar_path(&track_path, "txt")?;
 
     // Remove conflicting .lrc file if it exists
-    let lrc_path = build_sidecar_path(&track.file_path, "lrc").ok();
+    let lrc_path = build_sidecar_path(&track_path, "lrc").ok();
     if let Some(ref lrc_path) = lrc_path {
         let _ = remove_file(lrc_path);
     }
@@ -148,7 +163,31 @@ fn export_txt(
     })
 }
 
-/// Export synced lyrics to .lrc file
+/// Synced lyrics of a virtual track of a CUE sheet, timed from the start of the track.
+/// Lyrics synced against the whole audio file (none before the track start, some past its
+/// end) get the start offset subtracted; lyrics already timed from the track start are kept.
+fn track_relative_lrc(content: String, track: &PersistentTrack) -> String {
+    let Some(start_offset) = track.start_offset.filter(|offset| *offset > 0.0) else {
+        return content;
+    };
+    let start_ms = (start_offset * 1000.0).round() as i64;
+    let end_ms = (track.duration * 1000.0).round() as i64;
+
+    let timed_lines = parse_lrc(&content).timed_lines;
+    let is_file_relative = match (timed_lines.first(), timed_lines.last()) {
+        (Some(first), Some(last)) => first.timestamp_ms >= start_ms && last.timestamp_ms > end_ms,
+        _ => false,
+    };
+
+    if is_file_relative {
+        shift_timestamps(&content, -start_ms)
+    } else {
+        content
+    }
+}
+
+/// Export synced lyrics to .lrc file. For virtual tracks of a CUE sheet the timestamps
+/// are relative to the track start, not to the start of the audio file.
 fn export_lrc(
     track: &PersistentTrack,
     parsed: &ParsedLyricsfile,
@@ -165,10 +204,12 @@ fn export_lrc(
         }
     };
 
-    let lrc_path = build_sidecar_path(&track.file_path, "lrc")?;
+    let content = track_relative_lrc(content, track);
+    let track_path = sidecar_track_path(track);
+    let lrc_path = build_sidecar_path(&track_path, "lrc")?;
 
     // Remove conflicting .txt file if it exists
-    let txt_path = build_sidecar_path(&track.file_path, "txt").ok();
+    let txt_path = build_sidecar_path(&track_path, "txt").ok();
     if let Some(ref txt_path) = txt_path {
         let _ = remove_file(txt_path);
     }
@@ -187,6 +228,15 @@ fn export_embedded(
     track: &PersistentTrack,
     parsed: &ParsedLyricsfile,
 ) -> Result<ExportResult, ExportError> {
+    // Tags belong to the whole file, which holds all tracks of the CUE sheet
+    if track.start_offset.is_some() {
+        return Ok(ExportResult {
+            format: ExportFormat::Embedded,
+            path: None,
+            status: ExportStatus::Skipped("cannot embed lyrics of a CUE sheet track".to_string()),
+        });
+    }
+
     let plain_lyrics = parsed.plain_lyrics.clone().unwrap_or_default();
     let synced_lyrics = if parsed.is_instrumental {
         crate::lyricsfile::INSTRUMENTAL_LRC.to_string()
@@ -1,8 +1,14 @@
 use crate::db;
-use crate::persistent_entities::{PersistentAlbum, PersistentArtist, PersistentTrack};
+use crate::persistent_entities::{
+    DuplicateReason, DuplicateTrackGroup, PersistentAlbum, PersistentArtist, PersistentTrack,
+};
 use anyhow::Result;
 use rusqlite::Connection;
 
+/// Tracks with the same title and artist are duplicates if their durations differ by at
+/// most this many seconds
+const DUPLICATE_DURATION_TOLERANCE: f64 = 2.0;
+
 pub fn uninitialize_library(conn: &Connection) -> Result<()> {
     db::clean_library(conn)?;
     db::set_init(false, conn)?;
@@ -107,3 +113,81 @@ pub fn get_artist_track_ids(
 pub fn get_init(conn: &Connection) -> Result<bool> {
     db::get_init(conn)
 }
+
+/// Find duplicate tracks: files with the same content hash, and tracks with the same
+/// normalized title and artist and about the same duration. A group of exact copies is
+/// only reported by content hash.
+pub fn get_duplicate_tracks(conn: &Connection) -> Result<Vec<DuplicateTrackGroup>> {
+    let mut groups: Vec<DuplicateTrackGroup> = db::get_track_ids_by_shared_hash(conn)?
+        .into_iter()
+        .map(|track_ids| DuplicateTrackGroup {
+            reason: DuplicateReason::ContentHash,
+            track_ids,
+        })
+        .collect();
+    let hash_group_count = groups.len();
+
+    for tracks in db::get_tracks_by_shared_title_and_artist(conn)? {
+        for track_ids in group_by_duration(&tracks) {
+            let is_hash_group = groups[..hash_group_count]
+                .iter()
+                .any(|group| group.track_ids == track_ids);
+
+            if !is_hash_group {
+                groups.push(DuplicateTrackGroup {
+                    reason: DuplicateReason::Metadata,
+                    track_ids,
+                });
+            }
+        }
+    }
+
+    Ok(groups)
+}
+
+/// Split tracks ordered by duration into groups of tracks whose durations are close to
+/// the previous one; tracks without a close match are dropped. IDs are sorted per group.
+fn group_by_duration(tracks: &[(i64, f64)]) -> Vec<Vec<i64>> {
+    let mut groups: Vec<Vec<i64>> = Vec::new();
+    let mut current: Vec<i64> = Vec::new();
+    let mut previous_duration: Option<f64> = None;
+
+    for &(id, duration) in tracks {
+        let is_close = previous_duration
+            .is_some_and(|previous| duration - previous <= DUPLICATE_DURATION_TOLERANCE);
+
+        if !is_close && !current.is_empty() {
+            groups.push(std::mem::take(&mut current));
+        }
+        current.push(id);
+        previous_duration = Some(duration);
+    }
+    groups.push(current);
+
+    groups.retain(|group| group.len() > 1);
+    for group in groups.iter_mut() {
+        group.sort();
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_by_duration() {
+        let tracks = [
+            (4, 180.0),
+            (2, 181.5),
+            (9, 183.0),
+            (1, 200.0),
+            (3, 240.0),
+            (5, 241.0),
+        ];
+
+        assert_eq!(group_by_duration(&tracks), vec![vec![2, 4, 9], vec![3, 5]]);
+        assert!(group_by_duration(&[]).is_empty());
+    }
+}
@@ -0,0 +1,74 @@
+use crate::utils::prepare_input;
+
+/// `lrclib_cache.endpoint` of `get::request_raw` responses
+pub const GET_ENDPOINT: &str = "get";
+/// `lrclib_cache.endpoint` of `search::request` responses
+pub const SEARCH_ENDPOINT: &str = "search";
+
+/// Lyrics rarely change once published
+const FOUND_TTL_SECONDS: i64 = 30 * 24 * 60 * 60;
+/// Lyrics for a track that was not found may be published any time
+const NOT_FOUND_TTL_SECONDS: i64 = 7 * 24 * 60 * 60;
+const SEARCH_TTL_SECONDS: i64 = 24 * 60 * 60;
+
+/// Key of a `get` request. Text is compared like LRCLIB does, ignoring case, accents and
+/// punctuation, and the duration in whole seconds as it is sent.
+pub fn get_cache_key(title: &str, album_name: &str, artist_name: &str, duration: f64) -> String {
+    format!(
+        "{}\n{}\n{}\n{}",
+        prepare_input(title),
+        prepare_input(album_name),
+        prepare_input(artist_name),
+        duration.round()
+    )
+}
+
+/// Key of a `search` request
+pub fn search_cache_key(title: &str, album_name: &str, artist_name: &str, q: &str) -> String {
+    format!(
+        "{}\n{}\n{}\n{}",
+        prepare_input(title),
+        prepare_input(album_name),
+        prepare_input(artist_name),
+        prepare_input(q)
+    )
+}
+
+/// Whether a cached response of `endpoint` with `status` is still used at `age_seconds`
+pub fn is_fresh(endpoint: &str, status: u16, age_seconds: i64) -> bool {
+    let ttl_seconds = match (endpoint, status) {
+        (SEARCH_ENDPOINT, _) => SEARCH_TTL_SECONDS,
+        (_, 404) => NOT_FOUND_TTL_SECONDS,
+        _ => FOUND_TTL_SECONDS,
+    };
+
+    age_seconds < ttl_seconds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_cache_key_normalizes_request() {
+        assert_eq!(
+            get_cache_key("Don't Stop Me Now", "Jazz", "Queen", 209.4),
+            get_cache_key("don’t stop me  now", "JAZZ", " Queen ", 208.6)
+        );
+        assert_ne!(
+            get_cache_key("Don't Stop Me Now", "Jazz", "Queen", 209.0),
+            get_cache_key("Don't Stop Me Now", "Jazz", "Queen", 215.0)
+        );
+    }
+
+    #[test]
+    fn test_is_fresh() {
+        let day = 24 * 60 * 60;
+
+        assert!(is_fresh(GET_ENDPOINT, 200, 29 * day));
+        assert!(!is_fresh(GET_ENDPOINT, 200, 31 * day));
+        assert!(is_fresh(GET_ENDPOINT, 404, 6 * day));
+        assert!(!is_fresh(GET_ENDPOINT, 404, 8 * day));
+        assert!(!is_fresh(SEARCH_ENDPOINT, 200, 2 * day));
+    }
+}
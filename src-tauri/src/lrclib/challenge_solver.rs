@@ -1,7 +1,26 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
 use data_encoding::HEXUPPER;
 use ring::digest::{Context, SHA256};
 
-fn verify_nonce(result: &Vec<u8>, target: &Vec<u8>) -> bool {
+/// Nonces a worker hashes between looking at the cancel flag and counting its progress
+const BATCH_SIZE: u64 = 4096;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Progress of `solve_challenge`, reported about twice a second
+#[derive(Debug, Clone, Copy)]
+pub struct SolveProgress {
+    pub hashes: u64,
+    pub hash_rate: f64,
+    /// Estimated from the hashes a target takes on average, so the actual time varies a lot
+    pub eta_seconds: f64,
+}
+
+fn verify_nonce(result: &[u8], target: &[u8]) -> bool {
     if result.len() != target.len() {
         return false;
     }
@@ -14,27 +33,168 @@ fn verify_nonce(result: &Vec<u8>, target: &Vec<u8>) -> bool {
         }
     }
 
-    return true;
+    true
 }
 
-pub fn solve_challenge(prefix: &str, target_hex: &str) -> String {
-    let mut nonce = 0;
-    let mut hashed;
-    let target = HEXUPPER.decode(target_hex.as_bytes()).unwrap();
+/// Find a nonce whose SHA-256 hash after `prefix` is below the target, hashing on `threads`
+/// threads (0 for one per CPU core). Blocks until it is found, so it belongs on a blocking
+/// thread. Returns `None` once `cancelled` is set.
+pub fn solve_challenge(
+    prefix: &str,
+    target_hex: &str,
+    threads: usize,
+    cancelled: &AtomicBool,
+    mut on_progress: impl FnMut(SolveProgress),
+) -> Result<Option<String>> {
+    let target = HEXUPPER
+        .decode(target_hex.as_bytes())
+        .map_err(|err| anyhow!("Invalid challenge target {}: {}", target_hex, err))?;
+    if target.is_empty() {
+        return Err(anyhow!("Empty challenge target"));
+    }
+    let threads = match threads {
+        0 => thread::available_parallelism().map_or(1, |threads| threads.get()),
+        threads => threads,
+    };
+
+    // Every nonce is hashed after the same prefix, so hash it once and copy the state
+    let mut prefix_context = Context::new(&SHA256);
+    prefix_context.update(prefix.as_bytes());
+
+    let expected_hashes = expected_hashes(&target);
+    let hashes = AtomicU64::new(0);
+    let solution = Mutex::new(None);
+    let solved = AtomicBool::new(false);
+    let started = Instant::now();
+    let reporter = thread::current();
+
+    thread::scope(|scope| {
+        for worker in 0..threads {
+            let (prefix_context, target) = (&prefix_context, &target);
+            let (hashes, solution, solved, reporter) = (&hashes, &solution, &solved, &reporter);
+
+            // Worker n tries nonces n, n + threads, n + 2 * threads...
+            scope.spawn(move || {
+                let mut nonce = worker as u64;
+                let mut digits = [0; 20];
+
+                while !solved.load(Ordering::Relaxed) && !cancelled.load(Ordering::Relaxed) {
+                    for _ in 0..BATCH_SIZE {
+                        let mut context = prefix_context.clone();
+                        context.update(format_nonce(nonce, &mut digits));
+
+                        if verify_nonce(context.finish().as_ref(), target) {
+                            solution.lock().unwrap().get_or_insert(nonce);
+                            solved.store(true, Ordering::Relaxed);
+                            break;
+                        }
+                        nonce += threads as u64;
+                    }
+                    hashes.fetch_add(BATCH_SIZE, Ordering::Relaxed);
+                }
+
+                reporter.unpark();
+            });
+        }
+
+        while !solved.load(Ordering::Relaxed) && !cancelled.load(Ordering::Relaxed) {
+            thread::park_timeout(PROGRESS_INTERVAL);
+
+            let hashes = hashes.load(Ordering::Relaxed);
+            let hash_rate = hashes as f64 / started.elapsed().as_secs_f64();
+            let eta_seconds = if hash_rate > 0.0 {
+                (expected_hashes - hashes as f64).max(0.0) / hash_rate
+            } else {
+                0.0
+            };
+            on_progress(SolveProgress {
+                hashes,
+                hash_rate,
+                eta_seconds,
+            });
+        }
+    });
+
+    let solution = solution.into_inner().unwrap();
+    Ok(solution.map(|nonce| nonce.to_string()))
+}
 
+/// Decimal digits of `nonce`, written to the end of `buffer` without allocating
+fn format_nonce(mut nonce: u64, buffer: &mut [u8; 20]) -> &[u8] {
+    let mut start = buffer.len();
     loop {
+        start -= 1;
+        buffer[start] = b'0' + (nonce % 10) as u8;
+        nonce /= 10;
+        if nonce == 0 {
+            break;
+        }
+    }
+    &buffer[start..]
+}
+
+/// Hashes it takes on average to get one below `target`, from its leading 8 bytes
+fn expected_hashes(target: &[u8]) -> f64 {
+    let leading = &target[..target.len().min(8)];
+    let value = leading
+        .iter()
+        .fold(0.0, |value, &byte| value * 256.0 + byte as f64);
+    let range = 256f64.powi(leading.len() as i32);
+
+    range / (value + 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(prefix: &str, nonce: &str) -> Vec<u8> {
         let mut context = Context::new(&SHA256);
-        let input = format!("{}{}", prefix, nonce);
-        context.update(input.as_bytes());
-        hashed = context.finish().as_ref().to_vec();
+        context.update(format!("{}{}", prefix, nonce).as_bytes());
+        context.finish().as_ref().to_vec()
+    }
 
-        let result = verify_nonce(&hashed, &target);
-        if result {
-            break;
-        } else {
-            nonce += 1;
+    #[test]
+    fn test_format_nonce() {
+        let mut buffer = [0; 20];
+        assert_eq!(format_nonce(0, &mut buffer), b"0");
+        assert_eq!(format_nonce(1234567, &mut buffer), b"1234567");
+        assert_eq!(
+            format_nonce(u64::MAX, &mut buffer),
+            u64::MAX.to_string().as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_solve_challenge_finds_valid_nonce() {
+        let target_hex = format!("000F{}", "F".repeat(60));
+        let target = HEXUPPER.decode(target_hex.as_bytes()).unwrap();
+        let cancelled = AtomicBool::new(false);
+
+        for threads in [1, 3] {
+            let nonce = solve_challenge("test-prefix", &target_hex, threads, &cancelled, |_| {})
+                .unwrap()
+                .unwrap();
+            assert!(verify_nonce(&hash("test-prefix", &nonce), &target));
         }
     }
 
-    nonce.to_string()
+    #[test]
+    fn test_solve_challenge_stops_when_cancelled() {
+        let target_hex = "0".repeat(64);
+        let cancelled = AtomicBool::new(true);
+
+        let nonce = solve_challenge("test-prefix", &target_hex, 2, &cancelled, |_| {}).unwrap();
+        assert_eq!(nonce, None);
+        assert!(solve_challenge("test-prefix", "XYZ", 2, &cancelled, |_| {}).is_err());
+    }
+
+    #[test]
+    fn test_expected_hashes() {
+        assert_eq!(expected_hashes(&[0xFF; 32]), 1.0);
+        assert_eq!(
+            expected_hashes(&[0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]),
+            256.0
+        );
+    }
 }
@@ -0,0 +1,222 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Method, RequestBuilder, Response, StatusCode, Url};
+use tokio::time::Instant;
+
+/// Timeouts, retries and rate limit of the shared LRCLIB client
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    /// Whole request, from connecting until the response body is read
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    /// Retries after the first attempt of a request that timed out or got 429/503
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every further one
+    pub initial_backoff: Duration,
+    /// Upper bound for the backoff and for a server's `Retry-After`
+    pub max_backoff: Duration,
+    /// Requests sent per second over all callers (retries included)
+    pub requests_per_second: u32,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            connect_timeout: Duration::from_secs(5),
+            max_retries: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            requests_per_second: 5,
+        }
+    }
+}
+
+/// HTTP client shared by all LRCLIB requests, kept in `AppState`. Cloning it is cheap and
+/// the clones share their connection pool and rate limiter.
+#[derive(Clone)]
+pub struct LrclibClient {
+    http: reqwest::Client,
+    options: ClientOptions,
+    limiter: Arc<RateLimiter>,
+}
+
+impl LrclibClient {
+    pub fn new(options: ClientOptions) -> Result<Self> {
+        let version = env!("CARGO_PKG_VERSION");
+        let user_agent = format!(
+            "LRCGET v{} (https://github.com/tranxuanthang/lrcget)",
+            version
+        );
+        let http = reqwest::Client::builder()
+            .timeout(options.timeout)
+            .connect_timeout(options.connect_timeout)
+            .user_agent(user_agent)
+            .build()?;
+        let limiter = Arc::new(RateLimiter::new(options.requests_per_second));
+
+        Ok(Self {
+            http,
+            options,
+            limiter,
+        })
+    }
+
+    /// A client whose requests time out after `timeout` instead, e.g. for a slow mirror. It
+    /// still shares the connection pool and rate limiter.
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            options: ClientOptions {
+                timeout,
+                ..self.options.clone()
+            },
+            ..self.clone()
+        }
+    }
+
+    pub fn get(&self, url: Url) -> RequestBuilder {
+        self.http.get(url).timeout(self.options.timeout)
+    }
+
+    pub fn post(&self, url: Url) -> RequestBuilder {
+        self.http.post(url).timeout(self.options.timeout)
+    }
+
+    /// Send a request built with `get`/`post`, waiting for the rate limiter before every
+    /// attempt. Responses with 429 or 503 are retried after the server's `Retry-After` (or the
+    /// backoff without one); timeouts are only retried for GET requests, since a POST that
+    /// timed out may still have been processed.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let request = request.build()?;
+        let retry_timeouts = request.method() == Method::GET;
+        let mut retry = 0;
+
+        loop {
+            let attempt = request
+                .try_clone()
+                .ok_or_else(|| anyhow!("Request body cannot be sent again"))?;
+            self.limiter.acquire().await;
+
+            let retries_left = retry < self.options.max_retries;
+            let delay = match self.http.execute(attempt).await {
+                Ok(response) if retries_left && is_retryable_status(response.status()) => {
+                    retry_after(response.headers())
+                        .map(|delay| delay.min(self.options.max_backoff))
+                        .unwrap_or_else(|| self.backoff(retry))
+                }
+                Err(error) if retries_left && retry_timeouts && error.is_timeout() => {
+                    self.backoff(retry)
+                }
+                result => return Ok(result?),
+            };
+
+            tokio::time::sleep(delay).await;
+            retry += 1;
+        }
+    }
+
+    fn backoff(&self, retry: u32) -> Duration {
+        backoff_delay(
+            self.options.initial_backoff,
+            self.options.max_backoff,
+            retry,
+        )
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE
+}
+
+/// Exponential backoff: `initial`, `2 * initial`, `4 * initial`... up to `max`
+fn backoff_delay(initial: Duration, max: Duration, retry: u32) -> Duration {
+    initial
+        .checked_mul(2u32.saturating_pow(retry))
+        .map_or(max, |delay| delay.min(max))
+}
+
+/// `Retry-After` given in seconds. The HTTP-date form is not supported and falls back to the
+/// backoff.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?;
+    let seconds = value.trim().parse::<u64>().ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+/// Spaces requests evenly: every request reserves the next free slot and waits for it
+struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    fn new(requests_per_second: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / requests_per_second.max(1),
+            next_slot: Mutex::new(None),
+        }
+    }
+
+    async fn acquire(&self) {
+        let slot = self.reserve(Instant::now());
+        tokio::time::sleep_until(slot).await;
+    }
+
+    fn reserve(&self, now: Instant) -> Instant {
+        let mut next_slot = self.next_slot.lock().unwrap();
+        let slot = next_slot.map_or(now, |next_slot| next_slot.max(now));
+        *next_slot = Some(slot + self.interval);
+        slot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_backoff_delay() {
+        let initial = Duration::from_secs(1);
+        let max = Duration::from_secs(30);
+
+        assert_eq!(backoff_delay(initial, max, 0), Duration::from_secs(1));
+        assert_eq!(backoff_delay(initial, max, 1), Duration::from_secs(2));
+        assert_eq!(backoff_delay(initial, max, 3), Duration::from_secs(8));
+        assert_eq!(backoff_delay(initial, max, 5), max);
+        assert_eq!(backoff_delay(initial, max, 40), max);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(retry_after(&headers), None);
+    }
+
+    #[test]
+    fn test_rate_limiter_spaces_requests() {
+        let limiter = RateLimiter::new(4);
+        let start = Instant::now();
+
+        assert_eq!(limiter.reserve(start), start);
+        assert_eq!(limiter.reserve(start), start + Duration::from_millis(250));
+        assert_eq!(limiter.reserve(start), start + Duration::from_millis(500));
+
+        // Idle time is not saved up for later bursts
+        let later = start + Duration::from_secs(10);
+        assert_eq!(limiter.reserve(later), later);
+        assert_eq!(limiter.reserve(later), later + Duration::from_millis(250));
+    }
+}
@@ -1,10 +1,10 @@
-use std::time::Duration;
-
 use anyhow::Result;
 use reqwest;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use super::client::LrclibClient;
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Request {
@@ -26,28 +26,22 @@ pub async fn request(
     reason: &str,
     publish_token: &str,
     lrclib_instance: &str,
+    client: &LrclibClient,
 ) -> Result<()> {
     let data = Request {
         track_id,
         reason: reason.to_owned(),
     };
 
-    let version = env!("CARGO_PKG_VERSION");
-    let user_agent = format!(
-        "LRCGET v{} (https://github.com/tranxuanthang/lrcget)",
-        version
-    );
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(10))
-        .user_agent(user_agent)
-        .build()?;
     let api_endpoint = format!("{}/api/flag", lrclib_instance.trim_end_matches('/'));
     let url = reqwest::Url::parse(&api_endpoint)?;
     let res = client
-        .post(url)
-        .header("X-Publish-Token", publish_token)
-        .json(&data)
-        .send()
+        .send(
+            client
+                .post(url)
+                .header("X-Publish-Token", publish_token)
+                .json(&data),
+        )
         .await?;
 
     match res.status() {
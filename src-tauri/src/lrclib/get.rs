@@ -1,26 +1,53 @@
-use std::time::Duration;
-
 use crate::utils::strip_timestamp;
 use anyhow::Result;
 use reqwest;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use super::client::LrclibClient;
+use super::search::SearchItem;
+
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RawResponse {
+    #[serde(default)]
+    pub id: Option<i64>,
     pub plain_lyrics: Option<String>,
     pub synced_lyrics: Option<String>,
     pub lyricsfile: Option<String>,
-    instrumental: bool,
-    lang: Option<String>,
-    isrc: Option<String>,
-    spotify_id: Option<String>,
-    name: Option<String>,
-    album_name: Option<String>,
-    artist_name: Option<String>,
-    release_date: Option<String>,
-    duration: Option<f64>,
+    pub instrumental: bool,
+    pub lang: Option<String>,
+    pub isrc: Option<String>,
+    pub spotify_id: Option<String>,
+    pub name: Option<String>,
+    pub album_name: Option<String>,
+    pub artist_name: Option<String>,
+    pub release_date: Option<String>,
+    pub duration: Option<f64>,
+    /// Instance the lyrics came from, set after the lookup
+    #[serde(default)]
+    pub lrclib_instance: Option<String>,
+}
+
+impl From<SearchItem> for RawResponse {
+    fn from(item: SearchItem) -> Self {
+        RawResponse {
+            id: Some(item.id),
+            plain_lyrics: item.plain_lyrics,
+            synced_lyrics: item.synced_lyrics,
+            lyricsfile: item.lyricsfile,
+            instrumental: item.instrumental,
+            lang: None,
+            isrc: None,
+            spotify_id: None,
+            name: item.name,
+            album_name: item.album_name,
+            artist_name: item.artist_name,
+            release_date: None,
+            duration: item.duration,
+            lrclib_instance: item.lrclib_instance,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -65,12 +92,27 @@ pub struct ResponseError {
     message: String,
 }
 
+impl ResponseError {
+    pub fn not_found() -> Self {
+        ResponseError {
+            status_code: Some(404),
+            error: "NotFound".to_string(),
+            message: "There is no lyrics for this track".to_string(),
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.status_code == Some(404)
+    }
+}
+
 async fn make_request(
     title: &str,
     album_name: &str,
     artist_name: &str,
     duration: f64,
     lrclib_instance: &str,
+    client: &LrclibClient,
 ) -> Result<reqwest::Response> {
     let params: Vec<(String, String)> = vec![
         ("artist_name".to_owned(), artist_name.to_owned()),
@@ -79,18 +121,9 @@ async fn make_request(
         ("duration".to_owned(), duration.round().to_string()),
     ];
 
-    let version = env!("CARGO_PKG_VERSION");
-    let user_agent = format!(
-        "LRCGET v{} (https://github.com/tranxuanthang/lrcget)",
-        version
-    );
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(10))
-        .user_agent(user_agent)
-        .build()?;
     let api_endpoint = format!("{}/api/get", lrclib_instance.trim_end_matches('/'));
     let url = reqwest::Url::parse_with_params(&api_endpoint, &params)?;
-    Ok(client.get(url).send().await?)
+    client.send(client.get(url)).await
 }
 
 pub async fn request_raw(
@@ -99,8 +132,17 @@ pub async fn request_raw(
     artist_name: &str,
     duration: f64,
     lrclib_instance: &str,
+    client: &LrclibClient,
 ) -> Result<RawResponse> {
-    let res = make_request(title, album_name, artist_name, duration, lrclib_instance).await?;
+    let res = make_request(
+        title,
+        album_name,
+        artist_name,
+        duration,
+        lrclib_instance,
+        client,
+    )
+    .await?;
 
     match res.status() {
         reqwest::StatusCode::OK => {
@@ -113,21 +155,11 @@ pub async fn request_raw(
             {
                 Ok(lrclib_response)
             } else {
-                Err(ResponseError {
-                    status_code: Some(404),
-                    error: "NotFound".to_string(),
-                    message: "There is no lyrics for this track".to_string(),
-                }
-                .into())
+                Err(ResponseError::not_found().into())
             }
         }
 
-        reqwest::StatusCode::NOT_FOUND => Err(ResponseError {
-            status_code: Some(404),
-            error: "NotFound".to_string(),
-            message: "There is no lyrics for this track".to_string(),
-        }
-        .into()),
+        reqwest::StatusCode::NOT_FOUND => Err(ResponseError::not_found().into()),
 
         reqwest::StatusCode::BAD_REQUEST
         | reqwest::StatusCode::SERVICE_UNAVAILABLE
@@ -151,8 +183,17 @@ pub async fn request(
     artist_name: &str,
     duration: f64,
     lrclib_instance: &str,
+    client: &LrclibClient,
 ) -> Result<Response> {
-    let res = make_request(title, album_name, artist_name, duration, lrclib_instance).await?;
+    let res = make_request(
+        title,
+        album_name,
+        artist_name,
+        duration,
+        lrclib_instance,
+        client,
+    )
+    .await?;
 
     match res.status() {
         reqwest::StatusCode::OK => {
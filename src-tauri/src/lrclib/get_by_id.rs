@@ -1,11 +1,11 @@
-use std::time::Duration;
-
 use crate::utils::strip_timestamp;
 use anyhow::Result;
 use reqwest;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use super::client::LrclibClient;
+
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RawResponse {
@@ -21,6 +21,9 @@ pub struct RawResponse {
     pub artist_name: Option<String>,
     pub release_date: Option<String>,
     pub duration: Option<f64>,
+    /// Instance the lyrics came from, set after the lookup
+    #[serde(default)]
+    pub lrclib_instance: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -65,22 +68,22 @@ pub struct ResponseError {
     message: String,
 }
 
-async fn make_request(id: i64, lrclib_instance: &str) -> Result<reqwest::Response> {
-    let version = env!("CARGO_PKG_VERSION");
-    let user_agent = format!(
-        "LRCGET v{} (https://github.com/tranxuanthang/lrcget)",
-        version
-    );
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(10))
-        .user_agent(user_agent)
-        .build()?;
+async fn make_request(
+    id: i64,
+    lrclib_instance: &str,
+    client: &LrclibClient,
+) -> Result<reqwest::Response> {
     let api_endpoint = format!("{}/api/get/{}", lrclib_instance.trim_end_matches('/'), id);
-    Ok(client.get(&api_endpoint).send().await?)
+    let url = reqwest::Url::parse(&api_endpoint)?;
+    client.send(client.get(url)).await
 }
 
-pub async fn request_raw(id: i64, lrclib_instance: &str) -> Result<RawResponse> {
-    let res = make_request(id, lrclib_instance).await?;
+pub async fn request_raw(
+    id: i64,
+    lrclib_instance: &str,
+    client: &LrclibClient,
+) -> Result<RawResponse> {
+    let res = make_request(id, lrclib_instance, client).await?;
 
     match res.status() {
         reqwest::StatusCode::OK => {
@@ -125,8 +128,8 @@ pub async fn request_raw(id: i64, lrclib_instance: &str) -> Result<RawResponse>
     }
 }
 
-pub async fn request(id: i64, lrclib_instance: &str) -> Result<Response> {
-    let res = make_request(id, lrclib_instance).await?;
+pub async fn request(id: i64, lrclib_instance: &str, client: &LrclibClient) -> Result<Response> {
+    let res = make_request(id, lrclib_instance, client).await?;
 
     match res.status() {
         reqwest::StatusCode::OK => {
@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use reqwest::Url;
+
+use super::client::LrclibClient;
+use crate::persistent_entities::PersistentLrclibInstance;
+
+/// Clean up the instances entered in the config: URLs are trimmed of whitespace and trailing
+/// slashes, blank and repeated ones dropped, and at least one instance must stay enabled
+pub fn normalize_instances(
+    instances: Vec<PersistentLrclibInstance>,
+) -> Result<Vec<PersistentLrclibInstance>> {
+    let mut normalized: Vec<PersistentLrclibInstance> = Vec::new();
+
+    for instance in instances {
+        let url = instance.url.trim().trim_end_matches('/').to_owned();
+        if url.is_empty() || normalized.iter().any(|existing| existing.url == url) {
+            continue;
+        }
+        Url::parse(&url).map_err(|err| anyhow!("Invalid LRCLIB instance {}: {}", url, err))?;
+
+        normalized.push(PersistentLrclibInstance {
+            url,
+            enabled: instance.enabled,
+            timeout_seconds: instance.timeout_seconds.filter(|&seconds| seconds > 0),
+        });
+    }
+
+    if !normalized.iter().any(|instance| instance.enabled) {
+        return Err(anyhow!("At least one LRCLIB instance must be enabled"));
+    }
+
+    Ok(normalized)
+}
+
+/// The client to send requests to `instance` with, using its own timeout if it has one
+pub fn instance_client(instance: &PersistentLrclibInstance, client: &LrclibClient) -> LrclibClient {
+    match instance.timeout_seconds {
+        Some(seconds) => client.with_timeout(Duration::from_secs(seconds.into())),
+        None => client.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(url: &str, enabled: bool) -> PersistentLrclibInstance {
+        PersistentLrclibInstance {
+            url: url.to_owned(),
+            enabled,
+            timeout_seconds: Some(0),
+        }
+    }
+
+    #[test]
+    fn test_normalize_instances() {
+        let instances = normalize_instances(vec![
+            instance(" https://lrclib.example.org/ ", true),
+            instance("", true),
+            instance("https://lrclib.example.org", false),
+            instance("https://lrclib.net", false),
+        ])
+        .unwrap();
+
+        let urls: Vec<&str> = instances.iter().map(|i| i.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec!["https://lrclib.example.org", "https://lrclib.net"]
+        );
+        assert!(instances[0].enabled);
+        assert_eq!(instances[0].timeout_seconds, None);
+    }
+
+    #[test]
+    fn test_normalize_instances_rejects_invalid_lists() {
+        assert!(normalize_instances(vec![instance("https://lrclib.net", false)]).is_err());
+        assert!(normalize_instances(vec![instance("lrclib.net", true)]).is_err());
+        assert!(normalize_instances(vec![]).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+use anyhow::{anyhow, Result};
+use tauri::{AppHandle, Manager, State};
+
+use super::client::LrclibClient;
+use super::{cache, get, get_by_id, instances, search};
+use crate::db;
+use crate::providers::LyricsLookup;
+use crate::state::{AppState, ServiceAccess};
+
+/// The LRCLIB instances to send a request to, in the order they are tried, each with the
+/// client using its timeout: only `lrclib_instance` if given (e.g. the instance a search
+/// result came from), otherwise all enabled instances
+pub fn instance_clients(
+    lrclib_instance: Option<&str>,
+    app_handle: &AppHandle,
+) -> Result<Vec<(String, LrclibClient)>> {
+    // The client shared by all requests, so they reuse connections and the rate limit
+    let app_state: State<AppState> = app_handle.state();
+    let client = app_state.lrclib.clone();
+    let instances = app_handle.db(db::get_lrclib_instances)?;
+
+    if let Some(lrclib_instance) = lrclib_instance {
+        let client = instances
+            .iter()
+            .find(|instance| instance.url == lrclib_instance)
+            .map_or(client.clone(), |instance| {
+                instances::instance_client(instance, &client)
+            });
+        return Ok(vec![(lrclib_instance.to_owned(), client)]);
+    }
+
+    let instances: Vec<_> = instances
+        .iter()
+        .filter(|instance| instance.enabled)
+        .map(|instance| {
+            (
+                instance.url.clone(),
+                instances::instance_client(instance, &client),
+            )
+        })
+        .collect();
+    if instances.is_empty() {
+        return Err(anyhow!("No LRCLIB instance is enabled"));
+    }
+
+    Ok(instances)
+}
+
+/// Look lyrics up on each enabled LRCLIB instance in order until one has them. An instance
+/// that fails is skipped; if none has the lyrics, it is only an error when no instance could
+/// be asked at all. The lyrics record the instance they came from.
+pub async fn get(
+    title: &str,
+    album_name: &str,
+    artist_name: &str,
+    duration: f64,
+    bypass_cache: bool,
+    app_handle: &AppHandle,
+) -> Result<LyricsLookup> {
+    let mut answered = false;
+    let mut first_checked_at = None;
+    let mut last_error = None;
+
+    for (lrclib_instance, client) in instance_clients(None, app_handle)? {
+        let lookup = get_from(
+            title,
+            album_name,
+            artist_name,
+            duration,
+            bypass_cache,
+            &lrclib_instance,
+            &client,
+            app_handle,
+        )
+        .await;
+
+        match lookup {
+            Ok(LyricsLookup::Found(mut candidate)) => {
+                candidate.lrclib_instance = Some(lrclib_instance);
+                return Ok(LyricsLookup::Found(candidate));
+            }
+            Ok(LyricsLookup::NotFound { checked_at }) => {
+                answered = true;
+                if first_checked_at.is_none() {
+                    first_checked_at = checked_at;
+                }
+            }
+            Err(error) => {
+                eprintln!("LRCLIB instance {} failed: {}", lrclib_instance, error);
+                last_error = Some(error);
+            }
+        }
+    }
+
+    match last_error {
+        Some(error) if !answered => Err(error),
+        _ => Ok(LyricsLookup::NotFound {
+            checked_at: first_checked_at,
+        }),
+    }
+}
+
+/// `get::request_raw` answered from the cache while the cached response is fresh (see
+/// `cache`). With `bypass_cache` LRCLIB is always asked; the response is cached either way.
+async fn get_from(
+    title: &str,
+    album_name: &str,
+    artist_name: &str,
+    duration: f64,
+    bypass_cache: bool,
+    lrclib_instance: &str,
+    client: &LrclibClient,
+    app_handle: &AppHandle,
+) -> Result<LyricsLookup> {
+    let endpoint = cache::GET_ENDPOINT;
+    let cache_key = cache::get_cache_key(title, album_name, artist_name, duration);
+
+    if !bypass_cache {
+        let entry = app_handle
+            .db(|db| db::get_lrclib_cache_entry(lrclib_instance, endpoint, &cache_key, db))?
+            .filter(|entry| cache::is_fresh(endpoint, entry.status, entry.age_seconds));
+
+        if let Some(entry) = entry {
+            if entry.status == 404 {
+                return Ok(LyricsLookup::NotFound {
+                    checked_at: Some(entry.fetched_at),
+                });
+            }
+            if let Some(response) = entry.response {
+                let response: get::RawResponse = serde_json::from_str(&response)?;
+                return Ok(LyricsLookup::Found(Box::new(response.into())));
+            }
+        }
+    }
+
+    let result = get::request_raw(
+        title,
+        album_name,
+        artist_name,
+        duration,
+        lrclib_instance,
+        client,
+    )
+    .await;
+
+    match result {
+        Ok(response) => {
+            let json = serde_json::to_string(&response)?;
+            app_handle.db(|db| {
+                db::set_lrclib_cache_entry(
+                    lrclib_instance,
+                    endpoint,
+                    &cache_key,
+                    200,
+                    Some(&json),
+                    db,
+                )
+            })?;
+            Ok(LyricsLookup::Found(Box::new(response.into())))
+        }
+        Err(error)
+            if error
+                .downcast_ref::<get::ResponseError>()
+                .is_some_and(|error| error.is_not_found()) =>
+        {
+            let checked_at = app_handle.db(|db| {
+                db::set_lrclib_cache_entry(lrclib_instance, endpoint, &cache_key, 404, None, db)
+            })?;
+            Ok(LyricsLookup::NotFound {
+                checked_at: Some(checked_at),
+            })
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Search each enabled LRCLIB instance in order until one has results, skipping instances
+/// that fail like `get`. Every result records the instance it came from.
+pub async fn search(
+    title: &str,
+    album_name: &str,
+    artist_name: &str,
+    q: &str,
+    bypass_cache: bool,
+    app_handle: &AppHandle,
+) -> Result<search::Response> {
+    let mut searched = false;
+    let mut last_error = None;
+
+    for (lrclib_instance, client) in instance_clients(None, app_handle)? {
+        let result = search_from(
+            title,
+            album_name,
+            artist_name,
+            q,
+            bypass_cache,
+            &lrclib_instance,
+            &client,
+            app_handle,
+        )
+        .await;
+
+        match result {
+            Ok(mut response) if !response.0.is_empty() => {
+                for item in response.0.iter_mut() {
+                    item.lrclib_instance = Some(lrclib_instance.clone());
+                }
+                return Ok(response);
+            }
+            Ok(_) => searched = true,
+            Err(error) => {
+                eprintln!("LRCLIB instance {} failed: {}", lrclib_instance, error);
+                last_error = Some(error);
+            }
+        }
+    }
+
+    match last_error {
+        Some(error) if !searched => Err(error),
+        _ => Ok(search::Response(vec![])),
+    }
+}
+
+/// `search::request` through the `lrclib_cache`, like `get_from`
+async fn search_from(
+    title: &str,
+    album_name: &str,
+    artist_name: &str,
+    q: &str,
+    bypass_cache: bool,
+    lrclib_instance: &str,
+    client: &LrclibClient,
+    app_handle: &AppHandle,
+) -> Result<search::Response> {
+    let endpoint = cache::SEARCH_ENDPOINT;
+    let cache_key = cache::search_cache_key(title, album_name, artist_name, q);
+
+    if !bypass_cache {
+        let entry = app_handle
+            .db(|db| db::get_lrclib_cache_entry(lrclib_instance, endpoint, &cache_key, db))?
+            .filter(|entry| cache::is_fresh(endpoint, entry.status, entry.age_seconds));
+
+        if let Some(response) = entry.and_then(|entry| entry.response) {
+            return Ok(serde_json::from_str(&response)?);
+        }
+    }
+
+    let response =
+        search::request(title, album_name, artist_name, q, lrclib_instance, client).await?;
+
+    let json = serde_json::to_string(&response)?;
+    app_handle.db(|db| {
+        db::set_lrclib_cache_entry(lrclib_instance, endpoint, &cache_key, 200, Some(&json), db)
+    })?;
+
+    Ok(response)
+}
+
+/// `get_by_id::request_raw` on `lrclib_instance`, or on each enabled instance in order until
+/// one has the ID. The response records the instance it came from.
+pub async fn get_by_id(
+    id: i64,
+    lrclib_instance: Option<&str>,
+    app_handle: &AppHandle,
+) -> Result<get_by_id::RawResponse> {
+    let mut last_error = None;
+
+    for (lrclib_instance, client) in instance_clients(lrclib_instance, app_handle)? {
+        match get_by_id::request_raw(id, &lrclib_instance, &client).await {
+            Ok(mut response) => {
+                response.lrclib_instance = Some(lrclib_instance);
+                return Ok(response);
+            }
+            Err(error) => {
+                eprintln!("LRCLIB instance {} failed: {}", lrclib_instance, error);
+                last_error = Some(error);
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| anyhow!("No LRCLIB instance is enabled")))
+}
@@ -0,0 +1,327 @@
+use std::sync::LazyLock;
+
+use anyhow::{anyhow, Result};
+use collapse::collapse;
+use regex::Regex;
+use serde::Serialize;
+
+/// Rules used when the user has not configured any, one per line, in the order they are applied
+pub const DEFAULT_LOOKUP_RULES: &str =
+    "fullwidth\nexplicit\nremaster\nlive\nfeaturing\nedition\ndisc\nbrackets";
+
+/// A step of the lookup pipeline: a built-in rule by name, or a `/pattern/` line of the config
+/// whose matches are removed from titles
+pub enum LookupRule {
+    /// Full-width Latin letters, digits and punctuation (common in CJK tags) to ASCII
+    Fullwidth,
+    /// "(Explicit)", "[Clean Version]"
+    Explicit,
+    /// " - Remastered 2011", "(2009 Remaster)"
+    Remaster,
+    /// " - Live at Wembley", "(Live)"
+    Live,
+    /// "(feat. X)", " ft. X" in titles and artists
+    Featuring,
+    /// "(Deluxe Edition)", "[Single Version]", " - 25th Anniversary Edition" in titles and albums
+    Edition,
+    /// "Disc 2", "[CD1]" at the end of albums
+    Disc,
+    /// Everything in () and [] brackets of titles
+    Brackets,
+    Custom(Regex),
+}
+
+/// One set of values to look up a track with
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LookupVariant {
+    pub title: String,
+    pub album_name: String,
+    pub artist_name: String,
+    /// Rules applied to get this variant, empty for the track's own tags
+    pub rules: Vec<String>,
+}
+
+static REMASTER_RES: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    vec![
+        Regex::new(r"(?i)\s*[-–—]\s*(\d{4}\s+)?(digital(ly)?\s+)?remaster(ed)?\b.*$").unwrap(),
+        Regex::new(r"(?i)\s*[\(\[][^\)\]]*\bremaster(ed)?\b[^\)\]]*[\)\]]").unwrap(),
+    ]
+});
+static LIVE_RES: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    vec![
+        Regex::new(r"(?i)\s*[-–—]\s*live\b.*$").unwrap(),
+        Regex::new(r"(?i)\s*[\(\[]live\b[^\)\]]*[\)\]]").unwrap(),
+    ]
+});
+static FEATURING_RES: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    vec![
+        Regex::new(r"(?i)\s*[\(\[](feat\.?|ft\.|featuring)\s[^\)\]]*[\)\]]").unwrap(),
+        Regex::new(r"(?i)\s+(feat\.?|ft\.|featuring)\s.*$").unwrap(),
+    ]
+});
+static EXPLICIT_RES: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    vec![Regex::new(r"(?i)\s*[\(\[](explicit|clean)(\s+version)?[\)\]]").unwrap()]
+});
+static EDITION_RES: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    vec![
+        Regex::new(
+            r"(?i)\s*[\(\[][^\)\]]*\b(edition|version|deluxe|expanded|anniversary)\b[^\)\]]*[\)\]]",
+        )
+        .unwrap(),
+        Regex::new(r"(?i)\s*[-–—]\s*[^-–—]*\b(edition|deluxe|anniversary)\b[^-–—]*$").unwrap(),
+    ]
+});
+static DISC_RES: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    vec![Regex::new(r"(?i)\s*[-–—,]?\s*[\(\[]?\b(disc|disk|cd)\s*\d+[\)\]]?\s*$").unwrap()]
+});
+static BRACKETS_RES: LazyLock<Vec<Regex>> =
+    LazyLock::new(|| vec![Regex::new(r"\s*[\(\[][^\)\]]*[\)\]]").unwrap()]);
+
+#[derive(Clone, Copy)]
+enum Field {
+    Title,
+    Album,
+    Artist,
+}
+
+impl LookupRule {
+    pub fn name(&self) -> String {
+        match self {
+            LookupRule::Fullwidth => "fullwidth".to_owned(),
+            LookupRule::Explicit => "explicit".to_owned(),
+            LookupRule::Remaster => "remaster".to_owned(),
+            LookupRule::Live => "live".to_owned(),
+            LookupRule::Featuring => "featuring".to_owned(),
+            LookupRule::Edition => "edition".to_owned(),
+            LookupRule::Disc => "disc".to_owned(),
+            LookupRule::Brackets => "brackets".to_owned(),
+            LookupRule::Custom(pattern) => format!("/{}/", pattern.as_str()),
+        }
+    }
+
+    fn apply(&self, field: Field, value: &str) -> String {
+        let patterns: &[Regex] = match (self, field) {
+            (LookupRule::Fullwidth, _) => return to_halfwidth(value),
+            (LookupRule::Custom(pattern), Field::Title) => std::slice::from_ref(pattern),
+            (LookupRule::Explicit, Field::Title) => EXPLICIT_RES.as_slice(),
+            (LookupRule::Remaster, Field::Title) => REMASTER_RES.as_slice(),
+            (LookupRule::Live, Field::Title) => LIVE_RES.as_slice(),
+            (LookupRule::Featuring, Field::Title | Field::Artist) => FEATURING_RES.as_slice(),
+            (LookupRule::Edition, Field::Title | Field::Album) => EDITION_RES.as_slice(),
+            (LookupRule::Disc, Field::Album) => DISC_RES.as_slice(),
+            (LookupRule::Brackets, Field::Title) => BRACKETS_RES.as_slice(),
+            _ => return value.to_owned(),
+        };
+
+        let mut result = value.to_owned();
+        for pattern in patterns {
+            result = pattern.replace_all(&result, "").into_owned();
+        }
+
+        // Never strip a value down to nothing, e.g. a title that is only "(Intro)"
+        let result = collapse(&result);
+        if result.is_empty() {
+            value.to_owned()
+        } else {
+            result
+        }
+    }
+}
+
+/// Parse the rule list stored in the config (one rule per line), falling back to the defaults
+/// when it holds none
+pub fn parse_lookup_rules(rules: &str) -> Result<Vec<LookupRule>> {
+    let mut parsed = Vec::new();
+
+    for line in rules.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let rule = match line.to_lowercase().as_str() {
+            "fullwidth" => LookupRule::Fullwidth,
+            "explicit" => LookupRule::Explicit,
+            "remaster" => LookupRule::Remaster,
+            "live" => LookupRule::Live,
+            "featuring" => LookupRule::Featuring,
+            "edition" => LookupRule::Edition,
+            "disc" => LookupRule::Disc,
+            "brackets" => LookupRule::Brackets,
+            _ if line.len() > 2 && line.starts_with('/') && line.ends_with('/') => {
+                let pattern = &line[1..line.len() - 1];
+                LookupRule::Custom(
+                    Regex::new(pattern)
+                        .map_err(|err| anyhow!("Invalid lookup rule {}: {}", line, err))?,
+                )
+            }
+            _ => return Err(anyhow!("Unknown lookup rule: {}", line)),
+        };
+        parsed.push(rule);
+    }
+
+    if parsed.is_empty() {
+        return parse_lookup_rules(DEFAULT_LOOKUP_RULES);
+    }
+
+    Ok(parsed)
+}
+
+/// The values to look a track up with, in order: its own tags, then the result of applying
+/// the rules one after another. Each rule that changes something adds a variant, so later
+/// variants are more and more loosely normalized.
+pub fn lookup_variants(
+    title: &str,
+    album_name: &str,
+    artist_name: &str,
+    rules: &[LookupRule],
+) -> Vec<LookupVariant> {
+    let mut current = LookupVariant {
+        title: title.to_owned(),
+        album_name: album_name.to_owned(),
+        artist_name: artist_name.to_owned(),
+        rules: vec![],
+    };
+    let mut variants = vec![current.clone()];
+
+    for rule in rules {
+        let mut next = LookupVariant {
+            title: rule.apply(Field::Title, &current.title),
+            album_name: rule.apply(Field::Album, &current.album_name),
+            artist_name: rule.apply(Field::Artist, &current.artist_name),
+            rules: current.rules.clone(),
+        };
+
+        if next != current {
+            next.rules.push(rule.name());
+            variants.push(next.clone());
+            current = next;
+        }
+    }
+
+    variants
+}
+
+/// Add a last variant looking the track up by its primary artist alone, unless a variant
+/// already does. The variants start from the artist tag as written (e.g. `A; B`), which
+/// LRCLIB lists most tracks under, but not all credits are reduced to one artist by the rules.
+pub fn add_primary_artist_variant(variants: &mut Vec<LookupVariant>, primary_artist: &str) {
+    let Some(last) = variants.last() else {
+        return;
+    };
+
+    if variants
+        .iter()
+        .any(|variant| variant.artist_name == primary_artist)
+    {
+        return;
+    }
+
+    let mut variant = last.clone();
+    variant.artist_name = primary_artist.to_owned();
+    variant.rules.push("primary_artist".to_owned());
+    variants.push(variant);
+}
+
+/// Map full-width forms (U+FF01 to U+FF5E and the ideographic space) to their ASCII equivalents
+fn to_halfwidth(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            '\u{3000}' => ' ',
+            _ => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variant_titles(title: &str, rules: &str) -> Vec<String> {
+        let rules = parse_lookup_rules(rules).unwrap();
+        lookup_variants(title, "", "", &rules)
+            .into_iter()
+            .map(|variant| variant.title)
+            .collect()
+    }
+
+    #[test]
+    fn test_default_rules_strip_title_suffixes() {
+        let cases = [
+            ("Let It Be - Remastered 2009", "Let It Be"),
+            ("Heroes (2017 Remaster)", "Heroes"),
+            (
+                "Bohemian Rhapsody - Live at Wembley '86",
+                "Bohemian Rhapsody",
+            ),
+            ("Under Pressure (Live)", "Under Pressure"),
+            ("Stay (feat. Justin Bieber)", "Stay"),
+            ("Lose Yourself (Explicit)", "Lose Yourself"),
+            ("Yesterday [Single Version]", "Yesterday"),
+            ("Song (Intro)", "Song"),
+            ("（Ｌｉｖｅ） Ｓｏｎｇ", "Song"),
+        ];
+
+        for (title, expected) in cases {
+            let titles = variant_titles(title, "");
+            assert_eq!(titles.first().unwrap(), title);
+            assert_eq!(titles.last().unwrap(), expected, "{}", title);
+        }
+    }
+
+    #[test]
+    fn test_variants_are_applied_in_order() {
+        let rules = parse_lookup_rules("featuring\nedition\ndisc").unwrap();
+        let variants = lookup_variants(
+            "Song ft. B",
+            "Album (Deluxe Edition) [Disc 2]",
+            "A feat. B",
+            &rules,
+        );
+
+        assert_eq!(variants.len(), 4);
+        assert_eq!(variants[1].title, "Song");
+        assert_eq!(variants[1].artist_name, "A");
+        assert_eq!(variants[1].rules, vec!["featuring"]);
+        assert_eq!(variants[2].album_name, "Album [Disc 2]");
+        assert_eq!(variants[3].album_name, "Album");
+        assert_eq!(variants[3].rules, vec!["featuring", "edition", "disc"]);
+    }
+
+    #[test]
+    fn test_add_primary_artist_variant() {
+        let rules = parse_lookup_rules("featuring").unwrap();
+
+        let mut variants = lookup_variants("Song", "Album", "A feat. B", &rules);
+        add_primary_artist_variant(&mut variants, "A");
+        assert_eq!(variants.len(), 2);
+
+        let mut variants = lookup_variants("Song", "Album", "A; B", &rules);
+        add_primary_artist_variant(&mut variants, "A");
+        assert_eq!(variants.len(), 2);
+        assert_eq!(variants[0].artist_name, "A; B");
+        assert_eq!(variants[1].artist_name, "A");
+        assert_eq!(variants[1].rules, vec!["primary_artist"]);
+    }
+
+    #[test]
+    fn test_rules_that_change_nothing_add_no_variant() {
+        assert_eq!(variant_titles("Plain Title", ""), vec!["Plain Title"]);
+    }
+
+    #[test]
+    fn test_parse_lookup_rules() {
+        let rules = parse_lookup_rules("remaster\n/ - Single Mix$/\n").unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[1].name(), "/ - Single Mix$/");
+        assert_eq!(
+            variant_titles("Song - Single Mix", "/ - Single Mix$/"),
+            vec!["Song - Single Mix", "Song"]
+        );
+
+        assert!(parse_lookup_rules("remastered").is_err());
+        assert!(parse_lookup_rules("/(/").is_err());
+    }
+}
@@ -0,0 +1,398 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::providers::LyricsCandidate;
+use crate::utils::{prepare_input, prepare_search_input};
+
+const TITLE_WEIGHT: f64 = 0.35;
+const ARTIST_WEIGHT: f64 = 0.25;
+const ALBUM_WEIGHT: f64 = 0.1;
+const DURATION_WEIGHT: f64 = 0.2;
+const LYRICS_WEIGHT: f64 = 0.1;
+
+/// Durations within this many seconds count as equal, like LRCLIB's own /api/get matching
+const DURATION_TOLERANCE: f64 = 2.0;
+/// Candidates further off are another version of the track, whose synced lyrics would not fit
+const MAX_DURATION_DELTA: f64 = 10.0;
+
+/// Words in a bracketed part or a " - " suffix of a title that mark another recording of a
+/// song, whose lyrics differ or don't fit the original's timing
+const VERSION_MARKERS: &[&str] = &[
+    "instrumental",
+    "karaoke",
+    "live",
+    "acoustic",
+    "unplugged",
+    "remix",
+    "demo",
+    "acapella",
+    "cappella",
+];
+/// Factor for the title score of a candidate whose version markers differ from the target's
+const VERSION_MISMATCH_FACTOR: f64 = 0.25;
+
+static VERSION_PART_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[\(\[]([^\)\]]*)[\)\]]|\s[-–—]\s(.*)$").unwrap());
+
+/// The track lyrics are looked up for
+pub struct MatchTarget<'a> {
+    pub title: &'a str,
+    /// The title as tagged. Lookup variants may have stripped the version markers (like
+    /// "(Live)") that tell recordings apart from `title`.
+    pub tagged_title: &'a str,
+    pub album_name: &'a str,
+    pub artist_name: &'a str,
+    pub duration: f64,
+}
+
+/// Confidence (0 to 1) that a search result is the target track, and how it was reached
+#[derive(Debug, Clone)]
+pub struct MatchScore {
+    pub score: f64,
+    pub reason: String,
+}
+
+/// Score every search result that has lyrics against the target and return the best one.
+/// Results with a duration more than 10s off are never considered.
+pub fn best_match<'a>(
+    target: &MatchTarget,
+    candidates: &'a [LyricsCandidate],
+) -> Option<(&'a LyricsCandidate, MatchScore)> {
+    best_match_where(target, candidates, |_| true)
+}
+
+/// The best search result with lyrics when it is a confident match (see
+/// `is_confident_match`), otherwise the best result of all. An instrumental result can only
+/// be the latter, so it never hides a confident result with lyrics that scores lower.
+pub fn best_download_match<'a>(
+    target: &MatchTarget,
+    candidates: &'a [LyricsCandidate],
+    min_confidence: f64,
+) -> Option<(&'a LyricsCandidate, MatchScore)> {
+    best_match_where(target, candidates, |candidate| !candidate.instrumental)
+        .filter(|(candidate, score)| is_confident_match(candidate, score, min_confidence))
+        .or_else(|| best_match(target, candidates))
+}
+
+fn best_match_where<'a>(
+    target: &MatchTarget,
+    candidates: &'a [LyricsCandidate],
+    predicate: impl Fn(&LyricsCandidate) -> bool,
+) -> Option<(&'a LyricsCandidate, MatchScore)> {
+    candidates
+        .iter()
+        .filter(|candidate| predicate(candidate))
+        .filter_map(|candidate| Some((candidate, score_candidate(target, candidate)?)))
+        .max_by(|(_, a), (_, b)| a.score.total_cmp(&b.score))
+}
+
+/// Whether a search result is downloaded without the user picking it: it scores at least
+/// `min_confidence` and has lyrics. An instrumental result is only recorded as the best score.
+pub fn is_confident_match(
+    candidate: &LyricsCandidate,
+    score: &MatchScore,
+    min_confidence: f64,
+) -> bool {
+    score.score >= min_confidence && !candidate.instrumental
+}
+
+fn score_candidate(target: &MatchTarget, candidate: &LyricsCandidate) -> Option<MatchScore> {
+    let (lyrics_score, lyrics_kind) = if candidate.synced_lyrics.is_some() {
+        (1.0, "synced lyrics")
+    } else if candidate.plain_lyrics.is_some() {
+        (0.5, "plain lyrics")
+    } else if candidate.instrumental {
+        (0.0, "instrumental")
+    } else {
+        return None;
+    };
+
+    let (duration_score, duration_reason) = match candidate.duration {
+        Some(duration) => {
+            let delta = (duration - target.duration).abs();
+            if delta > MAX_DURATION_DELTA {
+                return None;
+            }
+            let score = if delta <= DURATION_TOLERANCE {
+                1.0
+            } else {
+                1.0 - (delta - DURATION_TOLERANCE) / (MAX_DURATION_DELTA - DURATION_TOLERANCE)
+            };
+            (score, format!("duration ±{}s", delta.round()))
+        }
+        None => (0.5, "unknown duration".to_owned()),
+    };
+
+    let candidate_title = candidate.name.as_deref().unwrap_or("");
+    let mut title_score = text_similarity(target.title, candidate_title);
+    // "Song (Karaoke)" is not "Song", even though their titles compare equal
+    let target_markers = version_markers(target.tagged_title);
+    let candidate_markers = version_markers(candidate_title);
+    let differing_markers: Vec<&str> = target_markers
+        .iter()
+        .filter(|marker| !candidate_markers.contains(marker))
+        .chain(
+            candidate_markers
+                .iter()
+                .filter(|marker| !target_markers.contains(marker)),
+        )
+        .copied()
+        .collect();
+    if !differing_markers.is_empty() {
+        title_score *= VERSION_MISMATCH_FACTOR;
+    }
+    let artist_score = text_similarity(
+        target.artist_name,
+        candidate.artist_name.as_deref().unwrap_or(""),
+    );
+    let album_score = text_similarity(
+        target.album_name,
+        candidate.album_name.as_deref().unwrap_or(""),
+    );
+
+    let score = TITLE_WEIGHT * title_score
+        + ARTIST_WEIGHT * artist_score
+        + ALBUM_WEIGHT * album_score
+        + DURATION_WEIGHT * duration_score
+        + LYRICS_WEIGHT * lyrics_score;
+    let mut reason = format!(
+        "title {}, artist {}, album {}, {}, {}",
+        percent(title_score),
+        percent(artist_score),
+        percent(album_score),
+        duration_reason,
+        lyrics_kind
+    );
+    if !differing_markers.is_empty() {
+        reason.push_str(&format!(
+            ", other version ({})",
+            differing_markers.join(", ")
+        ));
+    }
+
+    Some(MatchScore { score, reason })
+}
+
+pub fn percent(score: f64) -> String {
+    format!("{}%", (score * 100.0).round())
+}
+
+/// Similarity (0 to 1) of two names after dropping bracketed parts like "(Remastered 2011)",
+/// case, accents and punctuation. A name whose words all appear in the other (e.g. the
+/// primary artist in a "A & B" credit) still counts as close.
+fn text_similarity(a: &str, b: &str) -> f64 {
+    let a = prepare_search_input(a);
+    let b = prepare_search_input(b);
+
+    if a == b {
+        return 1.0;
+    }
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let a_chars: Vec<char> = a.chars().collect();
+    let b_chars: Vec<char> = b.chars().collect();
+    let max_len = a_chars.len().max(b_chars.len()) as f64;
+    let edit_similarity = 1.0 - levenshtein(&a_chars, &b_chars) as f64 / max_len;
+
+    let a_words: Vec<&str> = a.split_whitespace().collect();
+    let b_words: Vec<&str> = b.split_whitespace().collect();
+    let contained = a_words.iter().all(|word| b_words.contains(word))
+        || b_words.iter().all(|word| a_words.contains(word));
+
+    if contained {
+        edit_similarity.max(0.9)
+    } else {
+        edit_similarity
+    }
+}
+
+/// The version markers in the bracketed parts and " - " suffix of a title
+fn version_markers(title: &str) -> Vec<&'static str> {
+    let words: Vec<String> = VERSION_PART_RE
+        .captures_iter(title)
+        .filter_map(|captures| captures.get(1).or_else(|| captures.get(2)))
+        .flat_map(|part| {
+            prepare_input(part.as_str())
+                .split_whitespace()
+                .map(str::to_owned)
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    VERSION_MARKERS
+        .iter()
+        .copied()
+        .filter(|marker| words.iter().any(|word| word == marker))
+        .collect()
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, a_char) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(name: &str, artist_name: &str, duration: f64, synced: bool) -> LyricsCandidate {
+        LyricsCandidate {
+            provider: "lrclib".to_owned(),
+            id: "1".to_owned(),
+            name: Some(name.to_owned()),
+            artist_name: Some(artist_name.to_owned()),
+            album_name: Some("Jazz".to_owned()),
+            duration: Some(duration),
+            instrumental: false,
+            plain_lyrics: Some("Tonight I'm gonna have myself a real good time".to_owned()),
+            synced_lyrics: synced.then(|| "[00:01.00] Tonight".to_owned()),
+            lyricsfile: None,
+            lrclib_instance: None,
+        }
+    }
+
+    #[test]
+    fn test_text_similarity() {
+        assert_eq!(
+            text_similarity("Don't Stop Me Now (Remastered 2011)", "Don't stop me now"),
+            1.0
+        );
+        assert_eq!(text_similarity("Queen", "Queen & David Bowie"), 0.9);
+        assert!(text_similarity("Bohemian Rhapsody", "Bohemian Rapsody") > 0.9);
+        assert!(text_similarity("Bohemian Rhapsody", "Under Pressure") < 0.3);
+    }
+
+    #[test]
+    fn test_best_match_prefers_close_synced_candidate() {
+        let target = MatchTarget {
+            title: "Don't Stop Me Now (Remastered 2011)",
+            tagged_title: "Don't Stop Me Now (Remastered 2011)",
+            album_name: "Jazz (Deluxe Edition)",
+            artist_name: "Queen",
+            duration: 210.0,
+        };
+        let candidates = vec![
+            candidate("Don't Stop Me Now", "Queen", 211.0, false),
+            candidate("Don't Stop Me Now", "Queen", 209.0, true),
+            candidate("Don't Stop Me Now (Live)", "Queen", 240.0, true),
+        ];
+
+        let (best, score) = best_match(&target, &candidates).unwrap();
+        assert!(best.synced_lyrics.is_some());
+        assert_eq!(best.duration, Some(209.0));
+        assert!(score.score > 0.95, "{:?}", score);
+        assert_eq!(
+            score.reason,
+            "title 100%, artist 100%, album 100%, duration ±1s, synced lyrics"
+        );
+    }
+
+    #[test]
+    fn test_best_match_skips_candidates_without_lyrics_or_matching_duration() {
+        let target = MatchTarget {
+            title: "Under Pressure",
+            tagged_title: "Under Pressure",
+            album_name: "Hot Space",
+            artist_name: "Queen",
+            duration: 248.0,
+        };
+        let mut without_lyrics = candidate("Under Pressure", "Queen", 248.0, false);
+        without_lyrics.plain_lyrics = None;
+        let candidates = vec![
+            without_lyrics,
+            candidate("Under Pressure", "Queen", 300.0, true),
+        ];
+
+        assert!(best_match(&target, &candidates).is_none());
+    }
+
+    #[test]
+    fn test_one_sided_version_markers_are_penalized() {
+        let target = MatchTarget {
+            title: "Song",
+            tagged_title: "Song (Live)",
+            album_name: "Jazz",
+            artist_name: "Queen",
+            duration: 200.0,
+        };
+        let candidates = vec![
+            candidate("Song (Karaoke)", "Queen", 200.0, true),
+            candidate("Song - Live at Wembley", "Queen", 200.0, true),
+        ];
+
+        let (best, score) = best_match(&target, &candidates).unwrap();
+        assert_eq!(best.name.as_deref(), Some("Song - Live at Wembley"));
+        assert!(score.score > 0.95, "{:?}", score);
+
+        let (_, score) = best_match(&target, &candidates[..1]).unwrap();
+        assert!(score.score < 0.8, "{:?}", score);
+        assert!(score.reason.ends_with("other version (live, karaoke)"));
+
+        assert!(version_markers("Live Forever").is_empty());
+        assert_eq!(version_markers("Song [A Cappella]"), vec!["cappella"]);
+    }
+
+    #[test]
+    fn test_instrumental_candidates_are_never_confident() {
+        let target = MatchTarget {
+            title: "Song",
+            tagged_title: "Song",
+            album_name: "Jazz",
+            artist_name: "Queen",
+            duration: 200.0,
+        };
+        let mut instrumental = candidate("Song", "Queen", 200.0, false);
+        instrumental.plain_lyrics = None;
+        instrumental.instrumental = true;
+        let plain = candidate("Song", "Queen", 200.0, false);
+
+        let (_, instrumental_score) =
+            best_match(&target, std::slice::from_ref(&instrumental)).unwrap();
+        let (_, plain_score) = best_match(&target, std::slice::from_ref(&plain)).unwrap();
+        assert!(instrumental_score.score < plain_score.score);
+        assert!(!is_confident_match(&instrumental, &instrumental_score, 0.5));
+        assert!(is_confident_match(&plain, &plain_score, 0.5));
+    }
+
+    #[test]
+    fn test_best_download_match_skips_higher_scoring_instrumental() {
+        let target = MatchTarget {
+            title: "Song",
+            tagged_title: "Song",
+            album_name: "Jazz",
+            artist_name: "Queen",
+            duration: 200.0,
+        };
+        let mut instrumental = candidate("Song", "Queen", 200.0, false);
+        instrumental.plain_lyrics = None;
+        instrumental.instrumental = true;
+        let candidates = vec![instrumental, candidate("Song", "Queen", 206.0, false)];
+
+        let (best, best_score) = best_match(&target, &candidates).unwrap();
+        assert!(best.instrumental);
+
+        let (download, score) = best_download_match(&target, &candidates, 0.8).unwrap();
+        assert!(!download.instrumental);
+        assert!(score.score < best_score.score, "{:?}", score);
+        assert!(is_confident_match(download, &score, 0.8));
+
+        // Without a confident result with lyrics, the instrumental one is the recorded best
+        let (download, score) = best_download_match(&target, &candidates, 0.95).unwrap();
+        assert!(download.instrumental);
+        assert!(!is_confident_match(download, &score, 0.95));
+    }
+}
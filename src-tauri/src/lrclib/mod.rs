@@ -1,7 +1,13 @@
+pub mod cache;
 pub mod challenge_solver;
+pub mod client;
 pub mod flag;
 pub mod get;
 pub mod get_by_id;
+pub mod instances;
+pub mod lookup;
+pub mod lookup_variants;
+pub mod matcher;
 pub mod publish;
 pub mod request_challenge;
 pub mod search;
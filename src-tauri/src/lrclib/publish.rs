@@ -1,10 +1,10 @@
-use std::time::Duration;
-
 use anyhow::Result;
 use reqwest;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use super::client::LrclibClient;
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Request {
@@ -39,6 +39,7 @@ pub async fn request(
     lyricsfile: Option<&str>,
     publish_token: &str,
     lrclib_instance: &str,
+    client: &LrclibClient,
 ) -> Result<()> {
     let data = Request {
         artist_name: artist_name.to_owned(),
@@ -50,22 +51,15 @@ pub async fn request(
         lyricsfile: lyricsfile.map(str::to_owned),
     };
 
-    let version = env!("CARGO_PKG_VERSION");
-    let user_agent = format!(
-        "LRCGET v{} (https://github.com/tranxuanthang/lrcget)",
-        version
-    );
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(10))
-        .user_agent(user_agent)
-        .build()?;
     let api_endpoint = format!("{}/api/publish", lrclib_instance.trim_end_matches('/'));
     let url = reqwest::Url::parse(&api_endpoint)?;
     let res = client
-        .post(url)
-        .header("X-Publish-Token", publish_token)
-        .json(&data)
-        .send()
+        .send(
+            client
+                .post(url)
+                .header("X-Publish-Token", publish_token)
+                .json(&data),
+        )
         .await?;
 
     match res.status() {
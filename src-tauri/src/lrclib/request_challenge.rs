@@ -1,10 +1,10 @@
-use std::time::Duration;
-
 use anyhow::Result;
 use reqwest;
 use serde::Deserialize;
 use thiserror::Error;
 
+use super::client::LrclibClient;
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Response {
@@ -21,22 +21,13 @@ pub struct ResponseError {
     message: String,
 }
 
-pub async fn request(lrclib_instance: &str) -> Result<Response> {
-    let version = env!("CARGO_PKG_VERSION");
-    let user_agent = format!(
-        "LRCGET v{} (https://github.com/tranxuanthang/lrcget)",
-        version
-    );
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(10))
-        .user_agent(user_agent)
-        .build()?;
+pub async fn request(lrclib_instance: &str, client: &LrclibClient) -> Result<Response> {
     let api_endpoint = format!(
         "{}/api/request-challenge",
         lrclib_instance.trim_end_matches('/')
     );
     let url = reqwest::Url::parse(&api_endpoint)?;
-    let res = client.post(url).send().await?;
+    let res = client.send(client.post(url)).await?;
 
     match res.status() {
         reqwest::StatusCode::OK => {
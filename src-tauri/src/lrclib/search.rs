@@ -1,26 +1,29 @@
-use std::time::Duration;
-
 use anyhow::Result;
 use reqwest;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-#[derive(Deserialize, Serialize)]
+use super::client::LrclibClient;
+
+#[derive(Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct SearchItem {
-    id: i64,
-    name: Option<String>,
-    artist_name: Option<String>,
-    album_name: Option<String>,
-    duration: Option<f64>,
-    instrumental: bool,
-    plain_lyrics: Option<String>,
-    synced_lyrics: Option<String>,
-    lyricsfile: Option<String>,
+pub struct SearchItem {
+    pub id: i64,
+    pub name: Option<String>,
+    pub artist_name: Option<String>,
+    pub album_name: Option<String>,
+    pub duration: Option<f64>,
+    pub instrumental: bool,
+    pub plain_lyrics: Option<String>,
+    pub synced_lyrics: Option<String>,
+    pub lyricsfile: Option<String>,
+    /// Instance the result was found on. IDs are only valid on their own instance.
+    #[serde(default)]
+    pub lrclib_instance: Option<String>,
 }
 
 #[derive(Deserialize, Serialize)]
-pub struct Response(Vec<SearchItem>);
+pub struct Response(pub Vec<SearchItem>);
 
 #[derive(Error, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -37,6 +40,7 @@ pub async fn request(
     artist_name: &str,
     q: &str,
     lrclib_instance: &str,
+    client: &LrclibClient,
 ) -> Result<Response> {
     let params: Vec<(String, String)> = vec![
         ("track_name".to_owned(), title.to_owned()),
@@ -45,18 +49,9 @@ pub async fn request(
         ("q".to_owned(), q.to_owned()),
     ];
 
-    let version = env!("CARGO_PKG_VERSION");
-    let user_agent = format!(
-        "LRCGET v{} (https://github.com/tranxuanthang/lrcget)",
-        version
-    );
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(10))
-        .user_agent(user_agent)
-        .build()?;
     let api_endpoint = format!("{}/api/search", lrclib_instance.trim_end_matches('/'));
     let url = reqwest::Url::parse_with_params(&api_endpoint, &params)?;
-    let res = client.get(url).send().await?;
+    let res = client.send(client.get(url)).await?;
 
     match res.status() {
         reqwest::StatusCode::OK => {
@@ -71,6 +71,7 @@ struct LyricsfileMetadata {
     offset_ms: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     language: Option<String>,
+    #[serde(default)]
     instrumental: bool,
 }
 
@@ -109,6 +110,11 @@ struct ParsedLyricsfileDocument {
     plain: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct LyricsfileMetadataDocument {
+    metadata: LyricsfileMetadata,
+}
+
 #[derive(Debug, Deserialize)]
 struct ParsedLyricsfileMetadata {
     #[serde(default)]
@@ -187,6 +193,20 @@ pub fn parse_lyricsfile(lyricsfile: &str) -> Result<ParsedLyricsfile> {
     })
 }
 
+/// Title, album, artist and duration stored in a lyricsfile. Album and duration are optional
+/// in the format, so they come back empty and 0 when missing.
+pub fn parse_lyricsfile_metadata(lyricsfile: &str) -> Result<LyricsfileTrackMetadata> {
+    let document: LyricsfileMetadataDocument = serde_yaml::from_str(lyricsfile)?;
+    let metadata = document.metadata;
+
+    Ok(LyricsfileTrackMetadata {
+        title: metadata.title,
+        album_name: metadata.album.unwrap_or_default(),
+        artist_name: metadata.artist,
+        duration: metadata.duration_ms.map_or(0.0, |ms| ms as f64 / 1000.0),
+    })
+}
+
 pub fn lyrics_presence_from_lyricsfile(lyricsfile: &str) -> Result<LyricsPresence> {
     let document: ParsedLyricsfileDocument = serde_yaml::from_str(lyricsfile)?;
     let is_instrumental = document.metadata.instrumental;
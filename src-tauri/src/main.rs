@@ -8,19 +8,33 @@ pub mod export;
 pub mod library;
 pub mod lrclib;
 pub mod lyricsfile;
+pub mod mass_download;
 pub mod parser;
 pub mod persistent_entities;
 pub mod player;
+pub mod providers;
 pub mod scanner;
 pub mod state;
 pub mod utils;
+pub mod watcher;
 pub mod word_segmentation;
 
-use persistent_entities::{PersistentAlbum, PersistentArtist, PersistentConfig, PersistentTrack, PlayableTrack};
+use lrclib::challenge_solver::SolveProgress;
+use lrclib::client::LrclibClient;
+use lrclib::lookup_variants::LookupVariant;
+use lrclib::matcher::{best_download_match, is_confident_match, percent, MatchScore, MatchTarget};
+use persistent_entities::{
+    DuplicateTrackGroup, PersistentAlbum, PersistentArtist, PersistentConfig,
+    PersistentLrclibInstance, PersistentScanError, PersistentTrack, PlayableTrack,
+};
 use player::Player;
 use rusqlite::Connection;
+use scanner::job::ScanJob;
 use serde::{Deserialize, Serialize};
 use state::{AppState, Notify, NotifyType, ServiceAccess};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use tauri::{AppHandle, Emitter, Manager, State};
 
 struct ResolvedLyricsPayload {
@@ -31,6 +45,7 @@ struct ResolvedLyricsPayload {
 }
 
 const LRCLIB_TRACK_NOT_FOUND: &str = "This track does not exist in LRCLIB database";
+const CHALLENGE_CANCELLED: &str = "Cancelled while solving the LRCLIB challenge";
 
 #[derive(Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -38,6 +53,9 @@ struct PublishLyricsProgress {
     request_challenge: String,
     solve_challenge: String,
     publish_lyrics: String,
+    /// Hashes per second and estimated seconds left while solving the challenge
+    hash_rate: Option<f64>,
+    eta_seconds: Option<f64>,
 }
 
 #[derive(Clone, Serialize)]
@@ -46,6 +64,47 @@ struct FlagLyricsProgress {
     request_challenge: String,
     solve_challenge: String,
     flag_lyrics: String,
+    hash_rate: Option<f64>,
+    eta_seconds: Option<f64>,
+}
+
+/// Solve an LRCLIB challenge on the blocking pool with one thread per CPU core, passing its
+/// hash rate and ETA to `on_progress`. Returns `None` when cancelled with `cancel_challenge`.
+async fn solve_lrclib_challenge(
+    challenge: &lrclib::request_challenge::Response,
+    app_handle: &AppHandle,
+    on_progress: impl FnMut(SolveProgress) + Send + 'static,
+) -> Result<Option<String>, String> {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let app_state: State<AppState> = app_handle.state();
+    *app_state.challenge.lock().unwrap() = Some(cancelled.clone());
+
+    let prefix = challenge.prefix.clone();
+    let target = challenge.target.clone();
+    let solver_cancelled = cancelled.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        lrclib::challenge_solver::solve_challenge(
+            &prefix,
+            &target,
+            0,
+            &solver_cancelled,
+            on_progress,
+        )
+    })
+    .await;
+
+    // Unless another publish or flag has started solving in the meantime
+    let mut current = app_state.challenge.lock().unwrap();
+    if current
+        .as_ref()
+        .is_some_and(|current| Arc::ptr_eq(current, &cancelled))
+    {
+        *current = None;
+    }
+
+    result
+        .map_err(|err| err.to_string())?
+        .map_err(|err| err.to_string())
 }
 
 #[derive(Clone, Copy, Deserialize)]
@@ -167,10 +226,41 @@ async fn get_directories(app_state: State<'_, AppState>) -> Result<Vec<String>,
 async fn set_directories(
     directories: Vec<String>,
     app_state: State<'_, AppState>,
+    app_handle: AppHandle,
 ) -> Result<(), String> {
+    {
+        let conn_guard = app_state.db.lock().unwrap();
+        let conn = conn_guard.as_ref().unwrap();
+        db::set_directories(directories, conn).map_err(|err| err.to_string())?;
+    }
+
+    watcher::refresh_library_watcher(&app_handle).map_err(|err| err.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_directory_ignore_patterns(
+    app_state: State<'_, AppState>,
+) -> Result<HashMap<String, String>, String> {
     let conn_guard = app_state.db.lock().unwrap();
     let conn = conn_guard.as_ref().unwrap();
-    db::set_directories(directories, conn).map_err(|err| err.to_string())?;
+    let directory_patterns =
+        db::get_directory_ignore_patterns(conn).map_err(|err| err.to_string())?;
+
+    Ok(directory_patterns)
+}
+
+#[tauri::command]
+async fn set_directory_ignore_patterns(
+    directory: String,
+    ignore_patterns: String,
+    app_state: State<'_, AppState>,
+) -> Result<(), String> {
+    let conn_guard = app_state.db.lock().unwrap();
+    let conn = conn_guard.as_ref().unwrap();
+    db::set_directory_ignore_patterns(&directory, &ignore_patterns, conn)
+        .map_err(|err| err.to_string())?;
 
     Ok(())
 }
@@ -184,6 +274,15 @@ async fn get_init(app_state: State<'_, AppState>) -> Result<bool, String> {
     Ok(init)
 }
 
+#[tauri::command]
+async fn get_tags_reread_pending(app_state: State<'_, AppState>) -> Result<bool, String> {
+    let conn_guard = app_state.db.lock().unwrap();
+    let conn = conn_guard.as_ref().unwrap();
+    let pending = db::get_tags_reread_pending(conn).map_err(|err| err.to_string())?;
+
+    Ok(pending)
+}
+
 #[tauri::command]
 async fn get_config(app_state: State<'_, AppState>) -> Result<PersistentConfig, String> {
     let conn_guard = app_state.db.lock().unwrap();
@@ -202,25 +301,106 @@ async fn set_config(
     theme_mode: &str,
     lrclib_instance: &str,
     volume: f64,
+    lyrics_source_precedence: Option<String>,
+    watch_library: Option<bool>,
+    scan_worker_count: Option<u32>,
+    path_templates: Option<String>,
+    audio_extensions: Option<String>,
+    artist_separators: Option<String>,
+    search_fallback_min_confidence: Option<f64>,
+    lookup_rules: Option<String>,
+    lyrics_providers: Option<String>,
+    local_lyrics_folder: Option<String>,
+    download_concurrency: Option<u32>,
+    detection_method: Option<String>,
     app_state: State<'_, AppState>,
+    app_handle: AppHandle,
 ) -> Result<(), String> {
-    let conn_guard = app_state.db.lock().unwrap();
-    let conn = conn_guard.as_ref().unwrap();
-    db::set_config(
-        skip_tracks_with_synced_lyrics,
-        skip_tracks_with_plain_lyrics,
-        show_line_count,
-        try_embed_lyrics,
-        theme_mode,
-        lrclib_instance,
-        volume,
-        conn,
-    )
-    .map_err(|err| err.to_string())?;
+    let watch_library_changed = {
+        let conn_guard = app_state.db.lock().unwrap();
+        let conn = conn_guard.as_ref().unwrap();
+        let current_config = db::get_config(conn).map_err(|err| err.to_string())?;
+        let lyrics_source_precedence =
+            lyrics_source_precedence.unwrap_or(current_config.lyrics_source_precedence);
+        let watch_library = watch_library.unwrap_or(current_config.watch_library);
+        let scan_worker_count = scan_worker_count.unwrap_or(current_config.scan_worker_count);
+        let path_templates = path_templates.unwrap_or(current_config.path_templates);
+        scanner::path_template::PathTemplate::parse_list(&path_templates)
+            .map_err(|err| err.to_string())?;
+        let audio_extensions = audio_extensions.unwrap_or(current_config.audio_extensions);
+        let artist_separators = artist_separators.unwrap_or(current_config.artist_separators);
+        let search_fallback_min_confidence = search_fallback_min_confidence
+            .unwrap_or(current_config.search_fallback_min_confidence)
+            .clamp(0.0, 1.0);
+        let lookup_rules = lookup_rules.unwrap_or(current_config.lookup_rules);
+        lrclib::lookup_variants::parse_lookup_rules(&lookup_rules)
+            .map_err(|err| err.to_string())?;
+        let lyrics_providers = lyrics_providers.unwrap_or(current_config.lyrics_providers);
+        providers::parse_lyrics_providers(&lyrics_providers).map_err(|err| err.to_string())?;
+        let local_lyrics_folder = local_lyrics_folder.unwrap_or(current_config.local_lyrics_folder);
+        let download_concurrency = download_concurrency
+            .unwrap_or(current_config.download_concurrency)
+            .clamp(1, mass_download::MAX_CONCURRENCY as u32);
+        let detection_method = detection_method.unwrap_or(current_config.detection_method);
+        scanner::DetectionMethod::from_config_value(&detection_method)
+            .ok_or_else(|| format!("Unknown detection method: {}", detection_method))?;
+        db::set_config(
+            skip_tracks_with_synced_lyrics,
+            skip_tracks_with_plain_lyrics,
+            show_line_count,
+            try_embed_lyrics,
+            theme_mode,
+            lrclib_instance,
+            volume,
+            &lyrics_source_precedence,
+            watch_library,
+            scan_worker_count,
+            &path_templates,
+            &audio_extensions,
+            &artist_separators,
+            search_fallback_min_confidence,
+            &lookup_rules,
+            &lyrics_providers,
+            &local_lyrics_folder,
+            download_concurrency,
+            &detection_method,
+            conn,
+        )
+        .map_err(|err| err.to_string())?;
+
+        watch_library != current_config.watch_library
+    };
+
+    if watch_library_changed {
+        watcher::refresh_library_watcher(&app_handle).map_err(|err| err.to_string())?;
+    }
 
     Ok(())
 }
 
+#[tauri::command]
+async fn get_lrclib_instances(
+    app_handle: AppHandle,
+) -> Result<Vec<PersistentLrclibInstance>, String> {
+    app_handle
+        .db(db::get_lrclib_instances)
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+async fn set_lrclib_instances(
+    instances: Vec<PersistentLrclibInstance>,
+    app_handle: AppHandle,
+) -> Result<Vec<PersistentLrclibInstance>, String> {
+    let instances =
+        lrclib::instances::normalize_instances(instances).map_err(|err| err.to_string())?;
+    app_handle
+        .db_mut(|db| db::set_lrclib_instances(&instances, db))
+        .map_err(|err| err.to_string())?;
+
+    Ok(instances)
+}
+
 #[tauri::command]
 async fn uninitialize_library(app_state: State<'_, AppState>) -> Result<(), String> {
     let conn_guard = app_state.db.lock().unwrap();
@@ -247,28 +427,32 @@ async fn full_scan_library(
         library::full_wipe_library(conn).map_err(|err| err.to_string())?;
     }
 
-    // Step 2: Get directories
-    let directories = {
+    // Step 2: Get directories and config
+    let (directories, config, ignore_patterns) = {
         let conn_guard = app_state.db.lock().unwrap();
         let conn = conn_guard.as_ref().unwrap();
-        db::get_directories(conn).map_err(|err| err.to_string())?
+        let directories = db::get_directories(conn).map_err(|err| err.to_string())?;
+        let config = db::get_config(conn).map_err(|err| err.to_string())?;
+        let ignore_patterns =
+            db::get_directory_ignore_patterns(conn).map_err(|err| err.to_string())?;
+        (directories, config, ignore_patterns)
     };
 
-    // Determine detection method (default to Hash for reliability)
-    let detection_method = if use_hash_detection.unwrap_or(true) {
-        scanner::scan::DetectionMethod::Hash
-    } else {
-        scanner::scan::DetectionMethod::Metadata
-    };
+    let artwork_cache_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|err| err.to_string())?
+        .join(scanner::artwork::CACHE_DIR_NAME);
+    let scan_options = scanner::models::ScanOptions::from_config(&config, use_hash_detection)
+        .map_err(|err| err.to_string())?
+        .with_ignore_patterns(ignore_patterns)
+        .with_artwork_cache_dir(artwork_cache_dir);
 
     // Clone app_handle for use in the closure
     let app_handle_clone = app_handle.clone();
 
     // Step 3: Run full scan
-    let scan_result = tokio::task::block_in_place(|| {
-        let mut conn_guard = app_state.db.lock().unwrap();
-        let conn = conn_guard.as_mut().unwrap();
-
+    let scan_result = run_scan_job(&app_state, |conn, scan_job| {
         scanner::scan_library(
             &directories,
             conn,
@@ -276,7 +460,8 @@ async fn full_scan_library(
                 // Emit progress directly (synchronous)
                 let _ = app_handle_clone.emit("scan-progress", progress);
             },
-            detection_method,
+            &scan_options,
+            scan_job,
         )
     })
     .map_err(|err| err.to_string())?;
@@ -293,28 +478,32 @@ async fn scan_library(
     app_handle: AppHandle,
     use_hash_detection: Option<bool>,
 ) -> Result<scanner::models::ScanResult, String> {
-    // Get directories first (requires immutable access)
-    let directories = {
+    // Get directories and config first (requires immutable access)
+    let (directories, config, ignore_patterns) = {
         let conn_guard = app_state.db.lock().unwrap();
         let conn = conn_guard.as_ref().unwrap();
-        db::get_directories(conn).map_err(|err| err.to_string())?
+        let directories = db::get_directories(conn).map_err(|err| err.to_string())?;
+        let config = db::get_config(conn).map_err(|err| err.to_string())?;
+        let ignore_patterns =
+            db::get_directory_ignore_patterns(conn).map_err(|err| err.to_string())?;
+        (directories, config, ignore_patterns)
     };
 
-    // Determine detection method (default to Hash for reliability)
-    let detection_method = if use_hash_detection.unwrap_or(true) {
-        scanner::scan::DetectionMethod::Hash
-    } else {
-        scanner::scan::DetectionMethod::Metadata
-    };
+    let artwork_cache_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|err| err.to_string())?
+        .join(scanner::artwork::CACHE_DIR_NAME);
+    let scan_options = scanner::models::ScanOptions::from_config(&config, use_hash_detection)
+        .map_err(|err| err.to_string())?
+        .with_ignore_patterns(ignore_patterns)
+        .with_artwork_cache_dir(artwork_cache_dir);
 
     // Clone app_handle for use in the closure
     let app_handle_clone = app_handle.clone();
 
     // Run scan synchronously but use block_in_place to not block the runtime
-    let scan_result = tokio::task::block_in_place(|| {
-        let mut conn_guard = app_state.db.lock().unwrap();
-        let conn = conn_guard.as_mut().unwrap();
-
+    let scan_result = run_scan_job(&app_state, |conn, scan_job| {
         scanner::scan_library(
             &directories,
             conn,
@@ -322,7 +511,8 @@ async fn scan_library(
                 // Emit progress directly (synchronous)
                 let _ = app_handle_clone.emit("scan-progress", progress);
             },
-            detection_method,
+            &scan_options,
+            scan_job,
         )
     })
     .map_err(|err| err.to_string())?;
@@ -333,6 +523,123 @@ async fn scan_library(
     Ok(scan_result)
 }
 
+/// Scan only the given directories or files of the library, e.g. a newly added album folder.
+/// Tracks outside of them are left untouched.
+#[tauri::command]
+async fn scan_paths(
+    paths: Vec<String>,
+    app_state: State<'_, AppState>,
+    app_handle: AppHandle,
+    use_hash_detection: Option<bool>,
+) -> Result<scanner::models::ScanResult, String> {
+    let (directories, config, ignore_patterns) = {
+        let conn_guard = app_state.db.lock().unwrap();
+        let conn = conn_guard.as_ref().unwrap();
+        let directories = db::get_directories(conn).map_err(|err| err.to_string())?;
+        let config = db::get_config(conn).map_err(|err| err.to_string())?;
+        let ignore_patterns =
+            db::get_directory_ignore_patterns(conn).map_err(|err| err.to_string())?;
+        (directories, config, ignore_patterns)
+    };
+
+    // Only scan inside the library, so no tracks are added from elsewhere. Tracks of an
+    // unavailable library directory would be deleted instead of being kept offline.
+    for path in paths.iter() {
+        let directory = directories
+            .iter()
+            .find(|directory| std::path::Path::new(path).starts_with(directory))
+            .ok_or_else(|| format!("{} is not inside a library directory", path))?;
+
+        if !scanner::scan::is_directory_available(std::path::Path::new(directory)) {
+            return Err(format!("Library directory {} is not available", directory));
+        }
+    }
+
+    let artwork_cache_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|err| err.to_string())?
+        .join(scanner::artwork::CACHE_DIR_NAME);
+    let scan_options = scanner::models::ScanOptions::from_config(&config, use_hash_detection)
+        .map_err(|err| err.to_string())?
+        .with_ignore_patterns(ignore_patterns)
+        .with_artwork_cache_dir(artwork_cache_dir);
+
+    let app_handle_clone = app_handle.clone();
+
+    let scan_result = run_scan_job(&app_state, |conn, scan_job| {
+        scanner::scan_paths(
+            &paths,
+            conn,
+            &|progress| {
+                let _ = app_handle_clone.emit("scan-progress", progress);
+            },
+            &scan_options,
+            scan_job,
+        )
+    })
+    .map_err(|err| err.to_string())?;
+
+    let _ = app_handle.emit("scan-complete", &scan_result);
+
+    Ok(scan_result)
+}
+
+/// Run a scan on the scan connection, without blocking the runtime. Its job is registered in
+/// `AppState.scan_job` once the connection is acquired, so pause/resume/cancel requests reach
+/// the scan that holds it and not one still waiting for it. While the scan is paused it keeps
+/// the connection, so the watcher's `apply_file_changes` waits until it is resumed or stopped.
+fn run_scan_job(
+    app_state: &AppState,
+    scan: impl FnOnce(&mut Connection, &ScanJob) -> anyhow::Result<scanner::models::ScanResult>,
+) -> anyhow::Result<scanner::models::ScanResult> {
+    tokio::task::block_in_place(|| {
+        let mut conn_guard = app_state.scan_db.lock().unwrap();
+        let conn = conn_guard.as_mut().unwrap();
+
+        let scan_job = Arc::new(ScanJob::new());
+        *app_state.scan_job.lock().unwrap() = Some(Arc::clone(&scan_job));
+
+        let scan_result = scan(conn, &scan_job);
+
+        let mut current_job = app_state.scan_job.lock().unwrap();
+        if current_job
+            .as_ref()
+            .is_some_and(|job| Arc::ptr_eq(job, &scan_job))
+        {
+            *current_job = None;
+        }
+
+        scan_result
+    })
+}
+
+/// Apply a pause/resume/cancel request to the running library scan
+fn control_scan_job(app_state: &AppState, control: impl FnOnce(&ScanJob)) -> Result<(), String> {
+    match app_state.scan_job.lock().unwrap().as_deref() {
+        Some(scan_job) => {
+            control(scan_job);
+            Ok(())
+        }
+        None => Err("No library scan is running".to_string()),
+    }
+}
+
+#[tauri::command]
+async fn pause_scan(app_state: State<'_, AppState>) -> Result<(), String> {
+    control_scan_job(&app_state, ScanJob::pause)
+}
+
+#[tauri::command]
+async fn resume_scan(app_state: State<'_, AppState>) -> Result<(), String> {
+    control_scan_job(&app_state, ScanJob::resume)
+}
+
+#[tauri::command]
+async fn cancel_scan(app_state: State<'_, AppState>) -> Result<(), String> {
+    control_scan_job(&app_state, ScanJob::cancel)
+}
+
 #[tauri::command]
 async fn get_tracks(app_state: State<'_, AppState>) -> Result<Vec<PersistentTrack>, String> {
     let conn_guard = app_state.db.lock().unwrap();
@@ -390,6 +697,11 @@ async fn find_matching_tracks(
     let conn_guard = app_state.db.lock().unwrap();
     let conn = conn_guard.as_ref().unwrap();
 
+    // Tracks are matched on their primary artist, so "A feat. B" matches tracks by A
+    let config = db::get_config(conn).map_err(|err| err.to_string())?;
+    let separators = scanner::artist_credit::parse_artist_separators(&config.artist_separators);
+    let artist_name = scanner::artist_credit::primary_artist(&artist_name, &separators);
+
     // First, try to find tracks with all criteria (strong match)
     let strong_matches = db::find_tracks_by_metadata(
         &title,
@@ -598,22 +910,136 @@ async fn get_artist_track_ids(
 }
 
 #[tauri::command]
-async fn download_lyrics(track_id: i64, app_handle: AppHandle) -> Result<String, String> {
+async fn download_lyrics(
+    track_id: i64,
+    bypass_cache: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<String, String> {
     let track = app_handle
         .db(|db| db::get_track_by_id(track_id, db))
         .map_err(|err| err.to_string())?;
-    let config = app_handle
-        .db(|db| db::get_config(db))
+
+    download_track_lyrics(&track, bypass_cache.unwrap_or(false), &app_handle).await
+}
+
+/// Download lyrics of a track from the configured lyrics providers in priority order and
+/// store them
+async fn download_track_lyrics(
+    track: &PersistentTrack,
+    bypass_cache: bool,
+    app_handle: &AppHandle,
+) -> Result<String, String> {
+    let variants = track_lookup_variants(track, app_handle).map_err(|err| err.to_string())?;
+    let providers = providers::configured_providers(app_handle)
+        .await
         .map_err(|err| err.to_string())?;
-    let lrclib_response = lrclib::get::request_raw(
-        &track.title,
-        &track.album_name,
-        &track.artist_name,
-        track.duration,
-        &config.lrclib_instance,
-    )
-    .await
-    .map_err(|err| err.to_string())?;
+
+    // Ask each provider for the track's own tags first, then each more loosely normalized
+    // variant. A failed lookup doesn't stop the provider from being asked for the remaining
+    // variants, but it is remembered, so the track is not recorded as not found.
+    let mut found = None;
+    let mut first_checked_at = None;
+    let mut last_error = None;
+    'providers: for provider in &providers {
+        for variant in &variants {
+            let lookup = provider
+                .get(&variant_query(variant, track.duration), bypass_cache)
+                .await;
+
+            match lookup {
+                Ok(providers::LyricsLookup::Found(candidate)) => {
+                    found = Some((*candidate, variant));
+                    break 'providers;
+                }
+                Ok(providers::LyricsLookup::NotFound { checked_at }) => {
+                    if let Some(checked_at) = checked_at {
+                        first_checked_at.get_or_insert(checked_at);
+                    }
+                }
+                Err(error) => {
+                    eprintln!("Lyrics provider {} failed: {}", provider.name(), error);
+                    last_error = Some(error);
+                }
+            }
+        }
+    }
+
+    let (candidate, applied_rules, search_match) = match found {
+        Some((candidate, variant)) => (candidate, variant.rules.clone(), None),
+        None => {
+            let min_confidence = app_handle
+                .db(db::get_config)
+                .map_err(|err| err.to_string())?
+                .search_fallback_min_confidence;
+            // The most normalized variant is the most likely to be found by a search
+            let variant = variants
+                .last()
+                .expect("The track's own tags are always a variant");
+            let (fallback, search_error) = search_fallback(
+                &providers,
+                variant,
+                &track.title,
+                track.duration,
+                min_confidence,
+                bypass_cache,
+            )
+            .await;
+
+            match fallback {
+                Some((candidate, score))
+                    if is_confident_match(&candidate, &score, min_confidence) =>
+                {
+                    (candidate, vec![], Some(score))
+                }
+                fallback => {
+                    // A provider that failed may have the lyrics, so nothing is recorded
+                    if let Some(error) = search_error.or(last_error) {
+                        return Err(error.to_string());
+                    }
+
+                    let best_score = fallback.map(|(_, score)| score);
+                    app_handle
+                        .db(|db| {
+                            db::set_track_lrclib_not_found_at(
+                                track.id,
+                                first_checked_at.as_deref(),
+                                db,
+                            )?;
+                            db::set_track_lrclib_match(
+                                track.id,
+                                best_score.as_ref().map(|score| score.score),
+                                best_score.as_ref().map(|score| score.reason.as_str()),
+                                db,
+                            )
+                        })
+                        .map_err(|err| err.to_string())?;
+                    app_handle.emit("reload-track-id", track.id).unwrap();
+
+                    let not_found = lrclib::get::ResponseError::not_found().to_string();
+                    return Err(match best_score {
+                        // An instrumental result is never downloaded from a search
+                        Some(score) if score.score >= min_confidence => format!(
+                            "{} (best search result scored {}, but is instrumental: {})",
+                            not_found,
+                            percent(score.score),
+                            score.reason
+                        ),
+                        Some(score) => format!(
+                            "{} (best search result scored {}, below {}: {})",
+                            not_found,
+                            percent(score.score),
+                            percent(min_confidence),
+                            score.reason
+                        ),
+                        None => not_found,
+                    });
+                }
+            }
+        }
+    };
+    let provider = candidate.provider.clone();
+    let lrclib_response: lrclib::get::RawResponse = candidate.into();
+    let lrclib_instance = lrclib_response.lrclib_instance.clone();
     let resolved = resolve_lrclib_lyrics_payload(lrclib_response)?;
 
     // Build lyricsfile content from the resolved response
@@ -645,21 +1071,191 @@ async fn download_lyrics(track_id: i64, app_handle: AppHandle) -> Result<String,
                 track.duration,
                 &lyricsfile_content,
                 db,
+            )?;
+            db::set_track_lyricsfile_lrclib_instance(track.id, lrclib_instance.as_deref(), db)?;
+            db::set_track_lrclib_not_found_at(track.id, None, db)?;
+            db::set_track_lrclib_match(
+                track.id,
+                search_match.as_ref().map(|score| score.score),
+                search_match.as_ref().map(|score| score.reason.as_str()),
+                db,
             )
         })
         .map_err(|err| err.to_string())?;
 
-    app_handle.emit("reload-track-id", track_id).unwrap();
+    app_handle.emit("reload-track-id", track.id).unwrap();
 
-    if resolved.is_instrumental {
-        Ok("Marked track as instrumental".to_owned())
+    let message = if resolved.is_instrumental {
+        "Marked track as instrumental"
     } else if !resolved.synced_lyrics.is_empty() {
-        Ok("Synced lyrics downloaded".to_owned())
+        "Synced lyrics downloaded"
     } else if !resolved.plain_lyrics.is_empty() {
-        Ok("Plain lyrics downloaded".to_owned())
+        "Plain lyrics downloaded"
     } else {
-        Err(LRCLIB_TRACK_NOT_FOUND.to_owned())
+        return Err(LRCLIB_TRACK_NOT_FOUND.to_owned());
+    };
+    let message = if provider == providers::lrclib::PROVIDER_NAME {
+        message.to_owned()
+    } else {
+        format!("{} from {}", message, provider)
+    };
+
+    match search_match {
+        Some(score) => Ok(format!(
+            "{} (search result scored {}: {})",
+            message,
+            percent(score.score),
+            score.reason
+        )),
+        None if !applied_rules.is_empty() => Ok(format!(
+            "{} (found after applying lookup rules: {})",
+            message,
+            applied_rules.join(", ")
+        )),
+        None => Ok(message),
+    }
+}
+
+#[tauri::command]
+async fn preview_lookup_variants(
+    track_id: i64,
+    app_handle: AppHandle,
+) -> Result<Vec<LookupVariant>, String> {
+    let track = app_handle
+        .db(|db| db::get_track_by_id(track_id, db))
+        .map_err(|err| err.to_string())?;
+
+    track_lookup_variants(&track, &app_handle).map_err(|err| err.to_string())
+}
+
+/// The title, album and artist to look a track up with, in the order they are tried (see
+/// `lrclib::lookup_variants`)
+fn track_lookup_variants(
+    track: &PersistentTrack,
+    app_handle: &AppHandle,
+) -> anyhow::Result<Vec<LookupVariant>> {
+    let config = app_handle.db(db::get_config)?;
+    let rules = lrclib::lookup_variants::parse_lookup_rules(&config.lookup_rules)?;
+
+    // Look up by the artist tag as written, falling back to the primary artist
+    let artist_credit = track
+        .artist_credit
+        .as_deref()
+        .filter(|credit| !credit.trim().is_empty())
+        .unwrap_or(&track.artist_name);
+    let mut variants = lrclib::lookup_variants::lookup_variants(
+        &track.title,
+        &track.album_name,
+        artist_credit,
+        &rules,
+    );
+    lrclib::lookup_variants::add_primary_artist_variant(&mut variants, &track.artist_name);
+
+    Ok(variants)
+}
+
+/// The query a provider is asked with for a lookup variant of a track
+fn variant_query(variant: &LookupVariant, duration: f64) -> providers::LyricsQuery {
+    providers::LyricsQuery {
+        title: variant.title.clone(),
+        album_name: variant.album_name.clone(),
+        artist_name: variant.artist_name.clone(),
+        duration,
+    }
+}
+
+/// When every provider misses for every lookup variant, search each provider for the title
+/// of `variant` without bracketed parts like "(Remastered 2011)" and its artist, and score
+/// all results against it (and the version markers of `tagged_title`, the track's own title).
+/// The result is the one to download if it is confident, otherwise the best score to record
+/// (see `best_download_match`). Providers that fail are skipped; the last error is returned
+/// along with the result.
+async fn search_fallback(
+    providers: &[Box<dyn providers::LyricsProvider>],
+    variant: &LookupVariant,
+    tagged_title: &str,
+    duration: f64,
+    min_confidence: f64,
+    bypass_cache: bool,
+) -> (
+    Option<(providers::LyricsCandidate, MatchScore)>,
+    Option<anyhow::Error>,
+) {
+    let query = providers::LyricsQuery {
+        title: utils::prepare_search_input(&variant.title),
+        album_name: String::new(),
+        artist_name: variant.artist_name.clone(),
+        duration,
+    };
+
+    let mut candidates = Vec::new();
+    let mut last_error = None;
+    for provider in providers {
+        match provider.search(&query, "", bypass_cache).await {
+            Ok(results) => candidates.extend(results),
+            Err(error) => {
+                eprintln!("Lyrics provider {} failed: {}", provider.name(), error);
+                last_error = Some(error);
+            }
+        }
     }
+
+    let target = MatchTarget {
+        title: &variant.title,
+        tagged_title,
+        album_name: &variant.album_name,
+        artist_name: &variant.artist_name,
+        duration,
+    };
+
+    let best = best_download_match(&target, &candidates, min_confidence)
+        .map(|(candidate, score)| (candidate.clone(), score));
+
+    (best, last_error)
+}
+
+#[tauri::command]
+async fn start_mass_download(
+    track_ids: Option<Vec<i64>>,
+    concurrency: Option<usize>,
+    bypass_cache: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<mass_download::DownloadJobStatus, String> {
+    mass_download::start(
+        &app_handle,
+        track_ids,
+        concurrency,
+        bypass_cache.unwrap_or(false),
+    )
+    .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+async fn get_mass_download_status(
+    app_handle: AppHandle,
+) -> Result<Option<mass_download::DownloadJobStatus>, String> {
+    Ok(mass_download::status(&app_handle))
+}
+
+#[tauri::command]
+async fn pause_mass_download(
+    app_handle: AppHandle,
+) -> Result<mass_download::DownloadJobStatus, String> {
+    mass_download::pause(&app_handle).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+async fn resume_mass_download(
+    app_handle: AppHandle,
+) -> Result<mass_download::DownloadJobStatus, String> {
+    mass_download::resume(&app_handle).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+async fn cancel_mass_download(
+    app_handle: AppHandle,
+) -> Result<mass_download::DownloadJobStatus, String> {
+    mass_download::cancel(&app_handle).map_err(|err| err.to_string())
 }
 
 #[tauri::command]
@@ -672,6 +1268,7 @@ async fn apply_lyrics(
         .db(|db| db::get_track_by_id(track_id, db))
         .map_err(|err| err.to_string())?;
 
+    let lrclib_instance = lrclib_response.lrclib_instance.clone();
     let resolved = resolve_lrclib_lyrics_payload(lrclib_response)?;
 
     // Build lyricsfile content from the resolved response
@@ -703,7 +1300,8 @@ async fn apply_lyrics(
                 track.duration,
                 &lyricsfile_content,
                 db,
-            )
+            )?;
+            db::set_track_lyricsfile_lrclib_instance(track.id, lrclib_instance.as_deref(), db)
         })
         .map_err(|err| err.to_string())?;
 
@@ -728,39 +1326,53 @@ async fn retrieve_lyrics(
     album_name: String,
     artist_name: String,
     duration: f64,
+    bypass_cache: Option<bool>,
     app_handle: AppHandle,
 ) -> Result<lrclib::get::RawResponse, String> {
-    let config = app_handle
-        .db(|db: &Connection| db::get_config(db))
-        .map_err(|err| err.to_string())?;
-
-    let response = lrclib::get::request_raw(
+    let lookup = lrclib::lookup::get(
         &title,
         &album_name,
         &artist_name,
         duration,
-        &config.lrclib_instance,
+        bypass_cache.unwrap_or(false),
+        &app_handle,
     )
     .await
     .map_err(|err| err.to_string())?;
 
-    Ok(response)
+    match lookup {
+        providers::LyricsLookup::Found(candidate) => Ok((*candidate).into()),
+        providers::LyricsLookup::NotFound { .. } => {
+            Err(lrclib::get::ResponseError::not_found().to_string())
+        }
+    }
 }
 
 #[tauri::command]
 async fn retrieve_lyrics_by_id(
     id: i64,
+    lrclib_instance: Option<String>,
     app_handle: AppHandle,
 ) -> Result<lrclib::get_by_id::RawResponse, String> {
-    let config = app_handle
-        .db(|db: &Connection| db::get_config(db))
-        .map_err(|err| err.to_string())?;
+    lrclib::lookup::get_by_id(id, lrclib_instance.as_deref(), &app_handle)
+        .await
+        .map_err(|err| err.to_string())
+}
 
-    let response = lrclib::get_by_id::request_raw(id, &config.lrclib_instance)
+#[tauri::command]
+async fn retrieve_provider_lyrics_by_id(
+    provider: String,
+    id: String,
+    lrclib_instance: Option<String>,
+    app_handle: AppHandle,
+) -> Result<providers::LyricsCandidate, String> {
+    let kind = providers::ProviderKind::from_name(&provider)
+        .ok_or_else(|| format!("Unknown lyrics provider: {}", provider))?;
+    let provider = providers::provider(kind, lrclib_instance, &app_handle)
         .await
         .map_err(|err| err.to_string())?;
 
-    Ok(response)
+    provider.get_by_id(&id).await.map_err(|err| err.to_string())
 }
 
 #[tauri::command]
@@ -769,17 +1381,16 @@ async fn search_lyrics(
     album_name: String,
     artist_name: String,
     q: String,
+    bypass_cache: Option<bool>,
     app_handle: AppHandle,
 ) -> Result<lrclib::search::Response, String> {
-    let config = app_handle
-        .db(|db: &Connection| db::get_config(db))
-        .map_err(|err| err.to_string())?;
-    let response = lrclib::search::request(
+    let response = lrclib::lookup::search(
         &title,
         &album_name,
         &artist_name,
         &q,
-        &config.lrclib_instance,
+        bypass_cache.unwrap_or(false),
+        &app_handle,
     )
     .await
     .map_err(|err| err.to_string())?;
@@ -787,6 +1398,13 @@ async fn search_lyrics(
     Ok(response)
 }
 
+#[tauri::command]
+async fn purge_lrclib_cache(app_handle: AppHandle) -> Result<usize, String> {
+    app_handle
+        .db(|db: &Connection| db::purge_lrclib_cache(db))
+        .map_err(|err| err.to_string())
+}
+
 /// Result of preparing a lyricsfile from LRCLIB
 #[derive(Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -802,19 +1420,24 @@ struct PrepareLyricsfileResult {
 #[tauri::command]
 async fn prepare_lrclib_lyricsfile(
     lrclib_id: i64,
+    lrclib_instance: Option<String>,
     app_handle: AppHandle,
 ) -> Result<PrepareLyricsfileResult, String> {
-    // Get config for LRCLIB instance URL
-    let config = app_handle
-        .db(|db: &Connection| db::get_config(db))
+    // IDs are per instance: without the instance the lyrics came from, check every enabled one
+    let instances = lrclib::lookup::instance_clients(lrclib_instance.as_deref(), &app_handle)
         .map_err(|err| err.to_string())?;
 
-    let lrclib_instance = config.lrclib_instance;
-
     // Check if we already have this lyricsfile in the database
     let existing = app_handle
         .db(|db: &Connection| {
-            db::get_lyricsfile_by_lrclib(&lrclib_instance, lrclib_id, db)
+            for (lrclib_instance, _) in &instances {
+                if let Some(existing) =
+                    db::get_lyricsfile_by_lrclib(lrclib_instance, lrclib_id, db)?
+                {
+                    return Ok(Some(existing));
+                }
+            }
+            anyhow::Ok(None)
         })
         .map_err(|err| err.to_string())?;
 
@@ -832,9 +1455,11 @@ async fn prepare_lrclib_lyricsfile(
     }
 
     // Fetch from LRCLIB API
-    let lrclib_response = lrclib::get_by_id::request_raw(lrclib_id, &lrclib_instance)
-        .await
-        .map_err(|err| err.to_string())?;
+    let lrclib_response =
+        lrclib::lookup::get_by_id(lrclib_id, lrclib_instance.as_deref(), &app_handle)
+            .await
+            .map_err(|err| err.to_string())?;
+    let lrclib_instance = lrclib_response.lrclib_instance.clone().unwrap_or_default();
 
     // Extract metadata from LRCLIB response
     let title = lrclib_response.name.unwrap_or_default();
@@ -890,19 +1515,15 @@ async fn prepare_lrclib_lyricsfile(
 #[tauri::command]
 async fn refresh_lrclib_lyricsfile(
     lrclib_id: i64,
+    lrclib_instance: Option<String>,
     app_handle: AppHandle,
 ) -> Result<PrepareLyricsfileResult, String> {
-    // Get config for LRCLIB instance URL
-    let config = app_handle
-        .db(|db: &Connection| db::get_config(db))
-        .map_err(|err| err.to_string())?;
-
-    let lrclib_instance = config.lrclib_instance;
-
     // Fetch fresh data from LRCLIB API (always re-download)
-    let lrclib_response = lrclib::get_by_id::request_raw(lrclib_id, &lrclib_instance)
-        .await
-        .map_err(|err| err.to_string())?;
+    let lrclib_response =
+        lrclib::lookup::get_by_id(lrclib_id, lrclib_instance.as_deref(), &app_handle)
+            .await
+            .map_err(|err| err.to_string())?;
+    let lrclib_instance = lrclib_response.lrclib_instance.clone().unwrap_or_default();
 
     // Extract metadata from LRCLIB response
     let title = lrclib_response.name.unwrap_or_default();
@@ -1010,6 +1631,7 @@ async fn publish_lyrics(
     plain_lyrics: Option<String>,
     synced_lyrics: Option<String>,
     lyricsfile: Option<String>,
+    lrclib_instance: Option<String>,
     app_handle: AppHandle,
 ) -> Result<(), String> {
     let plain_lyrics = plain_lyrics.and_then(|lyrics| {
@@ -1041,20 +1663,33 @@ async fn publish_lyrics(
         return Err("No lyrics payload provided for publishing".to_owned());
     }
 
-    let config = app_handle
-        .db(|db: &Connection| db::get_config(db))
-        .map_err(|err| err.to_string())?;
+    // Publish to the chosen instance, or the one configured as the publish target
+    let lrclib_instance = match lrclib_instance {
+        Some(lrclib_instance) => lrclib_instance,
+        None => {
+            app_handle
+                .db(|db: &Connection| db::get_config(db))
+                .map_err(|err| err.to_string())?
+                .lrclib_instance
+        }
+    };
+    let (lrclib_instance, client) =
+        lrclib::lookup::instance_clients(Some(&lrclib_instance), &app_handle)
+            .map_err(|err| err.to_string())?
+            .remove(0);
 
     let mut progress = PublishLyricsProgress {
         request_challenge: "Pending".to_owned(),
         solve_challenge: "Pending".to_owned(),
         publish_lyrics: "Pending".to_owned(),
+        hash_rate: None,
+        eta_seconds: None,
     };
     progress.request_challenge = "In Progress".to_owned();
     app_handle
         .emit("publish-lyrics-progress", &progress)
         .unwrap();
-    let challenge_response = lrclib::request_challenge::request(&config.lrclib_instance)
+    let challenge_response = lrclib::request_challenge::request(&lrclib_instance, &client)
         .await
         .map_err(|err| err.to_string())?;
     progress.request_challenge = "Done".to_owned();
@@ -1062,10 +1697,23 @@ async fn publish_lyrics(
     app_handle
         .emit("publish-lyrics-progress", &progress)
         .unwrap();
-    let nonce = lrclib::challenge_solver::solve_challenge(
-        &challenge_response.prefix,
-        &challenge_response.target,
-    );
+    let mut solving_progress = progress.clone();
+    let progress_handle = app_handle.clone();
+    let nonce = solve_lrclib_challenge(&challenge_response, &app_handle, move |solve| {
+        solving_progress.hash_rate = Some(solve.hash_rate);
+        solving_progress.eta_seconds = Some(solve.eta_seconds);
+        progress_handle
+            .emit("publish-lyrics-progress", &solving_progress)
+            .unwrap();
+    })
+    .await?;
+    let Some(nonce) = nonce else {
+        progress.solve_challenge = "Cancelled".to_owned();
+        app_handle
+            .emit("publish-lyrics-progress", &progress)
+            .unwrap();
+        return Err(CHALLENGE_CANCELLED.to_owned());
+    };
     progress.solve_challenge = "Done".to_owned();
     progress.publish_lyrics = "In Progress".to_owned();
     app_handle
@@ -1081,7 +1729,8 @@ async fn publish_lyrics(
         synced_lyrics.as_deref(),
         lyricsfile.as_deref(),
         &publish_token,
-        &config.lrclib_instance,
+        &lrclib_instance,
+        &client,
     )
     .await
     .map_err(|err| err.to_string())?;
@@ -1096,29 +1745,54 @@ async fn publish_lyrics(
 async fn flag_lyrics(
     track_id: i64,
     flag_reason: String,
+    lrclib_instance: Option<String>,
     app_handle: AppHandle,
 ) -> Result<(), String> {
-    let config = app_handle
-        .db(|db: &Connection| db::get_config(db))
-        .map_err(|err| err.to_string())?;
+    // Flag on the instance the lyrics came from, or the configured publish target
+    let lrclib_instance = match lrclib_instance {
+        Some(lrclib_instance) => lrclib_instance,
+        None => {
+            app_handle
+                .db(|db: &Connection| db::get_config(db))
+                .map_err(|err| err.to_string())?
+                .lrclib_instance
+        }
+    };
+    let (lrclib_instance, client) =
+        lrclib::lookup::instance_clients(Some(&lrclib_instance), &app_handle)
+            .map_err(|err| err.to_string())?
+            .remove(0);
 
     let mut progress = FlagLyricsProgress {
         request_challenge: "Pending".to_owned(),
         solve_challenge: "Pending".to_owned(),
         flag_lyrics: "Pending".to_owned(),
+        hash_rate: None,
+        eta_seconds: None,
     };
     progress.request_challenge = "In Progress".to_owned();
     app_handle.emit("flag-lyrics-progress", &progress).unwrap();
-    let challenge_response = lrclib::request_challenge::request(&config.lrclib_instance)
+    let challenge_response = lrclib::request_challenge::request(&lrclib_instance, &client)
         .await
         .map_err(|err| err.to_string())?;
     progress.request_challenge = "Done".to_owned();
     progress.solve_challenge = "In Progress".to_owned();
     app_handle.emit("flag-lyrics-progress", &progress).unwrap();
-    let nonce = lrclib::challenge_solver::solve_challenge(
-        &challenge_response.prefix,
-        &challenge_response.target,
-    );
+    let mut solving_progress = progress.clone();
+    let progress_handle = app_handle.clone();
+    let nonce = solve_lrclib_challenge(&challenge_response, &app_handle, move |solve| {
+        solving_progress.hash_rate = Some(solve.hash_rate);
+        solving_progress.eta_seconds = Some(solve.eta_seconds);
+        progress_handle
+            .emit("flag-lyrics-progress", &solving_progress)
+            .unwrap();
+    })
+    .await?;
+    let Some(nonce) = nonce else {
+        progress.solve_challenge = "Cancelled".to_owned();
+        app_handle.emit("flag-lyrics-progress", &progress).unwrap();
+        return Err(CHALLENGE_CANCELLED.to_owned());
+    };
     progress.solve_challenge = "Done".to_owned();
     progress.flag_lyrics = "In Progress".to_owned();
     app_handle.emit("flag-lyrics-progress", &progress).unwrap();
@@ -1127,7 +1801,8 @@ async fn flag_lyrics(
         track_id,
         &flag_reason,
         &publish_token,
-        &config.lrclib_instance,
+        &lrclib_instance,
+        &client,
     )
     .await
     .map_err(|err| err.to_string())?;
@@ -1136,6 +1811,17 @@ async fn flag_lyrics(
     Ok(())
 }
 
+#[tauri::command]
+async fn cancel_challenge(app_state: State<'_, AppState>) -> Result<(), String> {
+    match app_state.challenge.lock().unwrap().as_deref() {
+        Some(cancelled) => {
+            cancelled.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+        None => Err("No LRCLIB challenge is being solved".to_string()),
+    }
+}
+
 #[tauri::command]
 async fn play_track(
     track_id: Option<i64>,
@@ -1193,6 +1879,7 @@ async fn play_track(
             instrumental: false,
             lyricsfile: None,
             lyricsfile_id: None,
+            start_offset: None,
         }
     } else {
         return Err("Either track_id or file_path must be provided".to_string());
@@ -1367,6 +2054,55 @@ async fn export_track_lyrics(
     })
 }
 
+#[tauri::command]
+async fn get_scan_errors(
+    error_kind: Option<String>,
+    search_query: Option<String>,
+    app_state: State<'_, AppState>,
+) -> Result<Vec<PersistentScanError>, String> {
+    let conn_guard = app_state.db.lock().unwrap();
+    let conn = conn_guard.as_ref().unwrap();
+    let scan_errors = db::get_scan_errors(error_kind.as_deref(), search_query.as_deref(), conn)
+        .map_err(|err| err.to_string())?;
+
+    Ok(scan_errors)
+}
+
+#[tauri::command]
+async fn clear_scan_errors(
+    error_kind: Option<String>,
+    app_state: State<'_, AppState>,
+) -> Result<usize, String> {
+    let conn_guard = app_state.db.lock().unwrap();
+    let conn = conn_guard.as_ref().unwrap();
+    let deleted_count =
+        db::clear_scan_errors(error_kind.as_deref(), conn).map_err(|err| err.to_string())?;
+
+    Ok(deleted_count)
+}
+
+#[tauri::command]
+async fn get_duplicate_tracks(
+    app_state: State<'_, AppState>,
+) -> Result<Vec<DuplicateTrackGroup>, String> {
+    let conn_guard = app_state.db.lock().unwrap();
+    let conn = conn_guard.as_ref().unwrap();
+    let groups = library::get_duplicate_tracks(conn).map_err(|err| err.to_string())?;
+
+    Ok(groups)
+}
+
+#[tauri::command]
+async fn get_metadata_inferred_track_ids(
+    app_state: State<'_, AppState>,
+) -> Result<Vec<i64>, String> {
+    let conn_guard = app_state.db.lock().unwrap();
+    let conn = conn_guard.as_ref().unwrap();
+    let track_ids = db::get_metadata_inferred_track_ids(conn).map_err(|err| err.to_string())?;
+
+    Ok(track_ids)
+}
+
 #[tauri::command]
 async fn get_track_ids_with_lyrics(app_state: State<'_, AppState>) -> Result<Vec<i64>, String> {
     let conn_guard = app_state.db.lock().unwrap();
@@ -1488,8 +2224,16 @@ async fn main() {
         .plugin(tauri_plugin_clipboard_manager::init())
         .manage(AppState {
             db: Default::default(),
+            scan_db: Default::default(),
             player: Default::default(),
             queued_notifications: std::sync::Mutex::new(Vec::new()),
+            watcher: Default::default(),
+            scan_job: Default::default(),
+            download_job: Default::default(),
+            challenge: Default::default(),
+            lrclib: LrclibClient::new(Default::default())
+                .expect("Failed to create the LRCLIB client"),
+            local_lyrics_index: Default::default(),
         })
         .setup(|app| {
             let handle = app.handle();
@@ -1497,6 +2241,9 @@ async fn main() {
             let app_state: State<AppState> = handle.state();
             let db = db::initialize_database(&handle).expect("Database initialize should succeed");
             *app_state.db.lock().unwrap() = Some(db);
+            let scan_db = db::open_writer_connection(handle)
+                .expect("Scan database connection should open");
+            *app_state.scan_db.lock().unwrap() = Some(scan_db);
 
             // Load config to get initial volume
             let initial_volume = handle
@@ -1519,6 +2266,14 @@ async fn main() {
                 }
             }
 
+            if let Err(e) = watcher::refresh_library_watcher(handle) {
+                eprintln!("Failed to start library watcher: {}", e);
+            }
+
+            if let Err(e) = mass_download::restore(handle) {
+                eprintln!("Failed to restore lyrics download: {}", e);
+            }
+
             let handle_clone = handle.clone();
 
             tokio::spawn(async move {
@@ -1553,12 +2308,21 @@ async fn main() {
         .invoke_handler(tauri::generate_handler![
             get_directories,
             set_directories,
+            get_directory_ignore_patterns,
+            set_directory_ignore_patterns,
             get_init,
+            get_tags_reread_pending,
             get_config,
             set_config,
+            get_lrclib_instances,
+            set_lrclib_instances,
             uninitialize_library,
             full_scan_library,
             scan_library,
+            scan_paths,
+            pause_scan,
+            resume_scan,
+            cancel_scan,
             get_tracks,
             get_track_ids,
             get_track,
@@ -1573,16 +2337,29 @@ async fn main() {
             get_album_track_ids,
             get_artist_track_ids,
             download_lyrics,
+            preview_lookup_variants,
+            start_mass_download,
+            get_mass_download_status,
+            pause_mass_download,
+            resume_mass_download,
+            cancel_mass_download,
             apply_lyrics,
             retrieve_lyrics,
             retrieve_lyrics_by_id,
+            retrieve_provider_lyrics_by_id,
             search_lyrics,
+            purge_lrclib_cache,
             save_lyrics,
             publish_lyrics,
             export_lyrics,
             export_track_lyrics,
             get_track_ids_with_lyrics,
+            get_metadata_inferred_track_ids,
+            get_duplicate_tracks,
+            get_scan_errors,
+            clear_scan_errors,
             flag_lyrics,
+            cancel_challenge,
             play_track,
             pause_track,
             resume_track,
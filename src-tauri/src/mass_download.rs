@@ -0,0 +1,470 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use rusqlite::Connection;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+
+use crate::db;
+use crate::persistent_entities::PersistentConfig;
+use crate::state::{AppState, ServiceAccess};
+
+/// Upper bound for parallel requests, so a job stays gentle on the LRCLIB instance
+pub const MAX_CONCURRENCY: usize = 8;
+
+/// State of a mass download job, as requested through pause/resume/cancel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadJobState {
+    Running,
+    Paused,
+    Cancelled,
+    Completed,
+}
+
+impl DownloadJobState {
+    /// Value stored in `download_jobs.state` (only unfinished jobs are stored)
+    fn as_str(self) -> &'static str {
+        match self {
+            DownloadJobState::Running => "running",
+            DownloadJobState::Paused => "paused",
+            DownloadJobState::Cancelled => "cancelled",
+            DownloadJobState::Completed => "completed",
+        }
+    }
+
+    fn from_db_value(value: &str) -> Self {
+        match value {
+            "paused" => DownloadJobState::Paused,
+            _ => DownloadJobState::Running,
+        }
+    }
+}
+
+/// Counters of a download job, sent with every event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadJobStatus {
+    pub state: DownloadJobState,
+    pub concurrency: usize,
+    pub total: usize,
+    pub downloaded: usize,
+    pub failed: usize,
+}
+
+/// Payload of `mass-download-progress`, emitted once per processed track
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadTrackProgress {
+    pub track_id: i64,
+    pub title: Option<String>,
+    pub artist_name: Option<String>,
+    pub success: bool,
+    pub message: String,
+    pub status: DownloadJobStatus,
+}
+
+struct DownloadQueue {
+    pending: VecDeque<i64>,
+    total: usize,
+    downloaded: usize,
+    failed: usize,
+    /// Set once the workers are done; tracks can't be added anymore
+    finished: bool,
+}
+
+/// A running mass download: a queue of track IDs worked off by `concurrency` workers.
+/// Every result is written to `download_job_tracks` right away, so the job continues
+/// with the remaining tracks after a restart (see `restore`).
+pub struct DownloadJob {
+    id: i64,
+    concurrency: usize,
+    /// Ask the providers even for tracks with a cached lookup
+    bypass_cache: bool,
+    state: watch::Sender<DownloadJobState>,
+    queue: Mutex<DownloadQueue>,
+}
+
+impl DownloadJob {
+    fn new(
+        id: i64,
+        concurrency: usize,
+        bypass_cache: bool,
+        state: DownloadJobState,
+        pending: Vec<i64>,
+        done: (usize, usize),
+    ) -> Self {
+        let (downloaded, failed) = done;
+
+        Self {
+            id,
+            concurrency,
+            bypass_cache,
+            state: watch::Sender::new(state),
+            queue: Mutex::new(DownloadQueue {
+                total: pending.len() + downloaded + failed,
+                pending: pending.into(),
+                downloaded,
+                failed,
+                finished: false,
+            }),
+        }
+    }
+
+    pub fn state(&self) -> DownloadJobState {
+        *self.state.borrow()
+    }
+
+    pub fn status(&self) -> DownloadJobStatus {
+        let queue = self.queue.lock().unwrap();
+
+        DownloadJobStatus {
+            state: self.state(),
+            concurrency: self.concurrency,
+            total: queue.total,
+            downloaded: queue.downloaded,
+            failed: queue.failed,
+        }
+    }
+
+    fn transition(&self, from: DownloadJobState, to: DownloadJobState) {
+        self.state.send_if_modified(|state| {
+            let modified = *state == from;
+            if modified {
+                *state = to;
+            }
+            modified
+        });
+    }
+
+    fn cancel(&self) {
+        self.state.send_if_modified(|state| {
+            let modified = matches!(state, DownloadJobState::Running | DownloadJobState::Paused);
+            if modified {
+                *state = DownloadJobState::Cancelled;
+            }
+            modified
+        });
+    }
+
+    /// Wait while the job is paused. Returns `false` if it was cancelled.
+    async fn checkpoint(&self) -> bool {
+        let mut state = self.state.subscribe();
+        let state = state
+            .wait_for(|state| *state != DownloadJobState::Paused)
+            .await
+            .map(|state| *state);
+
+        matches!(state, Ok(DownloadJobState::Running))
+    }
+
+    fn next_track(&self) -> Option<i64> {
+        self.queue.lock().unwrap().pending.pop_front()
+    }
+
+    /// Queue the tracks `add` stores in the job. Returns `false` without calling it if the
+    /// job already finished. The queue stays locked meanwhile, so the job cannot finish
+    /// between storing the tracks and queueing them.
+    fn append(&self, add: impl FnOnce() -> Result<Vec<i64>>) -> Result<bool> {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.finished {
+            return Ok(false);
+        }
+
+        let track_ids = add()?;
+        queue.pending.extend(&track_ids);
+        queue.total += track_ids.len();
+        Ok(true)
+    }
+
+    fn record(&self, success: bool) -> DownloadJobStatus {
+        {
+            let mut queue = self.queue.lock().unwrap();
+            if success {
+                queue.downloaded += 1;
+            } else {
+                queue.failed += 1;
+            }
+        }
+
+        self.status()
+    }
+
+    /// Mark the job finished unless tracks were queued after the workers stopped
+    fn finish(&self) -> bool {
+        let mut queue = self.queue.lock().unwrap();
+        let cancelled = self.state() == DownloadJobState::Cancelled;
+        if !cancelled && !queue.pending.is_empty() {
+            return false;
+        }
+
+        queue.finished = true;
+        if !cancelled {
+            self.state.send_replace(DownloadJobState::Completed);
+        }
+        true
+    }
+}
+
+/// Tracks a download of the whole library covers: those without lyrics, plus those whose
+/// kind of lyrics the config does not skip. Offline tracks are left out, since their lyrics
+/// could not be saved next to them.
+pub fn select_track_ids(config: &PersistentConfig, conn: &Connection) -> Result<Vec<i64>> {
+    let synced_lyrics = !config.skip_tracks_with_synced_lyrics;
+    let plain_lyrics = !config.skip_tracks_with_plain_lyrics;
+
+    // Instrumental tracks count as having either kind of lyrics
+    let mut track_ids = db::get_track_ids(
+        synced_lyrics,
+        plain_lyrics,
+        synced_lyrics && plain_lyrics,
+        true,
+        conn,
+    )?;
+
+    let offline: HashSet<i64> = db::get_offline_track_ids(conn)?.into_iter().collect();
+    track_ids.retain(|track_id| !offline.contains(track_id));
+
+    Ok(track_ids)
+}
+
+/// Download lyrics for the given tracks, or for the whole library (see `select_track_ids`).
+/// Given tracks are filtered the same way, so the skip settings hold for them too. While a
+/// job is running the tracks are added to it instead, skipping those already queued; they
+/// are downloaded with that job's concurrency and `bypass_cache`.
+pub fn start(
+    app_handle: &AppHandle,
+    track_ids: Option<Vec<i64>>,
+    concurrency: Option<usize>,
+    bypass_cache: bool,
+) -> Result<DownloadJobStatus> {
+    let config = app_handle.db(db::get_config)?;
+    let selected_track_ids = app_handle.db(|db| select_track_ids(&config, db))?;
+    let track_ids = match track_ids {
+        Some(mut track_ids) => {
+            let selected: HashSet<i64> = selected_track_ids.into_iter().collect();
+            track_ids.retain(|track_id| selected.contains(track_id));
+            track_ids
+        }
+        None => selected_track_ids,
+    };
+
+    let app_state: State<AppState> = app_handle.state();
+    let mut download_job = app_state.download_job.lock().unwrap();
+
+    if let Some(job) = download_job.as_ref() {
+        let appended = job.append(|| {
+            app_handle.db_mut(|db| db::add_download_job_tracks(job.id, &track_ids, db))
+        })?;
+        if appended {
+            return Ok(job.status());
+        }
+    }
+
+    if track_ids.is_empty() {
+        return Err(anyhow!("There are no tracks to download"));
+    }
+
+    let concurrency = concurrency
+        .unwrap_or(config.download_concurrency as usize)
+        .clamp(1, MAX_CONCURRENCY);
+    let state = DownloadJobState::Running;
+    let job_id = app_handle.db_mut(|db| -> Result<i64> {
+        let job_id = db::add_download_job(state.as_str(), concurrency as i64, bypass_cache, db)?;
+        db::add_download_job_tracks(job_id, &track_ids, db)?;
+        Ok(job_id)
+    })?;
+    let pending = app_handle.db(|db| db::get_pending_download_job_track_ids(job_id, db))?;
+
+    let job = Arc::new(DownloadJob::new(
+        job_id,
+        concurrency,
+        bypass_cache,
+        state,
+        pending,
+        (0, 0),
+    ));
+    *download_job = Some(Arc::clone(&job));
+    tauri::async_runtime::spawn(run(app_handle.clone(), Arc::clone(&job)));
+
+    Ok(job.status())
+}
+
+/// Continue the job left unfinished by the previous session, in the state it was left in
+pub fn restore(app_handle: &AppHandle) -> Result<()> {
+    let Some(persisted) = app_handle.db(db::get_download_job)? else {
+        return Ok(());
+    };
+    let pending = app_handle.db(|db| db::get_pending_download_job_track_ids(persisted.id, db))?;
+
+    let job = Arc::new(DownloadJob::new(
+        persisted.id,
+        (persisted.concurrency as usize).clamp(1, MAX_CONCURRENCY),
+        persisted.bypass_cache,
+        DownloadJobState::from_db_value(&persisted.state),
+        pending,
+        (persisted.downloaded as usize, persisted.failed as usize),
+    ));
+
+    let app_state: State<AppState> = app_handle.state();
+    *app_state.download_job.lock().unwrap() = Some(Arc::clone(&job));
+    tauri::async_runtime::spawn(run(app_handle.clone(), job));
+
+    Ok(())
+}
+
+/// Status of the current job, if there is one
+pub fn status(app_handle: &AppHandle) -> Option<DownloadJobStatus> {
+    let app_state: State<AppState> = app_handle.state();
+    let download_job = app_state.download_job.lock().unwrap();
+    download_job.as_ref().map(|job| job.status())
+}
+
+/// Stop starting new downloads; requests already sent still complete
+pub fn pause(app_handle: &AppHandle) -> Result<DownloadJobStatus> {
+    control(app_handle, |job| {
+        job.transition(DownloadJobState::Running, DownloadJobState::Paused)
+    })
+}
+
+pub fn resume(app_handle: &AppHandle) -> Result<DownloadJobStatus> {
+    control(app_handle, |job| {
+        job.transition(DownloadJobState::Paused, DownloadJobState::Running)
+    })
+}
+
+/// Drop the remaining tracks; the job is removed once requests already sent complete
+pub fn cancel(app_handle: &AppHandle) -> Result<DownloadJobStatus> {
+    control(app_handle, DownloadJob::cancel)
+}
+
+/// Apply a state change to the current job and persist its new state
+fn control(app_handle: &AppHandle, change: impl FnOnce(&DownloadJob)) -> Result<DownloadJobStatus> {
+    let app_state: State<AppState> = app_handle.state();
+    let job = app_state
+        .download_job
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| anyhow!("No lyrics download is running"))?;
+
+    change(&job);
+    app_handle.db(|db| db::set_download_job_state(job.id, job.state().as_str(), db))?;
+
+    let status = job.status();
+    let _ = app_handle.emit("mass-download-state", &status);
+    Ok(status)
+}
+
+/// Work off the job's queue, then forget the job and emit `mass-download-complete`
+async fn run(app_handle: AppHandle, job: Arc<DownloadJob>) {
+    loop {
+        let mut workers = JoinSet::new();
+        for _ in 0..job.concurrency {
+            workers.spawn(work(app_handle.clone(), Arc::clone(&job)));
+        }
+        while workers.join_next().await.is_some() {}
+
+        if job.finish() {
+            break;
+        }
+    }
+
+    if let Err(error) = app_handle.db_mut(|db| db::delete_download_job(job.id, db)) {
+        eprintln!("Failed to delete download job {}: {}", job.id, error);
+    }
+
+    let app_state: State<AppState> = app_handle.state();
+    {
+        let mut download_job = app_state.download_job.lock().unwrap();
+        if download_job
+            .as_ref()
+            .is_some_and(|current| Arc::ptr_eq(current, &job))
+        {
+            *download_job = None;
+        }
+    }
+
+    let _ = app_handle.emit("mass-download-complete", job.status());
+}
+
+/// One worker: download lyrics for queued tracks one at a time until the queue is empty
+async fn work(app_handle: AppHandle, job: Arc<DownloadJob>) {
+    while job.checkpoint().await {
+        let Some(track_id) = job.next_track() else {
+            break;
+        };
+
+        let track = app_handle.db(|db| db::get_track_by_id(track_id, db));
+        let result = match &track {
+            Ok(track) => crate::download_track_lyrics(track, job.bypass_cache, &app_handle).await,
+            Err(error) => Err(error.to_string()),
+        };
+        let (download_status, message) = match result {
+            Ok(message) => (db::DOWNLOAD_STATUS_DOWNLOADED, message),
+            Err(message) => (db::DOWNLOAD_STATUS_FAILED, message),
+        };
+
+        if let Err(error) = app_handle.db(|db| {
+            db::set_download_job_track_status(job.id, track_id, download_status, &message, db)
+        }) {
+            eprintln!("Failed to record download of track {}: {}", track_id, error);
+        }
+
+        let progress = DownloadTrackProgress {
+            track_id,
+            title: track.as_ref().ok().map(|track| track.title.clone()),
+            artist_name: track.as_ref().ok().map(|track| track.artist_name.clone()),
+            success: download_status == db::DOWNLOAD_STATUS_DOWNLOADED,
+            message,
+            status: job.record(download_status == db::DOWNLOAD_STATUS_DOWNLOADED),
+        };
+        let _ = app_handle.emit("mass-download-progress", &progress);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancel_wakes_paused_workers() {
+        let job = Arc::new(DownloadJob::new(
+            1,
+            2,
+            false,
+            DownloadJobState::Paused,
+            vec![1, 2],
+            (0, 0),
+        ));
+
+        let worker = {
+            let job = Arc::clone(&job);
+            tokio::spawn(async move { job.checkpoint().await })
+        };
+        job.cancel();
+
+        assert!(!worker.await.unwrap());
+        assert!(job.finish());
+        assert_eq!(job.state(), DownloadJobState::Cancelled);
+    }
+
+    #[test]
+    fn test_finished_job_takes_no_more_tracks() {
+        let job = DownloadJob::new(1, 1, false, DownloadJobState::Running, vec![1], (2, 1));
+        assert_eq!(job.status().total, 4);
+
+        assert!(!job.finish());
+        assert_eq!(job.next_track(), Some(1));
+        job.record(true);
+        assert!(job.finish());
+
+        let appended = job.append(|| panic!("A finished job stores no tracks"));
+        assert!(!appended.unwrap());
+        assert_eq!(job.state(), DownloadJobState::Completed);
+        assert_eq!(job.status().downloaded, 3);
+    }
+}
@@ -150,6 +150,29 @@ pub fn format_timestamp(timestamp_ms: i64) -> String {
     format!("[{:02}:{:02}.{:02}]", minutes, seconds, centiseconds)
 }
 
+/// Move the timestamps of every timed line by `offset_ms` (clamped at 0), keeping
+/// ID tags and untimed lines as they are
+pub fn shift_timestamps(input: &str, offset_ms: i64) -> String {
+    input
+        .lines()
+        .map(|line| {
+            let parsed = parse_lrc(line);
+            if parsed.timed_lines.is_empty() {
+                return line.to_string();
+            }
+
+            let text = &parsed.timed_lines[0].text;
+            let timestamps: String = parsed
+                .timed_lines
+                .iter()
+                .map(|timed_line| format_timestamp(timed_line.timestamp_ms + offset_ms))
+                .collect();
+            format!("{} {}", timestamps, text).trim_end().to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -272,4 +295,14 @@ mod tests {
         assert_eq!(parsed.timed_lines.len(), 0);
         assert_eq!(parsed.id_tags.len(), 0);
     }
+
+    #[test]
+    fn test_shift_timestamps() {
+        let lrc = "[ti:Title]\n[01:05.50] Line one\n[01:10.00][01:20.25] Chorus\n[01:30.00]";
+
+        assert_eq!(
+            shift_timestamps(lrc, -60_000),
+            "[ti:Title]\n[00:05.50] Line one\n[00:10.00][00:20.25] Chorus\n[00:30.00]"
+        );
+    }
 }
@@ -1,4 +1,4 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 /// A track that can be played - either from database or from a file path.
 /// This is used by the player and can represent both scanned library tracks
@@ -19,6 +19,7 @@ pub struct PlayableTrack {
     pub instrumental: bool,
     pub lyricsfile: Option<String>,
     pub lyricsfile_id: Option<i64>, // ID from lyricsfiles table
+    pub start_offset: Option<f64>,  // Start within the file for CUE sheet virtual tracks
 }
 
 impl From<PersistentTrack> for PlayableTrack {
@@ -37,6 +38,7 @@ impl From<PersistentTrack> for PlayableTrack {
             instrumental: track.instrumental,
             lyricsfile: track.lyricsfile,
             lyricsfile_id: track.lyricsfile_id,
+            start_offset: track.start_offset,
         }
     }
 }
@@ -50,8 +52,9 @@ pub struct PersistentTrack {
     pub album_name: String,
     pub album_artist_name: Option<String>,
     pub album_id: i64,
-    pub artist_name: String,
+    pub artist_name: String, // Primary credited artist
     pub artist_id: i64,
+    pub artist_credit: Option<String>, // Artist tag as written, e.g. "A feat. B"
     pub image_path: Option<String>,
     pub track_number: Option<i64>,
     pub txt_lyrics: Option<String>,
@@ -60,6 +63,18 @@ pub struct PersistentTrack {
     pub lyricsfile_id: Option<i64>, // ID from lyricsfiles table (null if no lyricsfile exists)
     pub duration: f64,
     pub instrumental: bool,
+    pub metadata_inferred: bool,
+    pub start_offset: Option<f64>, // Start within the file for CUE sheet virtual tracks
+    pub offline: bool,             // Library directory was unavailable during the last scan
+    pub disc_number: Option<i64>,
+    pub year: Option<i64>,
+    pub genre: Option<String>,
+    pub composer: Option<String>,
+    pub musicbrainz_recording_id: Option<String>,
+    pub isrc: Option<String>,
+    pub lrclib_not_found_at: Option<String>, // When a download last found nothing on LRCLIB
+    pub lrclib_match_score: Option<f64>,     // Confidence of the search fallback result
+    pub lrclib_match_reason: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -69,6 +84,9 @@ pub struct PersistentAlbum {
     pub image_path: Option<String>,
     pub artist_name: String,
     pub album_artist_name: Option<String>,
+    pub album_artist_sort_name: Option<String>,
+    pub year: Option<i64>,
+    pub musicbrainz_release_id: Option<String>,
     pub tracks_count: i64,
 }
 
@@ -89,4 +107,63 @@ pub struct PersistentConfig {
     pub theme_mode: String,
     pub lrclib_instance: String,
     pub volume: f64,
+    pub lyrics_source_precedence: String,
+    pub watch_library: bool,
+    pub scan_worker_count: u32,
+    pub path_templates: String,
+    pub audio_extensions: String,
+    pub artist_separators: String,
+    pub search_fallback_min_confidence: f64,
+    pub lookup_rules: String,
+    pub lyrics_providers: String,
+    pub local_lyrics_folder: String,
+    pub download_concurrency: u32,
+    /// `DetectionMethod` config value: `hash`, `full_hash` or `metadata`
+    pub detection_method: String,
+}
+
+/// An LRCLIB instance lyrics are looked up on, as persisted in `lrclib_instances`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PersistentLrclibInstance {
+    pub url: String,
+    pub enabled: bool,
+    /// Seconds a request may take, `None` for the client's default
+    pub timeout_seconds: Option<u32>,
+}
+
+/// Why the tracks of a `DuplicateTrackGroup` are considered duplicates
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateReason {
+    /// Several files with the same content hash
+    ContentHash,
+    /// Same normalized title and artist, with about the same duration
+    Metadata,
+}
+
+#[derive(Serialize)]
+pub struct DuplicateTrackGroup {
+    pub reason: DuplicateReason,
+    pub track_ids: Vec<i64>,
+}
+
+/// A mass download job that has not finished, as persisted in `download_jobs`
+#[derive(Serialize)]
+pub struct PersistentDownloadJob {
+    pub id: i64,
+    pub state: String,
+    pub concurrency: i64,
+    pub bypass_cache: bool,
+    pub total: i64,
+    pub downloaded: i64,
+    pub failed: i64,
+}
+
+#[derive(Serialize)]
+pub struct PersistentScanError {
+    pub id: i64,
+    pub file_path: String,
+    pub error_kind: String,
+    pub message: String,
+    pub created_at: String,
 }
@@ -77,7 +77,15 @@ impl Player {
         self.track = Some(track);
 
         if let Some(ref mut track) = self.track {
-            let sound_data = StreamingSoundData::from_file(&track.file_path)?;
+            let mut sound_data = StreamingSoundData::from_file(&track.file_path)?;
+
+            // Virtual tracks from a CUE sheet only play their range of the file; positions
+            // and duration are then relative to the track start
+            if let Some(start) = track.start_offset {
+                let end =
+                    (start + track.duration).min(sound_data.unsliced_duration().as_secs_f64());
+                sound_data = sound_data.slice(start..end);
+            }
 
             self.duration = sound_data.duration().as_secs_f64();
             self.sound_handle = Some(self.manager.play(sound_data)?);
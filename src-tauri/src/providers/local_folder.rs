@@ -0,0 +1,368 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use globwalk::glob_builder;
+
+use super::{LyricsCandidate, LyricsLookup, LyricsProvider, LyricsQuery, ProviderFuture};
+use crate::lyricsfile::{self, INSTRUMENTAL_LRC};
+use crate::parser::lrc::{is_instrumental_lrc, parse_lrc};
+use crate::utils::{prepare_input, prepare_search_input};
+
+pub const PROVIDER_NAME: &str = "local_folder";
+
+/// Files the folder is indexed from: LRC and plain text lyrics, and YAML lyricsfiles
+const LYRICS_EXTENSIONS: &[&str] = &["lrc", "txt", "yaml", "yml"];
+/// Durations within this many seconds count as equal, like LRCLIB's /api/get matching
+const DURATION_TOLERANCE: f64 = 2.0;
+/// An index older than this is rebuilt, so files added to the folder are picked up
+pub const INDEX_MAX_AGE: Duration = Duration::from_secs(60);
+
+/// Lyrics files found in the folder, with the normalized tags they are matched by
+pub struct LocalFolderIndex {
+    pub folder: PathBuf,
+    built_at: Instant,
+    entries: Vec<IndexEntry>,
+}
+
+struct IndexEntry {
+    candidate: LyricsCandidate,
+    title: String,
+    album_name: String,
+    artist_name: String,
+}
+
+impl LocalFolderIndex {
+    /// Read every lyrics file below `folder`. Files that cannot be read or have no title are
+    /// skipped.
+    pub fn build(folder: &Path) -> Result<Self> {
+        let pattern = format!(
+            "{}/**/*.{{{}}}",
+            folder.display(),
+            LYRICS_EXTENSIONS.join(",")
+        );
+        let walker = glob_builder(pattern).case_insensitive(true).build()?;
+
+        let mut entries = Vec::new();
+        for entry in walker.filter_map(|entry| entry.ok()) {
+            match read_lyrics_file(entry.path()) {
+                Ok(Some(candidate)) => entries.push(IndexEntry::new(candidate)),
+                Ok(None) => {}
+                Err(error) => eprintln!("Skipping {}: {}", entry.path().display(), error),
+            }
+        }
+
+        Ok(Self {
+            folder: folder.to_owned(),
+            built_at: Instant::now(),
+            entries,
+        })
+    }
+
+    pub fn is_fresh(&self) -> bool {
+        self.built_at.elapsed() < INDEX_MAX_AGE
+    }
+
+    /// The first file whose title and artist equal the query (ignoring case, accents and
+    /// punctuation), and whose album and duration do too when both sides have one
+    fn get(&self, query: &LyricsQuery) -> Option<&LyricsCandidate> {
+        let title = prepare_input(&query.title);
+        let album_name = prepare_input(&query.album_name);
+        let artist_name = prepare_input(&query.artist_name);
+
+        self.entries
+            .iter()
+            .find(|entry| {
+                entry.title == title
+                    && entry.artist_name == artist_name
+                    && (entry.album_name.is_empty()
+                        || album_name.is_empty()
+                        || entry.album_name == album_name)
+                    && entry.candidate.duration.map_or(true, |duration| {
+                        query.duration <= 0.0
+                            || (duration - query.duration).abs() <= DURATION_TOLERANCE
+                    })
+            })
+            .map(|entry| &entry.candidate)
+    }
+
+    /// Files containing every word of `q` in their title, artist or album, or without `q`
+    /// every word of the query's title (without bracketed parts), artist and album in the
+    /// respective tag
+    fn search(&self, query: &LyricsQuery, q: &str) -> Vec<LyricsCandidate> {
+        let q = prepare_input(q);
+        let title = prepare_search_input(&query.title);
+        let album_name = prepare_input(&query.album_name);
+        let artist_name = prepare_input(&query.artist_name);
+
+        self.entries
+            .iter()
+            .filter(|entry| {
+                if !q.is_empty() {
+                    let tags =
+                        format!("{} {} {}", entry.title, entry.artist_name, entry.album_name);
+                    return contains_words(&tags, &q);
+                }
+
+                contains_words(&entry.title, &title)
+                    && contains_words(&entry.artist_name, &artist_name)
+                    && contains_words(&entry.album_name, &album_name)
+            })
+            .map(|entry| entry.candidate.clone())
+            .collect()
+    }
+
+    fn get_by_id(&self, id: &str) -> Option<&LyricsCandidate> {
+        self.entries
+            .iter()
+            .map(|entry| &entry.candidate)
+            .find(|candidate| candidate.id == id)
+    }
+}
+
+impl IndexEntry {
+    fn new(candidate: LyricsCandidate) -> Self {
+        let normalize = |value: &Option<String>| prepare_input(value.as_deref().unwrap_or(""));
+
+        Self {
+            title: normalize(&candidate.name),
+            album_name: normalize(&candidate.album_name),
+            artist_name: normalize(&candidate.artist_name),
+            candidate,
+        }
+    }
+}
+
+/// The `.lrc`, `.txt` and lyricsfile files of a folder, matched by their `[ti:]`, `[ar:]` and
+/// `[al:]` tags (or lyricsfile metadata). The ID of a candidate is the path of its file.
+pub struct LocalFolderProvider {
+    index: Arc<LocalFolderIndex>,
+}
+
+impl LocalFolderProvider {
+    pub fn new(index: Arc<LocalFolderIndex>) -> Self {
+        Self { index }
+    }
+}
+
+impl LyricsProvider for LocalFolderProvider {
+    fn name(&self) -> &'static str {
+        PROVIDER_NAME
+    }
+
+    fn get<'a>(
+        &'a self,
+        query: &'a LyricsQuery,
+        _bypass_cache: bool,
+    ) -> ProviderFuture<'a, LyricsLookup> {
+        Box::pin(async move {
+            Ok(match self.index.get(query) {
+                Some(candidate) => LyricsLookup::Found(Box::new(candidate.clone())),
+                None => LyricsLookup::NotFound { checked_at: None },
+            })
+        })
+    }
+
+    fn search<'a>(
+        &'a self,
+        query: &'a LyricsQuery,
+        q: &'a str,
+        _bypass_cache: bool,
+    ) -> ProviderFuture<'a, Vec<LyricsCandidate>> {
+        Box::pin(async move { Ok(self.index.search(query, q)) })
+    }
+
+    fn get_by_id<'a>(&'a self, id: &'a str) -> ProviderFuture<'a, LyricsCandidate> {
+        Box::pin(async move {
+            self.index.get_by_id(id).cloned().ok_or_else(|| {
+                anyhow!(
+                    "{} is not a lyrics file in {}",
+                    id,
+                    self.index.folder.display()
+                )
+            })
+        })
+    }
+}
+
+/// Read a lyrics file into a candidate, or `None` when it has no title to be matched by
+fn read_lyrics_file(path: &Path) -> Result<Option<LyricsCandidate>> {
+    let content = std::fs::read_to_string(path)?;
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    let candidate = match extension.as_str() {
+        "yaml" | "yml" => Some(read_lyricsfile(path, content)?),
+        _ => read_lrc_or_txt(path, &content),
+    };
+
+    Ok(candidate.filter(|candidate| candidate.name.is_some()))
+}
+
+fn read_lrc_or_txt(path: &Path, content: &str) -> Option<LyricsCandidate> {
+    let parsed = parse_lrc(content);
+    let tag = |key: &str| {
+        parsed
+            .id_tags
+            .iter()
+            .find(|(tag_key, value)| tag_key == key && !value.is_empty())
+            .map(|(_, value)| value.clone())
+    };
+
+    let instrumental = is_instrumental_lrc(content);
+    let (plain_lyrics, synced_lyrics) = if instrumental {
+        (None, Some(INSTRUMENTAL_LRC.to_owned()))
+    } else if !parsed.timed_lines.is_empty() {
+        (None, Some(content.to_owned()))
+    } else {
+        let plain_lyrics = strip_id_tag_lines(content);
+        if plain_lyrics.is_empty() {
+            return None;
+        }
+        (Some(plain_lyrics), None)
+    };
+
+    Some(LyricsCandidate {
+        provider: PROVIDER_NAME.to_owned(),
+        id: path.to_string_lossy().into_owned(),
+        name: tag("ti"),
+        artist_name: tag("ar"),
+        album_name: tag("al"),
+        duration: tag("length").and_then(|length| parse_length(&length)),
+        instrumental,
+        plain_lyrics,
+        synced_lyrics,
+        lyricsfile: None,
+        lrclib_instance: None,
+    })
+}
+
+fn read_lyricsfile(path: &Path, content: String) -> Result<LyricsCandidate> {
+    let metadata = lyricsfile::parse_lyricsfile_metadata(&content)?;
+    let parsed = lyricsfile::parse_lyricsfile(&content)?;
+    let non_empty = |value: String| Some(value).filter(|value| !value.trim().is_empty());
+
+    Ok(LyricsCandidate {
+        provider: PROVIDER_NAME.to_owned(),
+        id: path.to_string_lossy().into_owned(),
+        name: non_empty(metadata.title),
+        artist_name: non_empty(metadata.artist_name),
+        album_name: non_empty(metadata.album_name),
+        duration: Some(metadata.duration).filter(|duration| *duration > 0.0),
+        instrumental: parsed.is_instrumental,
+        plain_lyrics: parsed.plain_lyrics,
+        synced_lyrics: parsed.synced_lyrics,
+        lyricsfile: Some(content),
+        lrclib_instance: None,
+    })
+}
+
+/// Plain lyrics without the lines that only hold an ID tag like `[ar: Queen]`. Section
+/// markers like `[Chorus]` are kept.
+fn strip_id_tag_lines(content: &str) -> String {
+    content
+        .lines()
+        .filter(|line| {
+            let line = line.trim();
+            !(line.starts_with('[') && line.ends_with(']') && line.contains(':'))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_owned()
+}
+
+/// Seconds of an LRC `[length:]` tag like `03:25` or `3:25.50`
+fn parse_length(length: &str) -> Option<f64> {
+    let (minutes, seconds) = length.split_once(':')?;
+    let minutes: f64 = minutes.trim().parse().ok()?;
+    let seconds: f64 = seconds.trim().parse().ok()?;
+
+    Some(minutes * 60.0 + seconds)
+}
+
+/// Whether every word of `needle` is a word of `haystack` (both already normalized)
+fn contains_words(haystack: &str, needle: &str) -> bool {
+    let words: Vec<&str> = haystack.split_whitespace().collect();
+    needle.split_whitespace().all(|word| words.contains(&word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(title: &str, album_name: &str, artist_name: &str, duration: f64) -> LyricsQuery {
+        LyricsQuery {
+            title: title.to_owned(),
+            album_name: album_name.to_owned(),
+            artist_name: artist_name.to_owned(),
+            duration,
+        }
+    }
+
+    #[test]
+    fn test_read_lrc_or_txt() {
+        let path = Path::new("/lyrics/queen.lrc");
+        let synced = read_lrc_or_txt(
+            path,
+            "[ti:Don't Stop Me Now]\n[ar:Queen]\n[al:Jazz]\n[length: 03:29.50]\n[00:01.00] Tonight",
+        )
+        .unwrap();
+        assert_eq!(synced.name.as_deref(), Some("Don't Stop Me Now"));
+        assert_eq!(synced.artist_name.as_deref(), Some("Queen"));
+        assert_eq!(synced.album_name.as_deref(), Some("Jazz"));
+        assert_eq!(synced.duration, Some(209.5));
+        assert!(synced.synced_lyrics.is_some());
+        assert_eq!(synced.id, "/lyrics/queen.lrc");
+
+        let plain =
+            read_lrc_or_txt(path, "[ti:Under Pressure]\n[ar:Queen]\n[Chorus]\nPressure").unwrap();
+        assert_eq!(plain.plain_lyrics.as_deref(), Some("[Chorus]\nPressure"));
+        assert!(plain.synced_lyrics.is_none());
+
+        assert!(read_lrc_or_txt(path, "[ti:Nothing]\n[ar:Queen]\n").is_none());
+    }
+
+    #[test]
+    fn test_index_get_and_search() {
+        let lrc =
+            "[ti:Don't Stop Me Now]\n[ar:Queen]\n[al:Jazz]\n[length:3:29]\n[00:01.00] Tonight";
+        let txt = "[ti:Under Pressure]\n[ar:Queen & David Bowie]\nPressure";
+        let index = LocalFolderIndex {
+            folder: PathBuf::from("/lyrics"),
+            built_at: Instant::now(),
+            entries: vec![
+                IndexEntry::new(read_lrc_or_txt(Path::new("/lyrics/a.lrc"), lrc).unwrap()),
+                IndexEntry::new(read_lrc_or_txt(Path::new("/lyrics/b.txt"), txt).unwrap()),
+            ],
+        };
+
+        let found = index
+            .get(&query("Don't stop me now", "JAZZ", "queen", 210.0))
+            .unwrap();
+        assert_eq!(found.id, "/lyrics/a.lrc");
+        assert!(index
+            .get(&query("Don't Stop Me Now", "Jazz", "Queen", 240.0))
+            .is_none());
+        assert!(index
+            .get(&query(
+                "Under Pressure",
+                "Hot Space",
+                "Queen & David Bowie",
+                248.0
+            ))
+            .is_some());
+
+        let results = index.search(&query("Under Pressure (Remastered)", "", "Queen", 0.0), "");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "/lyrics/b.txt");
+        assert_eq!(index.search(&query("", "", "", 0.0), "queen jazz").len(), 1);
+
+        assert!(index.get_by_id("/lyrics/b.txt").is_some());
+        assert!(index.get_by_id("/etc/passwd").is_none());
+    }
+}
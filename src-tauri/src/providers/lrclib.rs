@@ -0,0 +1,158 @@
+use anyhow::anyhow;
+use tauri::AppHandle;
+
+use super::{LyricsCandidate, LyricsLookup, LyricsProvider, LyricsQuery, ProviderFuture};
+use crate::lrclib::{get, get_by_id, lookup, search};
+
+pub const PROVIDER_NAME: &str = "lrclib";
+
+/// LRCLIB, asked through the `lrclib_cache` on each enabled instance in order
+pub struct LrclibProvider {
+    app_handle: AppHandle,
+    /// Instance `get_by_id` asks, since IDs are only valid on their own instance
+    lrclib_instance: Option<String>,
+}
+
+impl LrclibProvider {
+    pub fn new(app_handle: AppHandle, lrclib_instance: Option<String>) -> Self {
+        Self {
+            app_handle,
+            lrclib_instance,
+        }
+    }
+}
+
+impl LyricsProvider for LrclibProvider {
+    fn name(&self) -> &'static str {
+        PROVIDER_NAME
+    }
+
+    fn get<'a>(
+        &'a self,
+        query: &'a LyricsQuery,
+        bypass_cache: bool,
+    ) -> ProviderFuture<'a, LyricsLookup> {
+        Box::pin(async move {
+            lookup::get(
+                &query.title,
+                &query.album_name,
+                &query.artist_name,
+                query.duration,
+                bypass_cache,
+                &self.app_handle,
+            )
+            .await
+        })
+    }
+
+    fn search<'a>(
+        &'a self,
+        query: &'a LyricsQuery,
+        q: &'a str,
+        bypass_cache: bool,
+    ) -> ProviderFuture<'a, Vec<LyricsCandidate>> {
+        Box::pin(async move {
+            let response = lookup::search(
+                &query.title,
+                &query.album_name,
+                &query.artist_name,
+                q,
+                bypass_cache,
+                &self.app_handle,
+            )
+            .await?;
+
+            Ok(response.0.into_iter().map(Into::into).collect())
+        })
+    }
+
+    fn get_by_id<'a>(&'a self, id: &'a str) -> ProviderFuture<'a, LyricsCandidate> {
+        Box::pin(async move {
+            let lrclib_id: i64 = id
+                .parse()
+                .map_err(|_| anyhow!("Invalid LRCLIB ID: {}", id))?;
+            let response =
+                lookup::get_by_id(lrclib_id, self.lrclib_instance.as_deref(), &self.app_handle)
+                    .await?;
+
+            Ok(from_get_by_id_response(lrclib_id, response))
+        })
+    }
+}
+
+impl From<get::RawResponse> for LyricsCandidate {
+    fn from(response: get::RawResponse) -> Self {
+        LyricsCandidate {
+            provider: PROVIDER_NAME.to_owned(),
+            id: response.id.map(|id| id.to_string()).unwrap_or_default(),
+            name: response.name,
+            artist_name: response.artist_name,
+            album_name: response.album_name,
+            duration: response.duration,
+            instrumental: response.instrumental,
+            plain_lyrics: response.plain_lyrics,
+            synced_lyrics: response.synced_lyrics,
+            lyricsfile: response.lyricsfile,
+            lrclib_instance: response.lrclib_instance,
+        }
+    }
+}
+
+impl From<search::SearchItem> for LyricsCandidate {
+    fn from(item: search::SearchItem) -> Self {
+        LyricsCandidate {
+            provider: PROVIDER_NAME.to_owned(),
+            id: item.id.to_string(),
+            name: item.name,
+            artist_name: item.artist_name,
+            album_name: item.album_name,
+            duration: item.duration,
+            instrumental: item.instrumental,
+            plain_lyrics: item.plain_lyrics,
+            synced_lyrics: item.synced_lyrics,
+            lyricsfile: item.lyricsfile,
+            lrclib_instance: item.lrclib_instance,
+        }
+    }
+}
+
+fn from_get_by_id_response(id: i64, response: get_by_id::RawResponse) -> LyricsCandidate {
+    LyricsCandidate {
+        provider: PROVIDER_NAME.to_owned(),
+        id: id.to_string(),
+        name: response.name,
+        artist_name: response.artist_name,
+        album_name: response.album_name,
+        duration: response.duration,
+        instrumental: response.instrumental,
+        plain_lyrics: response.plain_lyrics,
+        synced_lyrics: response.synced_lyrics,
+        lyricsfile: response.lyricsfile,
+        lrclib_instance: response.lrclib_instance,
+    }
+}
+
+/// Candidates of every provider are stored through the LRCLIB response path, which knows how
+/// to turn provided lyricsfiles and plain/synced lyrics into a lyricsfile
+impl From<LyricsCandidate> for get::RawResponse {
+    fn from(candidate: LyricsCandidate) -> Self {
+        get::RawResponse {
+            id: (candidate.provider == PROVIDER_NAME)
+                .then(|| candidate.id.parse().ok())
+                .flatten(),
+            plain_lyrics: candidate.plain_lyrics,
+            synced_lyrics: candidate.synced_lyrics,
+            lyricsfile: candidate.lyricsfile,
+            instrumental: candidate.instrumental,
+            lang: None,
+            isrc: None,
+            spotify_id: None,
+            name: candidate.name,
+            album_name: candidate.album_name,
+            artist_name: candidate.artist_name,
+            release_date: None,
+            duration: candidate.duration,
+            lrclib_instance: candidate.lrclib_instance,
+        }
+    }
+}
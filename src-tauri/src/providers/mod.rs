@@ -0,0 +1,215 @@
+pub mod local_folder;
+pub mod lrclib;
+
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::db;
+use crate::state::{AppState, ServiceAccess};
+use local_folder::{LocalFolderIndex, LocalFolderProvider};
+use lrclib::LrclibProvider;
+
+/// Providers used when the user has not configured any, one per line in priority order
+pub const DEFAULT_LYRICS_PROVIDERS: &str = "lrclib";
+
+pub type ProviderFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// A source of lyrics that tracks can be looked up on
+pub trait LyricsProvider: Send + Sync {
+    /// Name of the provider in the `lyrics_providers` config and in candidates
+    fn name(&self) -> &'static str;
+
+    /// Lyrics of the track matching `query` exactly (ignoring case, accents and punctuation)
+    fn get<'a>(
+        &'a self,
+        query: &'a LyricsQuery,
+        bypass_cache: bool,
+    ) -> ProviderFuture<'a, LyricsLookup>;
+
+    /// Candidates for a search by title, album and artist, or by the keyword `q`
+    fn search<'a>(
+        &'a self,
+        query: &'a LyricsQuery,
+        q: &'a str,
+        bypass_cache: bool,
+    ) -> ProviderFuture<'a, Vec<LyricsCandidate>>;
+
+    /// The candidate with `id`, as returned by `get` or `search` of this provider
+    fn get_by_id<'a>(&'a self, id: &'a str) -> ProviderFuture<'a, LyricsCandidate>;
+}
+
+/// The track lyrics are looked up for
+#[derive(Debug, Clone)]
+pub struct LyricsQuery {
+    pub title: String,
+    pub album_name: String,
+    pub artist_name: String,
+    pub duration: f64,
+}
+
+/// Lyrics found by a provider. Serialized like an LRCLIB search result, so the frontend
+/// shows and applies candidates of every provider the same way.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LyricsCandidate {
+    pub provider: String,
+    /// ID of the candidate on its provider: the LRCLIB ID, or the path of a local file
+    pub id: String,
+    pub name: Option<String>,
+    pub artist_name: Option<String>,
+    pub album_name: Option<String>,
+    pub duration: Option<f64>,
+    pub instrumental: bool,
+    pub plain_lyrics: Option<String>,
+    pub synced_lyrics: Option<String>,
+    pub lyricsfile: Option<String>,
+    /// Instance an LRCLIB candidate was found on. IDs are only valid on their own instance.
+    pub lrclib_instance: Option<String>,
+}
+
+/// Result of `LyricsProvider::get`
+pub enum LyricsLookup {
+    Found(Box<LyricsCandidate>),
+    /// The provider has no lyrics for the track. LRCLIB tells when it was asked, since the
+    /// answer may come from its cache.
+    NotFound {
+        checked_at: Option<String>,
+    },
+}
+
+/// Kind of provider configured in `lyrics_providers`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProviderKind {
+    Lrclib,
+    /// The `.lrc`, `.txt` and lyricsfile files of `local_lyrics_folder`
+    LocalFolder,
+}
+
+impl ProviderKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            lrclib::PROVIDER_NAME => Some(ProviderKind::Lrclib),
+            local_folder::PROVIDER_NAME => Some(ProviderKind::LocalFolder),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProviderKind::Lrclib => lrclib::PROVIDER_NAME,
+            ProviderKind::LocalFolder => local_folder::PROVIDER_NAME,
+        }
+    }
+}
+
+/// Parse the provider list stored in the config (one name per line, in priority order),
+/// falling back to the defaults when it holds none
+pub fn parse_lyrics_providers(providers: &str) -> Result<Vec<ProviderKind>> {
+    let mut parsed = Vec::new();
+
+    for line in providers.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let kind = ProviderKind::from_name(line)
+            .ok_or_else(|| anyhow!("Unknown lyrics provider: {}", line))?;
+        if !parsed.contains(&kind) {
+            parsed.push(kind);
+        }
+    }
+
+    if parsed.is_empty() {
+        return parse_lyrics_providers(DEFAULT_LYRICS_PROVIDERS);
+    }
+
+    Ok(parsed)
+}
+
+/// The providers of the `lyrics_providers` config, in priority order. The local folder
+/// provider is left out while no `local_lyrics_folder` is set.
+pub async fn configured_providers(app_handle: &AppHandle) -> Result<Vec<Box<dyn LyricsProvider>>> {
+    let config = app_handle.db(db::get_config)?;
+    let mut providers = Vec::new();
+
+    for kind in parse_lyrics_providers(&config.lyrics_providers)? {
+        if kind == ProviderKind::LocalFolder && config.local_lyrics_folder.trim().is_empty() {
+            continue;
+        }
+        providers.push(provider(kind, None, app_handle).await?);
+    }
+
+    Ok(providers)
+}
+
+/// The provider of `kind`, whether or not it is configured. `lrclib_instance` is the LRCLIB
+/// instance IDs are looked up on, all enabled instances when `None`.
+pub async fn provider(
+    kind: ProviderKind,
+    lrclib_instance: Option<String>,
+    app_handle: &AppHandle,
+) -> Result<Box<dyn LyricsProvider>> {
+    match kind {
+        ProviderKind::Lrclib => Ok(Box::new(LrclibProvider::new(
+            app_handle.clone(),
+            lrclib_instance,
+        ))),
+        ProviderKind::LocalFolder => {
+            let folder = app_handle.db(db::get_config)?.local_lyrics_folder;
+            if folder.trim().is_empty() {
+                return Err(anyhow!(
+                    "The {} provider has no lyrics folder configured",
+                    kind.name()
+                ));
+            }
+            let index = local_folder_index(PathBuf::from(folder.trim()), app_handle).await?;
+            Ok(Box::new(LocalFolderProvider::new(index)))
+        }
+    }
+}
+
+/// The index of `folder` kept in `AppState`, rebuilt on the blocking pool once it is older
+/// than `local_folder::INDEX_MAX_AGE` or indexes another folder
+async fn local_folder_index(
+    folder: PathBuf,
+    app_handle: &AppHandle,
+) -> Result<Arc<LocalFolderIndex>> {
+    let app_state: State<AppState> = app_handle.state();
+
+    if let Some(index) = app_state.local_lyrics_index.lock().unwrap().as_ref() {
+        if index.folder == folder && index.is_fresh() {
+            return Ok(index.clone());
+        }
+    }
+
+    let index = tokio::task::spawn_blocking(move || LocalFolderIndex::build(&folder)).await??;
+    let index = Arc::new(index);
+    *app_state.local_lyrics_index.lock().unwrap() = Some(index.clone());
+
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lyrics_providers() {
+        assert_eq!(
+            parse_lyrics_providers("local_folder\n\nLRCLIB\nlocal_folder").unwrap(),
+            vec![ProviderKind::LocalFolder, ProviderKind::Lrclib]
+        );
+        assert_eq!(
+            parse_lyrics_providers(" \n").unwrap(),
+            vec![ProviderKind::Lrclib]
+        );
+        assert!(parse_lyrics_providers("musixmatch").is_err());
+    }
+}
@@ -0,0 +1,133 @@
+/// Separators used when the user has not configured any, one per line. Surrounding
+/// spaces are part of a separator. `/` and ` & ` are left out, since they are part of
+/// band names like `AC/DC` and `Simon & Garfunkel`.
+pub const DEFAULT_ARTIST_SEPARATORS: &str = ";\n feat. \n ft. ";
+
+/// Parse the separator list stored in the config (one separator per line, spaces kept),
+/// falling back to the defaults when it holds none
+pub fn parse_artist_separators(separators: &str) -> Vec<String> {
+    let mut parsed: Vec<String> = Vec::new();
+
+    for separator in separators.lines() {
+        let separator = separator.trim_end_matches('\r');
+        if !separator.trim().is_empty() && !parsed.iter().any(|s| s == separator) {
+            parsed.push(separator.to_owned());
+        }
+    }
+
+    if parsed.is_empty() {
+        parsed = DEFAULT_ARTIST_SEPARATORS
+            .lines()
+            .map(|separator| separator.to_owned())
+            .collect();
+    }
+
+    parsed
+}
+
+/// Split the values of an artist tag into the credited artists, in order of appearance.
+/// Separators match case-insensitively; duplicate names (ignoring case) are dropped,
+/// so the first artist of the credit is the primary one.
+pub fn split_artist_credit(values: &[String], separators: &[String]) -> Vec<String> {
+    let mut parts: Vec<String> = values.to_vec();
+
+    for separator in separators {
+        parts = parts
+            .iter()
+            .flat_map(|part| split_ignore_ascii_case(part, separator))
+            .collect();
+    }
+
+    let mut artists: Vec<String> = Vec::new();
+    for part in parts {
+        let artist = part.trim();
+        if !artist.is_empty() && !artists.iter().any(|a| a.eq_ignore_ascii_case(artist)) {
+            artists.push(artist.to_owned());
+        }
+    }
+
+    artists
+}
+
+/// The first artist credited by an artist tag value, or the whole value when it has none
+pub fn primary_artist(credit: &str, separators: &[String]) -> String {
+    split_artist_credit(&[credit.to_owned()], separators)
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| credit.trim().to_owned())
+}
+
+fn split_ignore_ascii_case(value: &str, separator: &str) -> Vec<String> {
+    // ASCII lowercasing keeps byte offsets, so matches index into the original value
+    let haystack = value.to_ascii_lowercase();
+    let needle = separator.to_ascii_lowercase();
+    let mut parts = Vec::new();
+    let mut start = 0;
+
+    while let Some(offset) = haystack[start..].find(&needle) {
+        parts.push(value[start..start + offset].to_owned());
+        start += offset + needle.len();
+    }
+    parts.push(value[start..].to_owned());
+
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> Vec<String> {
+        parse_artist_separators("")
+    }
+
+    #[test]
+    fn test_split_artist_credit_default_separators() {
+        let credit = vec!["Daft Punk Feat. Pharrell Williams; Nile Rodgers".to_owned()];
+
+        assert_eq!(
+            split_artist_credit(&credit, &defaults()),
+            vec!["Daft Punk", "Pharrell Williams", "Nile Rodgers"]
+        );
+        assert_eq!(
+            split_artist_credit(&["Eminem ft. Rihanna".to_owned()], &defaults()),
+            vec!["Eminem", "Rihanna"]
+        );
+
+        for band in ["AC/DC", "Simon & Garfunkel"] {
+            assert_eq!(
+                split_artist_credit(&[band.to_owned()], &defaults()),
+                vec![band]
+            );
+        }
+    }
+
+    #[test]
+    fn test_split_artist_credit_multiple_values() {
+        let values = vec![
+            "Artist A; Artist B".to_owned(),
+            "artist a".to_owned(),
+            "Artist C".to_owned(),
+        ];
+
+        assert_eq!(
+            split_artist_credit(&values, &defaults()),
+            vec!["Artist A", "Artist B", "Artist C"]
+        );
+    }
+
+    #[test]
+    fn test_primary_artist_with_custom_separators() {
+        let separators = parse_artist_separators(" ft. \n x \n\n");
+
+        assert_eq!(separators, vec![" ft. ", " x "]);
+        assert_eq!(
+            primary_artist("Artist A x Artist B", &separators),
+            "Artist A"
+        );
+        assert_eq!(
+            primary_artist("Artist A & Artist B", &separators),
+            "Artist A & Artist B"
+        );
+    }
+}
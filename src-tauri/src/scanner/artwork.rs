@@ -0,0 +1,137 @@
+use anyhow::Result;
+use image::ImageFormat;
+use lofty::file::TaggedFileExt;
+use lofty::picture::PictureType;
+use lofty::read_from_path;
+use std::path::{Path, PathBuf};
+
+/// Name of the thumbnail cache directory below the app data directory
+pub const CACHE_DIR_NAME: &str = "artwork";
+
+/// Thumbnails are scaled to fit a square of this size, keeping the aspect ratio
+const THUMBNAIL_SIZE: u32 = 512;
+
+/// Cover images picked up from album folders, in order of preference
+const COVER_FILE_STEMS: &[&str] = &["cover", "folder", "front", "album"];
+const COVER_FILE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png"];
+
+/// Artwork image data and the file it was read from (the track itself for embedded pictures)
+pub struct Artwork {
+    pub source_path: PathBuf,
+    pub data: Vec<u8>,
+}
+
+/// Find the artwork of a track: its embedded front cover (or any embedded picture),
+/// falling back to a cover image in the track's directory
+pub fn find_artwork(track_path: &Path) -> Option<Artwork> {
+    if let Some(data) = read_embedded_picture(track_path) {
+        return Some(Artwork {
+            source_path: track_path.to_path_buf(),
+            data,
+        });
+    }
+
+    let cover_path = find_cover_file(track_path.parent()?)?;
+    match std::fs::read(&cover_path) {
+        Ok(data) => Some(Artwork {
+            source_path: cover_path,
+            data,
+        }),
+        Err(e) => {
+            eprintln!("Error reading cover image {:?}: {}", cover_path, e);
+            None
+        }
+    }
+}
+
+/// Path of the cached thumbnail of an album's artwork. The source hash is part of the
+/// name, so a changed source gets a new thumbnail instead of a stale cached one.
+pub fn thumbnail_path(cache_dir: &Path, album_id: i64, source_hash: &str) -> PathBuf {
+    cache_dir.join(format!("{}-{}.jpg", album_id, source_hash))
+}
+
+/// Decode artwork and write it as a JPEG thumbnail
+pub fn write_thumbnail(data: &[u8], target: &Path) -> Result<()> {
+    let thumbnail = image::load_from_memory(data)?
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .to_rgb8();
+    thumbnail.save_with_format(target, ImageFormat::Jpeg)?;
+
+    Ok(())
+}
+
+/// Read the front cover from the tags of an audio file, or the first picture if there is none
+fn read_embedded_picture(path: &Path) -> Option<Vec<u8>> {
+    let tagged_file = read_from_path(path).ok()?;
+    let pictures: Vec<_> = tagged_file
+        .tags()
+        .iter()
+        .flat_map(|tag| tag.pictures())
+        .collect();
+
+    pictures
+        .iter()
+        .find(|picture| picture.pic_type() == PictureType::CoverFront)
+        .or_else(|| pictures.first())
+        .map(|picture| picture.data().to_vec())
+}
+
+/// Find a cover image like `cover.jpg` or `Folder.png` in a directory
+fn find_cover_file(directory: &Path) -> Option<PathBuf> {
+    let entries = std::fs::read_dir(directory).ok()?;
+    let candidates: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+
+    let matches = |part: Option<&std::ffi::OsStr>, expected: &str| {
+        part.and_then(|part| part.to_str())
+            .is_some_and(|part| part.eq_ignore_ascii_case(expected))
+    };
+
+    COVER_FILE_STEMS.iter().find_map(|stem| {
+        COVER_FILE_EXTENSIONS.iter().find_map(|extension| {
+            candidates
+                .iter()
+                .find(|path| {
+                    matches(path.file_stem(), stem) && matches(path.extension(), extension)
+                })
+                .cloned()
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_cover_file_prefers_cover_over_folder() {
+        let directory = std::env::temp_dir().join(format!("lrcget_artwork_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("Folder.PNG"), b"").unwrap();
+        std::fs::write(directory.join("back.jpg"), b"").unwrap();
+
+        assert_eq!(
+            find_cover_file(&directory),
+            Some(directory.join("Folder.PNG"))
+        );
+
+        std::fs::write(directory.join("cover.jpg"), b"").unwrap();
+        assert_eq!(
+            find_cover_file(&directory),
+            Some(directory.join("cover.jpg"))
+        );
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_thumbnail_path_includes_source_hash() {
+        assert_eq!(
+            thumbnail_path(Path::new("/cache"), 42, "00ff"),
+            PathBuf::from("/cache/42-00ff.jpg")
+        );
+    }
+}
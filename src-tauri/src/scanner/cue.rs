@@ -0,0 +1,307 @@
+use std::path::{Path, PathBuf};
+
+/// CUE sheet timestamps are `mm:ss:ff` with 75 frames per second
+const FRAMES_PER_SECOND: f64 = 75.0;
+
+/// A parsed CUE sheet
+#[derive(Debug, Clone, Default)]
+pub struct CueSheet {
+    pub path: PathBuf,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub files: Vec<CueFile>,
+}
+
+/// A `FILE` entry of a CUE sheet and its audio tracks
+#[derive(Debug, Clone, Default)]
+pub struct CueFile {
+    pub name: String,
+    pub tracks: Vec<CueTrack>,
+}
+
+/// An audio `TRACK` entry of a CUE sheet
+#[derive(Debug, Clone, Default)]
+pub struct CueTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    /// Start within the file in seconds (`INDEX 01`, or `INDEX 00` if there is none)
+    pub start: f64,
+}
+
+/// A `TRACK` entry while its `INDEX` lines are being read
+struct PendingTrack {
+    track: CueTrack,
+    pregap_start: Option<f64>,
+    start: Option<f64>,
+}
+
+impl CueSheet {
+    /// Read a CUE sheet, decoding it as Latin-1 if it is not valid UTF-8
+    pub fn read(path: &Path) -> std::io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let content = String::from_utf8(bytes)
+            .unwrap_or_else(|e| e.into_bytes().iter().map(|&b| b as char).collect());
+
+        Ok(Self::parse(path, &content))
+    }
+
+    pub fn parse(path: &Path, content: &str) -> Self {
+        let mut sheet = CueSheet {
+            path: path.to_path_buf(),
+            ..Default::default()
+        };
+        let mut pending: Option<PendingTrack> = None;
+        let mut in_track = false;
+
+        for line in content.lines() {
+            let line = line.trim().trim_start_matches('\u{feff}');
+            let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+
+            match command.to_ascii_uppercase().as_str() {
+                "FILE" => {
+                    finish_track(pending.take(), &mut sheet.files);
+                    in_track = false;
+                    sheet.files.push(CueFile {
+                        name: parse_file_name(rest),
+                        tracks: Vec::new(),
+                    });
+                }
+                "TRACK" => {
+                    finish_track(pending.take(), &mut sheet.files);
+                    in_track = true;
+
+                    let mut parts = rest.split_whitespace();
+                    let number = parts.next().and_then(|n| n.parse().ok());
+                    let is_audio = parts
+                        .next()
+                        .is_some_and(|kind| kind.eq_ignore_ascii_case("AUDIO"));
+
+                    if let (Some(number), true) = (number, is_audio) {
+                        pending = Some(PendingTrack {
+                            track: CueTrack {
+                                number,
+                                ..Default::default()
+                            },
+                            pregap_start: None,
+                            start: None,
+                        });
+                    }
+                }
+                "TITLE" | "PERFORMER" => {
+                    let value = Some(unquote(rest)).filter(|v| !v.is_empty());
+                    let is_title = command.eq_ignore_ascii_case("TITLE");
+
+                    match (&mut pending, in_track) {
+                        (Some(pending), _) if is_title => pending.track.title = value,
+                        (Some(pending), _) => pending.track.performer = value,
+                        // Belongs to a non-audio track
+                        (None, true) => {}
+                        (None, false) if is_title => sheet.title = value,
+                        (None, false) => sheet.performer = value,
+                    }
+                }
+                "INDEX" => {
+                    let Some(pending) = &mut pending else {
+                        continue;
+                    };
+
+                    let mut parts = rest.split_whitespace();
+                    let index = parts.next().and_then(|n| n.parse::<u32>().ok());
+                    let time = parts.next().and_then(parse_timestamp);
+
+                    match index {
+                        Some(0) => pending.pregap_start = time,
+                        Some(1) => pending.start = time,
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        finish_track(pending, &mut sheet.files);
+
+        sheet
+    }
+
+    /// Tracks this sheet defines for an audio file, if it splits the file into more than one
+    /// track. `FILE` entries are matched by file name, or by stem since sheets often still
+    /// refer to the `.wav` the rip was encoded from.
+    pub fn tracks_for(&self, audio_path: &Path) -> Option<&[CueTrack]> {
+        let file_name = audio_path.file_name()?.to_str()?.to_lowercase();
+        let file_stem = audio_path.file_stem()?.to_str()?.to_lowercase();
+
+        let name_of = |file: &CueFile| {
+            Path::new(&file.name)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| name.to_lowercase())
+        };
+        let stem_of = |file: &CueFile| {
+            Path::new(&file.name)
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .map(|stem| stem.to_lowercase())
+        };
+
+        let file = self
+            .files
+            .iter()
+            .find(|file| name_of(file).as_ref() == Some(&file_name))
+            .or_else(|| {
+                self.files
+                    .iter()
+                    .find(|file| stem_of(file).as_ref() == Some(&file_stem))
+            })?;
+
+        (file.tracks.len() > 1).then_some(file.tracks.as_slice())
+    }
+}
+
+/// Read all `.cue` files in a directory, skipping unreadable ones
+pub fn read_cue_sheets(directory: &Path) -> Vec<CueSheet> {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("cue"))
+        })
+        .filter_map(|path| match CueSheet::read(&path) {
+            Ok(sheet) => Some(sheet),
+            Err(e) => {
+                eprintln!("Error reading CUE sheet {:?}: {}", path, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Path standing in for a virtual track in sidecar file names: `<stem>.<NN>.<ext>`.
+/// Virtual tracks share one audio file, so each needs its own `.lrc`/`.txt` name.
+pub fn virtual_track_path(path: &Path, track_number: u32) -> PathBuf {
+    let file_stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+
+    path.with_file_name(format!("{}.{:02}.{}", file_stem, track_number, extension))
+}
+
+fn finish_track(pending: Option<PendingTrack>, files: &mut [CueFile]) {
+    let Some(pending) = pending else {
+        return;
+    };
+    let Some(start) = pending.start.or(pending.pregap_start) else {
+        return;
+    };
+
+    if let Some(file) = files.last_mut() {
+        file.tracks.push(CueTrack {
+            start,
+            ..pending.track
+        });
+    }
+}
+
+/// `"Album.flac" WAVE` -> `Album.flac`
+fn parse_file_name(rest: &str) -> String {
+    if let Some(quoted) = rest.strip_prefix('"') {
+        return quoted.split('"').next().unwrap_or_default().to_owned();
+    }
+
+    match rest.rsplit_once(char::is_whitespace) {
+        Some((name, _file_type)) => name.trim().to_owned(),
+        None => rest.to_owned(),
+    }
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches('"').trim().to_owned()
+}
+
+/// `mm:ss:ff` -> seconds
+fn parse_timestamp(value: &str) -> Option<f64> {
+    let mut parts = value.split(':').map(|part| part.parse::<u32>().ok());
+    let minutes = parts.next()??;
+    let seconds = parts.next()??;
+    let frames = parts.next()??;
+
+    Some(minutes as f64 * 60.0 + seconds as f64 + frames as f64 / FRAMES_PER_SECOND)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEET: &str = r#"REM GENRE Rock
+PERFORMER "The Artist"
+TITLE "The Album"
+FILE "The Album.wav" WAVE
+  TRACK 01 AUDIO
+    TITLE "First Song"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Second Song"
+    PERFORMER "Guest Artist"
+    INDEX 00 04:10:00
+    INDEX 01 04:12:37
+  TRACK 03 DATA
+    TITLE "Not Audio"
+    INDEX 01 08:00:00
+"#;
+
+    #[test]
+    fn test_parse_sheet() {
+        let sheet = CueSheet::parse(Path::new("/music/The Album.cue"), SHEET);
+
+        assert_eq!(sheet.title.as_deref(), Some("The Album"));
+        assert_eq!(sheet.performer.as_deref(), Some("The Artist"));
+        assert_eq!(sheet.files.len(), 1);
+        assert_eq!(sheet.files[0].name, "The Album.wav");
+
+        let tracks = &sheet.files[0].tracks;
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].title.as_deref(), Some("First Song"));
+        assert_eq!(tracks[0].performer, None);
+        assert_eq!(tracks[0].start, 0.0);
+        assert_eq!(tracks[1].number, 2);
+        assert_eq!(tracks[1].performer.as_deref(), Some("Guest Artist"));
+        assert!((tracks[1].start - (4.0 * 60.0 + 12.0 + 37.0 / 75.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_tracks_for_matches_stem_case_insensitively() {
+        let sheet = CueSheet::parse(Path::new("/music/The Album.cue"), SHEET);
+
+        assert_eq!(
+            sheet
+                .tracks_for(Path::new("/music/the album.FLAC"))
+                .map(|tracks| tracks.len()),
+            Some(2)
+        );
+        assert!(sheet.tracks_for(Path::new("/music/Other.flac")).is_none());
+    }
+
+    #[test]
+    fn test_single_track_files_are_not_split() {
+        let sheet = CueSheet::parse(
+            Path::new("/music/album.cue"),
+            "FILE \"01.flac\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00\nFILE \"02.flac\" WAVE\n  TRACK 02 AUDIO\n    INDEX 01 00:00:00\n",
+        );
+
+        assert!(sheet.tracks_for(Path::new("/music/01.flac")).is_none());
+    }
+
+    #[test]
+    fn test_virtual_track_path() {
+        assert_eq!(
+            virtual_track_path(Path::new("/music/Album.flac"), 3),
+            PathBuf::from("/music/Album.03.flac")
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use anyhow::Result;
 use std::path::Path;
-use xxhash_rust::xxh3::xxh3_64;
+use xxhash_rust::xxh3::{xxh3_64, Xxh3};
 
 const QUICK_HASH_SIZE: usize = 64 * 1024; // 64KB
 
@@ -18,3 +18,41 @@ pub fn compute_quick_hash(path: &Path) -> Result<String> {
     let hash = xxh3_64(&buffer);
     Ok(format!("{:016x}", hash))
 }
+
+/// Compute a hash of the whole file. Slower than `compute_quick_hash`, but files that
+/// only differ after their first 64KB get different hashes.
+pub fn compute_full_hash(path: &Path) -> Result<String> {
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0u8; QUICK_HASH_SIZE];
+    let mut hasher = Xxh3::new();
+
+    loop {
+        let bytes_read = file.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+
+    Ok(format!("{:016x}", hasher.digest()))
+}
+
+/// Hash of one virtual track of a CUE sheet: the file's hash salted with the track's index
+/// and start offset, so the tracks sharing a file are no copies or moves of each other
+pub fn compute_virtual_track_hash(
+    file_hash: &str,
+    track_index: usize,
+    start_offset: f64,
+) -> String {
+    let salted = format!(
+        "{}:{}:{}",
+        file_hash,
+        track_index,
+        (start_offset * 1000.0).round() as i64
+    );
+
+    format!("{:016x}", xxh3_64(salted.as_bytes()))
+}
@@ -0,0 +1,158 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Name of the per-folder ignore file picked up during scans
+pub const IGNORE_FILE_NAME: &str = ".lrcgetignore";
+
+/// Gitignore-style exclusion rules for the library directories
+///
+/// Each library directory can have its own patterns (stored in `directories.ignore_patterns`),
+/// and any folder below it can contain an `.lrcgetignore` file. Like with gitignore, rules in a
+/// deeper ignore file take precedence, so `!pattern` can re-include files excluded higher up.
+/// Ignore files are read lazily and cached for the lifetime of this value.
+pub struct IgnoreRules {
+    roots: Vec<(PathBuf, Gitignore)>,
+    ignore_files: HashMap<PathBuf, Option<Gitignore>>,
+}
+
+impl IgnoreRules {
+    /// Build the rules from library directory paths mapped to their newline-separated patterns
+    pub fn new(directory_patterns: &HashMap<String, String>) -> Self {
+        let roots = directory_patterns
+            .iter()
+            .map(|(directory, patterns)| {
+                let root = PathBuf::from(directory);
+                let mut builder = GitignoreBuilder::new(&root);
+
+                for line in patterns.lines() {
+                    if let Err(e) = builder.add_line(None, line) {
+                        eprintln!("Invalid ignore pattern for {}: {}", directory, e);
+                    }
+                }
+
+                let matcher = builder.build().unwrap_or_else(|e| {
+                    eprintln!("Failed to build ignore rules for {}: {}", directory, e);
+                    Gitignore::empty()
+                });
+
+                (root, matcher)
+            })
+            .collect();
+
+        Self {
+            roots,
+            ignore_files: HashMap::new(),
+        }
+    }
+
+    /// Check whether a file or directory below one of the library directories is excluded.
+    /// Paths outside every library directory are never ignored.
+    pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let Some((root, root_matcher)) = self
+            .roots
+            .iter()
+            .filter(|(root, _)| path.starts_with(root))
+            .max_by_key(|(root, _)| root.components().count())
+        else {
+            return false;
+        };
+
+        // The deepest ignore file with a matching rule decides
+        for directory in path.ancestors().skip(1) {
+            if !directory.starts_with(root) {
+                break;
+            }
+
+            let matcher = self
+                .ignore_files
+                .entry(directory.to_path_buf())
+                .or_insert_with(|| read_ignore_file(directory));
+
+            if let Some(matcher) = matcher {
+                match matcher.matched_path_or_any_parents(path, is_dir) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => {}
+                }
+            }
+        }
+
+        root_matcher
+            .matched_path_or_any_parents(path, is_dir)
+            .is_ignore()
+    }
+}
+
+/// Read the `.lrcgetignore` file of a directory, if there is one
+fn read_ignore_file(directory: &Path) -> Option<Gitignore> {
+    let ignore_file = directory.join(IGNORE_FILE_NAME);
+    if !ignore_file.is_file() {
+        return None;
+    }
+
+    let mut builder = GitignoreBuilder::new(directory);
+    if let Some(e) = builder.add(&ignore_file) {
+        eprintln!("Error reading {:?}: {}", ignore_file, e);
+    }
+
+    match builder.build() {
+        Ok(matcher) => Some(matcher),
+        Err(e) => {
+            eprintln!("Failed to build ignore rules from {:?}: {}", ignore_file, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(directory: &Path, patterns: &str) -> IgnoreRules {
+        let directory_patterns =
+            HashMap::from([(directory.to_string_lossy().to_string(), patterns.to_owned())]);
+        IgnoreRules::new(&directory_patterns)
+    }
+
+    #[test]
+    fn test_directory_patterns() {
+        let root = PathBuf::from("/music");
+        let mut rules = rules(&root, "@eaDir/\n/Samples\n*.m4r\n");
+
+        assert!(rules.is_ignored(&root.join("Artist/@eaDir/song.mp3"), false));
+        assert!(rules.is_ignored(&root.join("Samples/kick.wav"), false));
+        assert!(rules.is_ignored(&root.join("Ringtones/ring.m4r"), false));
+        assert!(!rules.is_ignored(&root.join("Artist/Samples/song.mp3"), false));
+        assert!(!rules.is_ignored(&root.join("Artist/Album/song.mp3"), false));
+    }
+
+    #[test]
+    fn test_paths_outside_library_are_not_ignored() {
+        let mut rules = rules(Path::new("/music"), "*.mp3");
+
+        assert!(!rules.is_ignored(Path::new("/elsewhere/song.mp3"), false));
+    }
+
+    #[test]
+    fn test_ignore_file_overrides_directory_patterns() {
+        let root = std::env::temp_dir().join(format!("lrcget_ignore_rules_{}", std::process::id()));
+        let album = root.join("Artist").join("Album");
+        std::fs::create_dir_all(&album).unwrap();
+        std::fs::write(
+            root.join("Artist").join(IGNORE_FILE_NAME),
+            "bonus*\n!bonus keep.mp3\n",
+        )
+        .unwrap();
+
+        let mut rules = rules(&root, "*.wav\n");
+
+        assert!(rules.is_ignored(&album.join("bonus track.mp3"), false));
+        assert!(!rules.is_ignored(&album.join("bonus keep.mp3"), false));
+        assert!(rules.is_ignored(&album.join("demo.wav"), false));
+        assert!(!rules.is_ignored(&root.join("bonus track.mp3"), false));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,108 @@
+use serde::Serialize;
+use std::sync::{Condvar, Mutex};
+
+/// State of a running scan, as requested through its `ScanJob`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanJobState {
+    Running,
+    Paused,
+    Cancelled,
+}
+
+/// Handle to pause, resume or cancel a running scan from another thread
+///
+/// The scanner only looks at it between batches (see `checkpoint`), so a request takes
+/// effect once the current batch is committed. Cancelling stops the scan there; it is no
+/// rollback, as the batches committed so far are kept.
+pub struct ScanJob {
+    state: Mutex<ScanJobState>,
+    state_changed: Condvar,
+}
+
+impl Default for ScanJob {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScanJob {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(ScanJobState::Running),
+            state_changed: Condvar::new(),
+        }
+    }
+
+    pub fn state(&self) -> ScanJobState {
+        *self.state.lock().unwrap()
+    }
+
+    pub fn pause(&self) {
+        self.transition(ScanJobState::Running, ScanJobState::Paused);
+    }
+
+    pub fn resume(&self) {
+        self.transition(ScanJobState::Paused, ScanJobState::Running);
+    }
+
+    pub fn cancel(&self) {
+        *self.state.lock().unwrap() = ScanJobState::Cancelled;
+        self.state_changed.notify_all();
+    }
+
+    /// Block while the scan is paused. Returns `false` if it was cancelled.
+    pub fn checkpoint(&self) -> bool {
+        let state = self
+            .state_changed
+            .wait_while(self.state.lock().unwrap(), |state| {
+                *state == ScanJobState::Paused
+            })
+            .unwrap();
+
+        *state != ScanJobState::Cancelled
+    }
+
+    fn transition(&self, from: ScanJobState, to: ScanJobState) {
+        let mut state = self.state.lock().unwrap();
+        if *state == from {
+            *state = to;
+            self.state_changed.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_cancel_wakes_paused_checkpoint() {
+        let job = Arc::new(ScanJob::new());
+        job.pause();
+
+        let scanner = {
+            let job = Arc::clone(&job);
+            std::thread::spawn(move || job.checkpoint())
+        };
+        job.cancel();
+
+        assert!(!scanner.join().unwrap());
+        assert_eq!(job.state(), ScanJobState::Cancelled);
+    }
+
+    #[test]
+    fn test_cancelled_job_cannot_be_resumed() {
+        let job = ScanJob::new();
+        assert!(job.checkpoint());
+
+        job.pause();
+        job.resume();
+        assert!(job.checkpoint());
+
+        job.cancel();
+        job.resume();
+        assert!(!job.checkpoint());
+    }
+}
@@ -1,9 +1,14 @@
+use crate::parser::lrc::{format_timestamp, is_instrumental_lrc, parse_lrc};
+use crate::scanner::cue::{virtual_track_path, CueSheet};
+use crate::scanner::path_template::PathTemplate;
 use anyhow::Result;
+use lofty::config::ParseOptions;
 use lofty::error::LoftyError;
-use lofty::file::AudioFile;
-use lofty::file::TaggedFileExt;
+use lofty::file::{AudioFile, FileType, TaggedFile, TaggedFileExt};
+use lofty::id3::v2::{FrameId, SynchronizedTextFrame, TimestampFormat};
+use lofty::mpeg::MpegFile;
 use lofty::read_from_path;
-use lofty::tag::Accessor;
+use lofty::tag::{Accessor, ItemKey, Tag};
 use std::path::Path;
 use thiserror::Error;
 
@@ -14,19 +19,87 @@ pub struct TrackMetadata {
     pub file_name: String,
     pub title: String,
     pub album: String,
+    /// The artist tag as written, e.g. `A feat. B`
     pub artist: String,
+    /// The credited artists, primary artist first. Holds the raw tag values until the
+    /// scanner splits them with the configured separators.
+    pub artists: Vec<String>,
+    /// Whether `artists` holds the values of a multi-value `ARTISTS` tag, which already
+    /// are one artist each and are not split
+    pub artists_tagged: bool,
     pub album_artist: String,
     pub duration: f64,
     pub track_number: Option<u32>,
+    /// Whether some of the fields above were inferred from the file path
+    pub metadata_inferred: bool,
+    /// Start within the audio file in seconds, for virtual tracks from a CUE sheet
+    pub start_offset: Option<f64>,
+    /// Optional tags that are stored but not required to index the track
+    pub extended: ExtendedTags,
 }
 
-/// Lyrics information from external files
+/// Optional tag fields of a track; the album-level ones (year, album artist sort name,
+/// release ID) are stored on its album
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExtendedTags {
+    pub disc_number: Option<u32>,
+    pub year: Option<u32>,
+    pub genre: Option<String>,
+    pub composer: Option<String>,
+    pub album_artist_sort: Option<String>,
+    pub musicbrainz_recording_id: Option<String>,
+    pub musicbrainz_release_id: Option<String>,
+    pub isrc: Option<String>,
+}
+
+impl ExtendedTags {
+    /// Read the extended fields from a file's primary tag
+    pub fn from_tag(tag: Option<&Tag>) -> Self {
+        let Some(tag) = tag else {
+            return Self::default();
+        };
+        let string = |key: ItemKey| tag_value(tag.get_string(key).map(Into::into));
+
+        Self {
+            disc_number: tag.disk(),
+            year: tag.date().map(|date| u32::from(date.year)),
+            genre: tag_value(tag.genre()),
+            composer: string(ItemKey::Composer),
+            album_artist_sort: string(ItemKey::AlbumArtistSortOrder),
+            musicbrainz_recording_id: string(ItemKey::MusicBrainzRecordingId),
+            musicbrainz_release_id: string(ItemKey::MusicBrainzReleaseId),
+            isrc: string(ItemKey::Isrc),
+        }
+    }
+}
+
+/// Lyrics information from sidecar files or embedded tags
 #[derive(Debug, Clone, Default)]
 pub struct LyricsInfo {
     pub txt_lyrics: Option<String>,
     pub lrc_lyrics: Option<String>,
 }
 
+/// Which lyrics source wins when a track has both sidecar and embedded lyrics
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LyricsPrecedence {
+    /// Prefer `.txt`/`.lrc` sidecar files, fall back to embedded tags (default)
+    #[default]
+    Sidecar,
+    /// Prefer lyrics embedded in the audio file tags, fall back to sidecar files
+    Embedded,
+}
+
+impl LyricsPrecedence {
+    /// Parse the value stored in `config_data.lyrics_source_precedence`
+    pub fn from_config_value(value: &str) -> Self {
+        match value {
+            "embedded" => LyricsPrecedence::Embedded,
+            _ => LyricsPrecedence::Sidecar,
+        }
+    }
+}
+
 /// Errors that can occur during metadata extraction
 #[derive(Error, Debug)]
 pub enum MetadataError {
@@ -41,8 +114,54 @@ pub enum MetadataError {
 }
 
 impl TrackMetadata {
+    /// The primary credited artist, which the lyrics lookup uses
+    pub fn primary_artist(&self) -> &str {
+        &self.credited_artists()[0]
+    }
+
+    /// The credited artists, or the artist tag as written when it was not split
+    pub fn credited_artists(&self) -> &[String] {
+        if self.artists.is_empty() {
+            std::slice::from_ref(&self.artist)
+        } else {
+            &self.artists
+        }
+    }
+
+    /// The text the search index holds for the track's artist: the artist tag as written
+    /// followed by every credited artist, so each of them finds the track
+    pub fn searchable_artists(&self) -> String {
+        std::iter::once(&self.artist)
+            .chain(self.credited_artists())
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
     /// Extract metadata from an audio file path
     pub fn from_path(path: &Path) -> Result<Self, MetadataError> {
+        let tagged_file = read_tagged_file(path)?;
+        Self::from_tagged_file(path, &tagged_file, &[])
+    }
+
+    /// Extract metadata from an already parsed audio file
+    fn from_tagged_file(
+        path: &Path,
+        tagged_file: &TaggedFile,
+        path_templates: &[PathTemplate],
+    ) -> Result<Self, MetadataError> {
+        let duration = tagged_file.properties().duration().as_secs_f64();
+        Self::from_tag(path, tagged_file.primary_tag(), duration, path_templates)
+    }
+
+    /// Build metadata from a file's primary tag (if any). Missing title, album or
+    /// artist tags are inferred from the first path template that matches the path.
+    fn from_tag(
+        path: &Path,
+        tag: Option<&Tag>,
+        duration: f64,
+        path_templates: &[PathTemplate],
+    ) -> Result<Self, MetadataError> {
         let file_path = path.display().to_string();
         let file_name = path
             .file_name()
@@ -50,54 +169,66 @@ impl TrackMetadata {
             .map(|s| s.to_owned())
             .unwrap_or_default();
 
-        // Parse audio file
-        let tagged_file = read_from_path(&file_path).map_err(|e| MetadataError::ParseFailed {
-            path: file_path.clone(),
-            source: e,
-        })?;
+        let mut title = tag_value(tag.and_then(|t| t.title()));
+        let mut album = tag_value(tag.and_then(|t| t.album()));
+        let artist_values = tag_values(tag, ItemKey::TrackArtist);
+        let artists_values = tag_values(tag, ItemKey::TrackArtists);
+        let mut artist = [&artist_values, &artists_values]
+            .into_iter()
+            .find(|values| !values.is_empty())
+            .map(|values| values.join("; "));
+        let mut album_artist = tag_value(
+            tag.and_then(|t| t.get_string(ItemKey::AlbumArtist))
+                .map(Into::into),
+        );
+        let mut track_number = tag.and_then(|t| t.track());
+        let mut metadata_inferred = false;
 
-        let tag = tagged_file
-            .primary_tag()
-            .ok_or_else(|| MetadataError::NoPrimaryTag {
-                path: file_path.clone(),
-            })?
-            .to_owned();
+        if title.is_none() || album.is_none() || artist.is_none() {
+            let inferred = path_templates.iter().find_map(|template| {
+                template.match_path(path).filter(|fields| {
+                    (title.is_some() || fields.title.is_some())
+                        && (album.is_some() || fields.album.is_some())
+                        && (artist.is_some() || fields.artist.is_some())
+                })
+            });
 
-        let properties = tagged_file.properties();
+            if let Some(fields) = inferred {
+                title = title.or(fields.title);
+                album = album.or(fields.album);
+                artist = artist.or(fields.artist);
+                album_artist = album_artist.or(fields.album_artist);
+                track_number = track_number.or(fields.track_number);
+                metadata_inferred = true;
+            } else if tag.is_none() {
+                return Err(MetadataError::NoPrimaryTag { path: file_path });
+            }
+        }
 
         // Extract required fields
-        let title = tag
-            .title()
-            .ok_or_else(|| MetadataError::MissingField {
-                field: "title".to_string(),
-                path: file_path.clone(),
-            })?
-            .to_string();
-
-        let album = tag
-            .album()
-            .ok_or_else(|| MetadataError::MissingField {
-                field: "album".to_string(),
-                path: file_path.clone(),
-            })?
-            .to_string();
-
-        let artist = tag
-            .artist()
-            .ok_or_else(|| MetadataError::MissingField {
-                field: "artist".to_string(),
-                path: file_path.clone(),
-            })?
-            .to_string();
+        let title = title.ok_or_else(|| MetadataError::MissingField {
+            field: "title".to_string(),
+            path: file_path.clone(),
+        })?;
 
-        // Album artist is optional, fallback to artist
-        let album_artist = tag
-            .get_string(lofty::tag::ItemKey::AlbumArtist)
-            .map(|s| s.to_string())
-            .unwrap_or_else(|| artist.clone());
+        let album = album.ok_or_else(|| MetadataError::MissingField {
+            field: "album".to_string(),
+            path: file_path.clone(),
+        })?;
 
-        let duration = properties.duration().as_secs_f64();
-        let track_number = tag.track();
+        let artist = artist.ok_or_else(|| MetadataError::MissingField {
+            field: "artist".to_string(),
+            path: file_path.clone(),
+        })?;
+
+        // Album artist is optional, fallback to artist
+        let album_artist = album_artist.unwrap_or_else(|| artist.clone());
+        // A multi-value `ARTISTS` tag lists the credited artists better than the artist tag
+        let artists_tagged = !artists_values.is_empty();
+        let artists = [artists_values, artist_values]
+            .into_iter()
+            .find(|values| !values.is_empty())
+            .unwrap_or_else(|| vec![artist.clone()]);
 
         Ok(TrackMetadata {
             file_path,
@@ -105,9 +236,14 @@ impl TrackMetadata {
             title,
             album,
             artist,
+            artists,
+            artists_tagged,
             album_artist,
             duration,
             track_number,
+            metadata_inferred,
+            start_offset: None,
+            extended: ExtendedTags::from_tag(tag),
         })
     }
 }
@@ -136,15 +272,223 @@ impl LyricsInfo {
 
         result
     }
+
+    /// Extract lyrics embedded in the audio file tags
+    ///
+    /// Reads the generic lyrics items (ID3v2 `USLT`, Vorbis `LYRICS`/`UNSYNCEDLYRICS`,
+    /// MP4 `©lyr`) and, for MPEG files, the ID3v2 `SYLT` frame which lofty keeps as binary.
+    pub fn from_tagged_file(path: &Path, tagged_file: &TaggedFile) -> Self {
+        let mut result = LyricsInfo::default();
+
+        for tag in tagged_file.tags() {
+            for value in tag
+                .get_strings(ItemKey::Lyrics)
+                .chain(tag.get_strings(ItemKey::UnsyncLyrics))
+            {
+                if value.trim().is_empty() {
+                    continue;
+                }
+
+                // Lyrics items may hold either LRC or plain text, so classify by content
+                if is_synced_lyrics(value) {
+                    result.lrc_lyrics.get_or_insert_with(|| value.to_string());
+                } else {
+                    result.txt_lyrics.get_or_insert_with(|| value.to_string());
+                }
+            }
+        }
+
+        if result.lrc_lyrics.is_none() && tagged_file.file_type() == FileType::Mpeg {
+            result.lrc_lyrics = read_id3v2_synced_lyrics(path);
+        }
+
+        result
+    }
+
+    /// Fill the missing lyrics kinds from another source
+    pub fn or(self, fallback: LyricsInfo) -> Self {
+        LyricsInfo {
+            txt_lyrics: self.txt_lyrics.or(fallback.txt_lyrics),
+            lrc_lyrics: self.lrc_lyrics.or(fallback.lrc_lyrics),
+        }
+    }
+}
+
+/// Parse an audio file with lofty
+fn read_tagged_file(path: &Path) -> Result<TaggedFile, MetadataError> {
+    read_from_path(path).map_err(|e| MetadataError::ParseFailed {
+        path: path.display().to_string(),
+        source: e,
+    })
+}
+
+/// Read a tag value, treating blank values as missing
+fn tag_value(value: Option<std::borrow::Cow<'_, str>>) -> Option<String> {
+    value
+        .map(|s| s.to_string())
+        .filter(|s| !s.trim().is_empty())
+}
+
+/// All non-empty values of a multi-value tag item (ID3v2 frames may pack several values
+/// separated by NUL)
+fn tag_values(tag: Option<&Tag>, key: ItemKey) -> Vec<String> {
+    tag.map(|tag| {
+        tag.get_strings(key)
+            .flat_map(|value| value.split('\0'))
+            .filter(|value| !value.trim().is_empty())
+            .map(|value| value.trim().to_owned())
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Check whether lyrics text contains LRC timestamps (or the instrumental marker)
+fn is_synced_lyrics(lyrics: &str) -> bool {
+    is_instrumental_lrc(lyrics) || !parse_lrc(lyrics).timed_lines.is_empty()
+}
+
+/// Read the first millisecond-based ID3v2 `SYLT` frame and convert it to LRC
+fn read_id3v2_synced_lyrics(path: &Path) -> Option<String> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let mpeg_file = MpegFile::read_from(&mut file, ParseOptions::new()).ok()?;
+    let id3v2 = mpeg_file.id3v2()?;
+
+    let frame_id = FrameId::new("SYLT").ok()?;
+    let binary_frame = match id3v2.get(&frame_id)? {
+        lofty::id3::v2::Frame::Binary(binary_frame) => binary_frame,
+        _ => return None,
+    };
+
+    let sylt = SynchronizedTextFrame::parse(&binary_frame.data, binary_frame.flags()).ok()?;
+    if sylt.timestamp_format != TimestampFormat::MS || sylt.content.is_empty() {
+        return None;
+    }
+
+    let lrc = sylt
+        .content
+        .iter()
+        .map(|(timestamp_ms, text)| format!("{} {}", format_timestamp(*timestamp_ms as i64), text))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Some(lrc)
 }
 
-/// Convenience function to extract both metadata and lyrics
-pub fn extract_track_info(path: &Path) -> Result<(TrackMetadata, LyricsInfo), MetadataError> {
-    let metadata = TrackMetadata::from_path(path)?;
-    let lyrics = LyricsInfo::from_path(path);
+/// Convenience function to extract metadata plus sidecar and embedded lyrics.
+/// `path_templates` are used to infer missing title/album/artist tags.
+pub fn extract_track_info(
+    path: &Path,
+    lyrics_precedence: LyricsPrecedence,
+    path_templates: &[PathTemplate],
+) -> Result<(TrackMetadata, LyricsInfo), MetadataError> {
+    // Formats lofty cannot read are reported as `parse_failed` scan errors: indexing them
+    // from their path alone would leave them without a duration to look up lyrics with
+    let tagged_file = read_tagged_file(path)?;
+    let metadata = TrackMetadata::from_tagged_file(path, &tagged_file, path_templates)?;
+
+    let sidecar_lyrics = LyricsInfo::from_path(path);
+    let embedded_lyrics = LyricsInfo::from_tagged_file(path, &tagged_file);
+    let lyrics = match lyrics_precedence {
+        LyricsPrecedence::Sidecar => sidecar_lyrics.or(embedded_lyrics),
+        LyricsPrecedence::Embedded => embedded_lyrics.or(sidecar_lyrics),
+    };
+
     Ok((metadata, lyrics))
 }
 
+/// Extract the virtual tracks a CUE sheet defines for an audio file.
+///
+/// Sheet fields win over the file's tags, which only fill in what the sheet leaves out.
+/// Each track runs until the next one starts (or the file ends), and its lyrics are read
+/// from `<stem>.<NN>.lrc`/`.txt` sidecars since the tracks share one audio file.
+pub fn extract_cue_tracks(
+    path: &Path,
+    cue_sheet: &CueSheet,
+) -> Result<Vec<(TrackMetadata, LyricsInfo)>, MetadataError> {
+    let file_path = path.display().to_string();
+    let Some(cue_tracks) = cue_sheet.tracks_for(path) else {
+        return Err(MetadataError::MissingField {
+            field: "tracks".to_string(),
+            path: file_path,
+        });
+    };
+
+    let tagged_file = read_tagged_file(path)?;
+    let tag = tagged_file.primary_tag();
+    let file_duration = tagged_file.properties().duration().as_secs_f64();
+    let file_name = path
+        .file_name()
+        .and_then(|f| f.to_str())
+        .map(|s| s.to_owned())
+        .unwrap_or_default();
+
+    let album = cue_sheet
+        .title
+        .clone()
+        .or_else(|| tag_value(tag.and_then(|t| t.album())))
+        .ok_or_else(|| MetadataError::MissingField {
+            field: "album".to_string(),
+            path: file_path.clone(),
+        })?;
+    let album_artist = cue_sheet
+        .performer
+        .clone()
+        .or_else(|| {
+            tag_value(
+                tag.and_then(|t| t.get_string(ItemKey::AlbumArtist))
+                    .map(Into::into),
+            )
+        })
+        .or_else(|| tag_value(tag.and_then(|t| t.artist())));
+    // The recording tags of a file split by a sheet belong to none of its tracks
+    let extended = ExtendedTags {
+        musicbrainz_recording_id: None,
+        isrc: None,
+        ..ExtendedTags::from_tag(tag)
+    };
+
+    cue_tracks
+        .iter()
+        .enumerate()
+        .map(|(index, cue_track)| {
+            let artist = cue_track
+                .performer
+                .clone()
+                .or_else(|| album_artist.clone())
+                .ok_or_else(|| MetadataError::MissingField {
+                    field: "artist".to_string(),
+                    path: file_path.clone(),
+                })?;
+            let end = cue_tracks
+                .get(index + 1)
+                .map(|next| next.start)
+                .unwrap_or(file_duration);
+
+            let metadata = TrackMetadata {
+                file_path: file_path.clone(),
+                file_name: file_name.clone(),
+                title: cue_track
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| format!("Track {:02}", cue_track.number)),
+                album: album.clone(),
+                album_artist: album_artist.clone().unwrap_or_else(|| artist.clone()),
+                artists: vec![artist.clone()],
+                artists_tagged: false,
+                artist,
+                duration: (end - cue_track.start).max(0.0),
+                track_number: Some(cue_track.number),
+                metadata_inferred: false,
+                start_offset: Some(cue_track.start),
+                extended: extended.clone(),
+            };
+            let lyrics = LyricsInfo::from_path(&virtual_track_path(path, cue_track.number));
+
+            Ok((metadata, lyrics))
+        })
+        .collect()
+}
+
 /// Check if a file is an instrumental based on lyrics content
 pub fn is_instrumental_lyrics(lrc_lyrics: &Option<String>) -> bool {
     match lrc_lyrics {
@@ -156,3 +500,51 @@ pub fn is_instrumental_lyrics(lrc_lyrics: &Option<String>) -> bool {
         None => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_synced_lyrics() {
+        assert!(is_synced_lyrics("[00:01.50] Line one\n[00:02.75] Line two"));
+        assert!(is_synced_lyrics("[au: instrumental]"));
+        assert!(!is_synced_lyrics("Line one\nLine two"));
+        assert!(!is_synced_lyrics("[ti:Title]\n[ar:Artist]"));
+    }
+
+    #[test]
+    fn test_lyrics_info_or_fills_missing_kinds() {
+        let sidecar = LyricsInfo {
+            txt_lyrics: Some("sidecar plain".to_string()),
+            lrc_lyrics: None,
+        };
+        let embedded = LyricsInfo {
+            txt_lyrics: Some("embedded plain".to_string()),
+            lrc_lyrics: Some("[00:01.00] embedded synced".to_string()),
+        };
+
+        let merged = sidecar.or(embedded);
+        assert_eq!(merged.txt_lyrics.as_deref(), Some("sidecar plain"));
+        assert_eq!(
+            merged.lrc_lyrics.as_deref(),
+            Some("[00:01.00] embedded synced")
+        );
+    }
+
+    #[test]
+    fn test_lyrics_precedence_from_config_value() {
+        assert_eq!(
+            LyricsPrecedence::from_config_value("embedded"),
+            LyricsPrecedence::Embedded
+        );
+        assert_eq!(
+            LyricsPrecedence::from_config_value("sidecar"),
+            LyricsPrecedence::Sidecar
+        );
+        assert_eq!(
+            LyricsPrecedence::from_config_value("unknown"),
+            LyricsPrecedence::Sidecar
+        );
+    }
+}
@@ -1,7 +1,15 @@
+pub mod artist_credit;
+pub mod artwork;
+pub mod cue;
 pub mod hasher;
+pub mod ignore_rules;
+pub mod job;
 pub mod metadata;
 pub mod models;
+pub mod path_template;
 pub mod scan;
 
-pub use metadata::{extract_track_info, is_instrumental_lyrics, LyricsInfo, TrackMetadata};
-pub use scan::{scan_library, DetectionMethod};
+pub use metadata::{
+    extract_track_info, is_instrumental_lyrics, LyricsInfo, LyricsPrecedence, TrackMetadata,
+};
+pub use scan::{scan_library, scan_paths, DetectionMethod};
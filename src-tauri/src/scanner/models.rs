@@ -1,3 +1,74 @@
+use crate::persistent_entities::PersistentConfig;
+use crate::scanner::artist_credit::parse_artist_separators;
+use crate::scanner::metadata::{LyricsPrecedence, MetadataError};
+use crate::scanner::path_template::{PathTemplate, PathTemplateError};
+use crate::scanner::scan::{parse_audio_extensions, DetectionMethod};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Settings that control how a library scan processes files
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    /// How to detect unchanged and moved files
+    pub detection_method: DetectionMethod,
+    /// Which lyrics source wins when both sidecar and embedded lyrics exist
+    pub lyrics_precedence: LyricsPrecedence,
+    /// Number of workers hashing files and reading tags (0 = one per CPU core)
+    pub worker_count: usize,
+    /// Templates to infer missing title/album/artist tags from file paths
+    pub path_templates: Vec<PathTemplate>,
+    /// Lowercase extensions of the audio files to scan
+    pub audio_extensions: Vec<String>,
+    /// Separators splitting an artist tag into the credited artists (see `artist_credit`)
+    pub artist_separators: Vec<String>,
+    /// Gitignore-style patterns per library directory (see `IgnoreRules`)
+    pub ignore_patterns: HashMap<String, String>,
+    /// Directory of the album artwork thumbnail cache (artwork is skipped without one)
+    pub artwork_cache_dir: Option<PathBuf>,
+}
+
+impl ScanOptions {
+    /// The configured detection method is used unless `use_hash_detection` asks to hash files
+    /// or not; hashing then follows the config in hashing whole files or their start.
+    /// Fails when the configured path templates are invalid.
+    pub fn from_config(
+        config: &PersistentConfig,
+        use_hash_detection: Option<bool>,
+    ) -> Result<Self, PathTemplateError> {
+        let configured =
+            DetectionMethod::from_config_value(&config.detection_method).unwrap_or_default();
+        let detection_method = match (use_hash_detection, configured) {
+            (None, detection_method) => detection_method,
+            (Some(false), _) => DetectionMethod::Metadata,
+            (Some(true), DetectionMethod::FullHash) => DetectionMethod::FullHash,
+            (Some(true), _) => DetectionMethod::Hash,
+        };
+
+        Ok(Self {
+            detection_method,
+            lyrics_precedence: LyricsPrecedence::from_config_value(
+                &config.lyrics_source_precedence,
+            ),
+            worker_count: config.scan_worker_count as usize,
+            path_templates: PathTemplate::parse_list(&config.path_templates)?,
+            audio_extensions: parse_audio_extensions(&config.audio_extensions),
+            artist_separators: parse_artist_separators(&config.artist_separators),
+            ignore_patterns: HashMap::new(),
+            artwork_cache_dir: None,
+        })
+    }
+
+    pub fn with_ignore_patterns(mut self, ignore_patterns: HashMap<String, String>) -> Self {
+        self.ignore_patterns = ignore_patterns;
+        self
+    }
+
+    pub fn with_artwork_cache_dir(mut self, artwork_cache_dir: PathBuf) -> Self {
+        self.artwork_cache_dir = Some(artwork_cache_dir);
+        self
+    }
+}
+
 /// Track information from the database
 #[derive(Debug, Clone)]
 pub struct DbTrack {
@@ -6,6 +77,8 @@ pub struct DbTrack {
     pub file_size: Option<i64>,
     pub modified_time: Option<i64>,
     pub content_hash: Option<String>,
+    /// Set for virtual tracks split from the file by a CUE sheet
+    pub start_offset: Option<f64>,
 }
 
 /// Summary of scan operation results
@@ -24,12 +97,87 @@ pub struct ScanResult {
     pub moved: usize,
     /// Number of unchanged files
     pub unchanged: usize,
+    /// Number of files that failed to scan (details in `scan_errors`)
+    pub errors: usize,
+    /// Number of audio files skipped by ignore rules
+    pub ignored: usize,
+    /// Number of tracks kept as offline because their library directory is unavailable
+    pub offline: usize,
+    /// Library directories that were missing or empty, and so not scanned
+    pub offline_directories: Vec<String>,
     /// Whether this was the first successful scan
     pub is_initial_scan: bool,
+    /// Whether the scan was cancelled before it went through all files
+    pub cancelled: bool,
     /// Duration of scan in milliseconds
     pub duration_ms: u64,
 }
 
+/// Why a file failed to scan, stored in `scan_errors.error_kind`
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanErrorKind {
+    /// The file or directory entry could not be read (permissions, I/O errors)
+    Unreadable,
+    /// The content hash could not be computed
+    HashFailed,
+    /// lofty could not parse the file
+    ParseFailed,
+    /// A required tag is missing and no path template matched
+    MissingField,
+    /// The file has no tags and no path template matched
+    NoPrimaryTag,
+    /// The track could not be written to the database
+    Database,
+}
+
+impl ScanErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScanErrorKind::Unreadable => "unreadable",
+            ScanErrorKind::HashFailed => "hash_failed",
+            ScanErrorKind::ParseFailed => "parse_failed",
+            ScanErrorKind::MissingField => "missing_field",
+            ScanErrorKind::NoPrimaryTag => "no_primary_tag",
+            ScanErrorKind::Database => "database",
+        }
+    }
+}
+
+impl From<&MetadataError> for ScanErrorKind {
+    fn from(error: &MetadataError) -> Self {
+        match error {
+            MetadataError::ParseFailed { .. } => ScanErrorKind::ParseFailed,
+            MetadataError::MissingField { .. } => ScanErrorKind::MissingField,
+            MetadataError::NoPrimaryTag { .. } => ScanErrorKind::NoPrimaryTag,
+        }
+    }
+}
+
+/// A file that failed to scan
+#[derive(Debug, Clone)]
+pub struct ScanError {
+    pub file_path: String,
+    pub kind: ScanErrorKind,
+    pub message: String,
+}
+
+impl ScanError {
+    pub fn new(path: &std::path::Path, kind: ScanErrorKind, message: impl ToString) -> Self {
+        Self {
+            file_path: path.to_string_lossy().to_string(),
+            kind,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<(&std::path::Path, MetadataError)> for ScanError {
+    fn from((path, error): (&std::path::Path, MetadataError)) -> Self {
+        Self::new(path, ScanErrorKind::from(&error), error)
+    }
+}
+
 /// Progress update during scan
 #[derive(Debug, Clone, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -0,0 +1,275 @@
+use regex::Regex;
+use std::path::{Component, Path};
+use thiserror::Error;
+
+/// Templates used when the user has not configured any
+pub const DEFAULT_PATH_TEMPLATES: &str =
+    "{artist}/{album}/{track} - {title}\n{artist}/{album}/{title}\n{artist} - {title}";
+
+/// Errors that can occur while parsing a path template
+#[derive(Error, Debug)]
+pub enum PathTemplateError {
+    #[error("Unknown placeholder `{{{placeholder}}}` in path template `{template}`")]
+    UnknownPlaceholder {
+        placeholder: String,
+        template: String,
+    },
+
+    #[error("Unclosed placeholder in path template `{template}`")]
+    UnclosedPlaceholder { template: String },
+
+    #[error("Path template `{template}` has no placeholders")]
+    NoPlaceholders { template: String },
+}
+
+/// Track fields inferred from a file's path
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InferredFields {
+    pub title: Option<String>,
+    pub album: Option<String>,
+    pub artist: Option<String>,
+    pub album_artist: Option<String>,
+    pub track_number: Option<u32>,
+}
+
+/// A path template such as `{artist}/{album}/{track} - {title}`
+///
+/// Each `/`-separated segment is matched against the trailing components of a file path,
+/// the last segment against the file name without extension. Supported placeholders are
+/// `{title}`, `{album}`, `{artist}`, `{album_artist}`, `{track}` (digits) and `{ignore}`.
+#[derive(Debug, Clone)]
+pub struct PathTemplate {
+    template: String,
+    segments: Vec<Regex>,
+}
+
+impl PathTemplate {
+    pub fn parse(template: &str) -> Result<Self, PathTemplateError> {
+        let template = template.trim().trim_matches('/');
+        let mut has_placeholder = false;
+
+        let segments = template
+            .split('/')
+            .map(|segment| {
+                let (regex, segment_has_placeholder) = compile_segment(segment, template)?;
+                has_placeholder |= segment_has_placeholder;
+                Ok(regex)
+            })
+            .collect::<Result<Vec<_>, PathTemplateError>>()?;
+
+        if !has_placeholder {
+            return Err(PathTemplateError::NoPlaceholders {
+                template: template.to_owned(),
+            });
+        }
+
+        Ok(Self {
+            template: template.to_owned(),
+            segments,
+        })
+    }
+
+    /// Parse newline-separated templates (as stored in the config), skipping blank lines.
+    /// Fails on the first invalid template.
+    pub fn parse_list(templates: &str) -> Result<Vec<Self>, PathTemplateError> {
+        templates
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(Self::parse)
+            .collect()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// Match the trailing components of `path` against this template
+    pub fn match_path(&self, path: &Path) -> Option<InferredFields> {
+        let file_stem = path.file_stem()?.to_str()?;
+        let parents: Vec<&str> = path
+            .parent()?
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => name.to_str(),
+                _ => None,
+            })
+            .collect();
+
+        let dir_segment_count = self.segments.len() - 1;
+        if parents.len() < dir_segment_count {
+            return None;
+        }
+
+        let components = parents[parents.len() - dir_segment_count..]
+            .iter()
+            .copied()
+            .chain(std::iter::once(file_stem));
+
+        let mut fields = InferredFields::default();
+
+        for (regex, component) in self.segments.iter().zip(components) {
+            let captures = regex.captures(component)?;
+
+            for name in regex.capture_names().flatten() {
+                let value = captures.name(name)?.as_str().trim();
+                if value.is_empty() {
+                    return None;
+                }
+
+                match name {
+                    "title" => fields.title = Some(value.to_owned()),
+                    "album" => fields.album = Some(value.to_owned()),
+                    "artist" => fields.artist = Some(value.to_owned()),
+                    "album_artist" => fields.album_artist = Some(value.to_owned()),
+                    "track" => fields.track_number = value.parse().ok(),
+                    _ => {}
+                }
+            }
+        }
+
+        Some(fields)
+    }
+}
+
+/// Compile one template segment into an anchored regex.
+/// Returns whether the segment contains any placeholder.
+fn compile_segment(segment: &str, template: &str) -> Result<(Regex, bool), PathTemplateError> {
+    let mut pattern = String::from("^");
+    let mut rest = segment;
+    let mut has_placeholder = false;
+
+    while let Some(start) = rest.find('{') {
+        pattern.push_str(&regex::escape(&rest[..start]));
+
+        let Some(placeholder_len) = rest[start..].find('}') else {
+            return Err(PathTemplateError::UnclosedPlaceholder {
+                template: template.to_owned(),
+            });
+        };
+        let end = start + placeholder_len;
+
+        let placeholder = &rest[start + 1..end];
+        let group = match placeholder {
+            "title" | "album" | "artist" | "album_artist" => format!("(?P<{}>.+?)", placeholder),
+            "track" => "(?P<track>\\d+)".to_owned(),
+            "ignore" => ".+?".to_owned(),
+            _ => {
+                return Err(PathTemplateError::UnknownPlaceholder {
+                    placeholder: placeholder.to_owned(),
+                    template: template.to_owned(),
+                })
+            }
+        };
+
+        // The same placeholder twice in one segment would be an invalid regex;
+        // treat repeats as plain wildcards instead
+        if group.starts_with("(?P<") && pattern.contains(&format!("(?P<{}>", placeholder)) {
+            pattern.push_str(".+?");
+        } else {
+            pattern.push_str(&group);
+        }
+
+        has_placeholder = true;
+        rest = &rest[end + 1..];
+    }
+
+    pattern.push_str(&regex::escape(rest));
+    pattern.push('$');
+
+    let regex = Regex::new(&pattern).expect("escaped path template should be a valid regex");
+
+    Ok((regex, has_placeholder))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn path(parts: &[&str]) -> PathBuf {
+        parts.iter().collect()
+    }
+
+    #[test]
+    fn test_match_artist_album_track_title() {
+        let template = PathTemplate::parse("{artist}/{album}/{track} - {title}").unwrap();
+        let fields = template
+            .match_path(&path(&["music", "Artist", "Album", "03 - Song Name.flac"]))
+            .unwrap();
+
+        assert_eq!(fields.artist.as_deref(), Some("Artist"));
+        assert_eq!(fields.album.as_deref(), Some("Album"));
+        assert_eq!(fields.title.as_deref(), Some("Song Name"));
+        assert_eq!(fields.track_number, Some(3));
+    }
+
+    #[test]
+    fn test_no_match_when_segment_does_not_fit() {
+        let template = PathTemplate::parse("{artist}/{album}/{track} - {title}").unwrap();
+
+        assert!(template
+            .match_path(&path(&["music", "Artist", "Album", "Song Name.mp3"]))
+            .is_none());
+    }
+
+    #[test]
+    fn test_no_match_when_path_is_too_short() {
+        let template = PathTemplate::parse("{artist}/{album}/{title}").unwrap();
+
+        assert!(template.match_path(&path(&["Album", "Song.mp3"])).is_none());
+    }
+
+    #[test]
+    fn test_match_file_name_only() {
+        let template = PathTemplate::parse("{artist} - {title}").unwrap();
+        let fields = template
+            .match_path(&path(&["music", "Some Artist - A - Song.ogg"]))
+            .unwrap();
+
+        assert_eq!(fields.artist.as_deref(), Some("Some Artist"));
+        assert_eq!(fields.title.as_deref(), Some("A - Song"));
+        assert_eq!(fields.album, None);
+    }
+
+    #[test]
+    fn test_literal_characters_are_escaped() {
+        let template = PathTemplate::parse("{artist}/[{album}]/{title}").unwrap();
+        let fields = template
+            .match_path(&path(&["Artist", "[Album (Deluxe)]", "Song.mp3"]))
+            .unwrap();
+
+        assert_eq!(fields.album.as_deref(), Some("Album (Deluxe)"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            PathTemplate::parse("{artist}/{year}/{title}"),
+            Err(PathTemplateError::UnknownPlaceholder { .. })
+        ));
+        assert!(matches!(
+            PathTemplate::parse("{artist}/{title"),
+            Err(PathTemplateError::UnclosedPlaceholder { .. })
+        ));
+        assert!(matches!(
+            PathTemplate::parse("music/songs"),
+            Err(PathTemplateError::NoPlaceholders { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_list_skips_blank_lines() {
+        let templates = PathTemplate::parse_list("{artist}/{title}\n\n  \n{title}").unwrap();
+        let templates: Vec<&str> = templates.iter().map(|t| t.as_str()).collect();
+
+        assert_eq!(templates, vec!["{artist}/{title}", "{title}"]);
+    }
+
+    #[test]
+    fn test_parse_list_rejects_invalid_templates() {
+        assert!(matches!(
+            PathTemplate::parse_list("{artist}/{title}\n{nope}\n{title}"),
+            Err(PathTemplateError::UnknownPlaceholder { .. })
+        ));
+    }
+}
@@ -1,16 +1,33 @@
 use crate::db;
 use crate::db::ScanTrackInfo;
 use crate::lyricsfile::{build_lyricsfile, LyricsfileTrackMetadata};
-use crate::scanner::hasher::compute_quick_hash;
-use crate::scanner::metadata::extract_track_info;
-use crate::scanner::models::{ScanProgress, ScanResult};
+use crate::scanner::artist_credit::split_artist_credit;
+use crate::scanner::artwork;
+use crate::scanner::cue::{read_cue_sheets, CueSheet};
+use crate::scanner::hasher::{compute_full_hash, compute_quick_hash, compute_virtual_track_hash};
+use crate::scanner::ignore_rules::{IgnoreRules, IGNORE_FILE_NAME};
+use crate::scanner::job::ScanJob;
+use crate::scanner::metadata::{extract_cue_tracks, extract_track_info, LyricsInfo, TrackMetadata};
+use crate::scanner::models::{
+    DbTrack, ScanError, ScanErrorKind, ScanOptions, ScanProgress, ScanResult,
+};
 use anyhow::Result;
-use globwalk::glob;
+use globwalk::{glob_builder, GlobWalker};
+use rayon::prelude::*;
 use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender};
 use std::time::{Instant, SystemTime};
 
 const BATCH_SIZE: usize = 100;
 
+/// Prepared batches the walker of a scan may get ahead of the writer
+const PIPELINE_DEPTH: usize = 2;
+
+/// Audio file extensions scanned when the user has not configured any
+pub const DEFAULT_AUDIO_EXTENSIONS: &str = "mp3,m4a,m4b,flac,ogg,opus,wav,aif,aiff,ape,wv,mpc";
+
 /// Method to detect file changes during scan
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum DetectionMethod {
@@ -19,6 +36,9 @@ pub enum DetectionMethod {
     /// - Cross-platform timestamp differences
     /// - Filesystems with different timestamp precision
     Hash,
+    /// Like `Hash`, but hashing whole files instead of their first 64KB - slowest, but files
+    /// that start the same (e.g. with the same large embedded cover) are told apart
+    FullHash,
     /// Use file metadata (mtime + size) - faster but with limitations:
     /// - May create duplicates if files are moved with different metadata
     /// - Best for: Large libraries on single filesystem that rarely move
@@ -31,250 +51,1245 @@ impl Default for DetectionMethod {
     }
 }
 
+impl DetectionMethod {
+    /// Parse the value stored in `config_data.detection_method`
+    pub fn from_config_value(value: &str) -> Option<Self> {
+        match value {
+            "hash" => Some(DetectionMethod::Hash),
+            "full_hash" => Some(DetectionMethod::FullHash),
+            "metadata" => Some(DetectionMethod::Metadata),
+            _ => None,
+        }
+    }
+}
+
 /// Single-pass streaming scan - discovers and processes files simultaneously
 ///
 /// This approach eliminates the double-traversal problem by processing files
 /// in a single pass and emitting progress updates showing processed count.
+///
+/// `job` is checked before each batch: a paused scan waits there, and a cancelled scan
+/// stops after the current batch without deleting anything. This is no rollback: batches
+/// committed so far are kept, and tracks not reached yet are restored from pending instead
+/// of being treated as deleted files.
+///
+/// Library directories that are missing or empty are not walked; their tracks are marked
+/// offline (see `is_directory_available`) and listed in `ScanResult::offline_directories`.
 pub fn scan_library(
     directories: &[String],
     conn: &mut Connection,
     progress_callback: &dyn Fn(ScanProgress),
-    detection_method: DetectionMethod,
+    options: &ScanOptions,
+    job: &ScanJob,
 ) -> Result<ScanResult> {
     let start_time = Instant::now();
     let is_initial_scan = !db::get_init(conn)?;
 
-    // Phase 1: Mark all tracks as pending
+    // Phase 1: Mark all tracks as pending and forget errors of the previous scan
     db::mark_all_tracks_pending(conn)?;
+    db::clear_scan_errors(None, conn)?;
+
+    // An unmounted drive looks like a folder whose files were all deleted. Keep the tracks of
+    // unavailable directories as offline instead; they become regular tracks again once the
+    // directory is back.
+    let (available_directories, offline_directories): (Vec<String>, Vec<String>) = directories
+        .iter()
+        .cloned()
+        .partition(|directory| is_directory_available(Path::new(directory)));
+    let offline = db::mark_tracks_offline_by_paths(&offline_directories, conn)?;
+
+    let mut result = scan_pending(
+        &available_directories,
+        conn,
+        progress_callback,
+        options,
+        job,
+        start_time,
+        is_initial_scan,
+    )?;
+    result.offline = offline;
+    result.offline_directories = offline_directories;
+
+    // Mark as initialized after first successful scan
+    if is_initial_scan && !result.cancelled {
+        db::set_init(true, conn)?;
+    }
+
+    Ok(result)
+}
+
+/// Scan only the given directories (subtrees) or files of the library
+///
+/// Works like `scan_library`, but only tracks below these paths are marked pending, so
+/// tracks elsewhere in the library are left untouched and only missing files below the
+/// paths are deleted. A file path is re-read on its own (unless it is no audio file or is
+/// ignored, which deletes its track). A path that does not exist anymore is not walked,
+/// which deletes all of its tracks.
+pub fn scan_paths(
+    paths: &[String],
+    conn: &mut Connection,
+    progress_callback: &dyn Fn(ScanProgress),
+    options: &ScanOptions,
+    job: &ScanJob,
+) -> Result<ScanResult> {
+    let start_time = Instant::now();
+    let paths = normalize_scan_paths(paths);
+
+    // Phase 1: Mark the tracks below the paths as pending and forget their errors
+    db::mark_tracks_pending_by_paths(&paths, conn)?;
+    db::delete_scan_errors_by_paths(&paths, conn)?;
+
+    let existing_paths: Vec<String> = paths
+        .into_iter()
+        .filter(|path| Path::new(path).exists())
+        .collect();
+
+    scan_pending(
+        &existing_paths,
+        conn,
+        progress_callback,
+        options,
+        job,
+        start_time,
+        false,
+    )
+}
+
+/// Walk and process the given paths, then delete the tracks that are still pending
+/// (phases 2 and 3 of a scan; the caller marks the tracks to check as pending first)
+///
+/// Phase 2 is a two-stage pipeline: a walker thread discovers the files and prepares each
+/// batch on the worker pool (see `walk_and_prepare`), and hands it over a bounded channel to
+/// the writer (the calling thread, which owns the connection). So the next batch is walked,
+/// stat'ed and hashed while the writer reads tags of the current one and commits it.
+fn scan_pending(
+    directories: &[String],
+    conn: &mut Connection,
+    progress_callback: &dyn Fn(ScanProgress),
+    options: &ScanOptions,
+    job: &ScanJob,
+    start_time: Instant,
+    is_initial_scan: bool,
+) -> Result<ScanResult> {
+    let pool = build_worker_pool(options)?;
+    let (sender, receiver) = mpsc::sync_channel(PIPELINE_DEPTH);
 
     let mut total_files = 0;
     let mut processed_files = 0;
     let mut added = 0;
+    let mut modified = 0;
     let mut moved = 0;
     let mut unchanged = 0;
+    let mut errors = 0;
+    let mut cancelled = false;
+
+    // Phase 2: Discover AND process files in a single pass
+    let ignored = std::thread::scope(|scope| -> Result<usize> {
+        // Owned by this closure, so the walker stops as soon as the writer returns early
+        let receiver: Receiver<WalkedBatch> = receiver;
+        let pool = &pool;
+        let walker = scope.spawn(move || walk_and_prepare(directories, options, pool, sender));
+
+        for walked in receiver.iter() {
+            let (prepared, discovered_files) = match walked {
+                WalkedBatch::Prepared(prepared, discovered_files) => (prepared, discovered_files),
+                WalkedBatch::WalkError(directory, e) => {
+                    record_walk_error(&directory, &e, conn)?;
+                    errors += 1;
+                    continue;
+                }
+            };
+            total_files = discovered_files;
+
+            if !job.checkpoint() {
+                cancelled = true;
+                break;
+            }
+
+            let batch_len = prepared.len();
+            let batch_result = process_prepared_batch(prepared, conn, options, pool)?;
+            added += batch_result.added;
+            modified += batch_result.modified;
+            moved += batch_result.moved;
+            unchanged += batch_result.unchanged;
+            errors += batch_result.errors;
+            processed_files += batch_len;
+
+            // Emit progress after processing each batch
+            progress_callback(ScanProgress::processing(processed_files, total_files));
+        }
+
+        drop(receiver);
+        walker
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })?;
+
+    // Phase 3: Delete tracks that weren't processed (deleted files)
+    progress_callback(ScanProgress::updating());
+    let deleted = if cancelled {
+        db::restore_pending_tracks(conn)?;
+        0
+    } else {
+        let deleted = db::delete_unprocessed_tracks(conn)?;
+        update_album_artwork(conn, options, &pool, directories)?;
+        deleted
+    };
+
+    let duration_ms = start_time.elapsed().as_millis() as u64;
+
+    Ok(ScanResult {
+        total_files,
+        added,
+        modified,
+        deleted,
+        moved,
+        unchanged,
+        errors,
+        ignored,
+        offline: 0,
+        offline_directories: Vec::new(),
+        is_initial_scan,
+        cancelled,
+        duration_ms,
+    })
+}
+
+/// Walk the directories of a scan and send their files to the writer in prepared batches
+/// (see `prepare_batch`), along with unreadable entries. A path that is a file is taken as
+/// is. Returns the number of ignored files. Stops early once the writer hung up, i.e. the
+/// scan was cancelled or failed.
+fn walk_and_prepare(
+    directories: &[String],
+    options: &ScanOptions,
+    pool: &rayon::ThreadPool,
+    sender: SyncSender<WalkedBatch>,
+) -> Result<usize> {
+    let mut ignore_rules = IgnoreRules::new(&options.ignore_patterns);
+    let mut discovered_files = 0;
+    let mut ignored = 0;
     let mut batch = Vec::with_capacity(BATCH_SIZE);
 
-    // Phase 2: Stream through files with globwalk - discover AND process in single pass
     for directory in directories {
-        let pattern = format!(
-            "{}/**/*.{{mp3,m4a,flac,ogg,opus,wav,MP3,M4A,FLAC,OGG,OPUS,WAV}}",
-            directory
-        );
+        let path = Path::new(directory);
+        let entries: Box<dyn Iterator<Item = std::result::Result<PathBuf, globwalk::WalkError>>> =
+            if path.is_file() {
+                if !is_audio_file(path, &options.audio_extensions) {
+                    continue;
+                }
+                Box::new(std::iter::once(Ok(path.to_path_buf())))
+            } else {
+                Box::new(
+                    walk_audio_files(directory, &options.audio_extensions)?
+                        .map(|item| item.map(|entry| entry.into_path())),
+                )
+            };
 
-        for item in glob(&pattern)? {
+        for item in entries {
             match item {
-                Ok(entry) => {
-                    batch.push(entry);
-                    total_files += 1;
-
-                    if batch.len() >= BATCH_SIZE {
-                        let batch_result = process_batch(&batch, conn, detection_method)?;
-                        added += batch_result.added;
-                        moved += batch_result.moved;
-                        unchanged += batch_result.unchanged;
-                        processed_files += batch.len();
+                Ok(file_path) => {
+                    // Ignored files stay pending, so tracks already in the library are
+                    // deleted in phase 3
+                    if ignore_rules.is_ignored(&file_path, false) {
+                        ignored += 1;
+                        continue;
+                    }
 
-                        // Emit progress after processing each batch
-                        progress_callback(ScanProgress::processing(processed_files, total_files));
+                    batch.push(file_path);
+                    discovered_files += 1;
 
+                    if batch.len() >= BATCH_SIZE {
+                        let prepared = prepare_batch(&batch, options, pool);
+                        if sender
+                            .send(WalkedBatch::Prepared(prepared, discovered_files))
+                            .is_err()
+                        {
+                            return Ok(ignored);
+                        }
                         batch.clear();
                     }
                 }
                 Err(e) => {
-                    eprintln!("Error reading entry: {}", e);
+                    if sender
+                        .send(WalkedBatch::WalkError(directory.clone(), e))
+                        .is_err()
+                    {
+                        return Ok(ignored);
+                    }
                 }
             }
         }
     }
 
-    // Process remaining files in batch
+    // Remaining files of the last batch
     if !batch.is_empty() {
-        let batch_result = process_batch(&batch, conn, detection_method)?;
+        let prepared = prepare_batch(&batch, options, pool);
+        let _ = sender.send(WalkedBatch::Prepared(prepared, discovered_files));
+    }
+
+    Ok(ignored)
+}
+
+/// Apply a set of changed paths reported by the filesystem watcher to the library
+///
+/// Paths that still exist are processed like a regular scan batch (directories are
+/// walked for audio files), and tracks at paths that are gone get deleted. Existing
+/// paths are handled first so a rename is detected as a move instead of delete + add.
+/// Files excluded by ignore rules are treated like removed files, and a changed
+/// `.lrcgetignore` or CUE sheet re-applies its whole directory. Paths that are gone
+/// because their library directory (one of `directories`) became unavailable, e.g. an
+/// unmounted drive, have the tracks of the whole directory marked offline instead.
+pub fn apply_file_changes(
+    paths: &[PathBuf],
+    directories: &[String],
+    conn: &mut Connection,
+    progress_callback: &dyn Fn(ScanProgress),
+    options: &ScanOptions,
+) -> Result<ScanResult> {
+    let start_time = Instant::now();
+    let pool = build_worker_pool(options)?;
+    let mut ignore_rules = IgnoreRules::new(&options.ignore_patterns);
+
+    let mut files = Vec::new();
+    let mut removed_paths = Vec::new();
+    let mut errors = 0;
+    let mut ignored = 0;
+
+    let mut paths: Vec<&Path> = paths
+        .iter()
+        .map(|path| match path.parent() {
+            Some(parent)
+                if path.file_name() == Some(IGNORE_FILE_NAME.as_ref())
+                    || path
+                        .extension()
+                        .is_some_and(|ext| ext.eq_ignore_ascii_case("cue")) =>
+            {
+                parent
+            }
+            _ => path.as_path(),
+        })
+        .collect();
+    paths.sort();
+    paths.dedup();
+
+    // Errors recorded for these paths are stale now; they are recorded again if they persist
+    let changed_paths: Vec<String> = paths
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    db::delete_scan_errors_by_paths(&changed_paths, conn)?;
+
+    for path in paths {
+        if path.is_dir() {
+            let directory = path.to_string_lossy();
+            for item in walk_audio_files(&directory, &options.audio_extensions)? {
+                match item {
+                    Ok(entry) if ignore_rules.is_ignored(entry.path(), false) => {
+                        removed_paths.push(entry.path().to_string_lossy().to_string());
+                        ignored += 1;
+                    }
+                    Ok(entry) => files.push(entry.into_path()),
+                    Err(e) => {
+                        record_walk_error(&directory, &e, conn)?;
+                        errors += 1;
+                    }
+                }
+            }
+        } else if path.is_file() {
+            if !is_audio_file(path, &options.audio_extensions) {
+                continue;
+            }
+
+            if ignore_rules.is_ignored(path, false) {
+                removed_paths.push(path.to_string_lossy().to_string());
+                ignored += 1;
+            } else {
+                files.push(path.to_path_buf());
+            }
+        } else {
+            removed_paths.push(path.to_string_lossy().to_string());
+        }
+    }
+
+    files.sort();
+    files.dedup();
+
+    let total_files = files.len();
+    let mut processed_files = 0;
+    let mut added = 0;
+    let mut modified = 0;
+    let mut moved = 0;
+    let mut unchanged = 0;
+
+    for batch in files.chunks(BATCH_SIZE) {
+        let batch_result = process_batch(batch, conn, options, &pool)?;
         added += batch_result.added;
+        modified += batch_result.modified;
         moved += batch_result.moved;
         unchanged += batch_result.unchanged;
+        errors += batch_result.errors;
         processed_files += batch.len();
 
-        // Emit final progress
         progress_callback(ScanProgress::processing(processed_files, total_files));
     }
 
-    // Phase 3: Delete tracks that weren't processed (deleted files)
-    progress_callback(ScanProgress::updating());
-    let deleted = db::delete_unprocessed_tracks(conn)?;
-
-    // Mark as initialized after first successful scan
-    if is_initial_scan {
-        db::set_init(true, conn)?;
+    // An unmounted drive looks like all of its files were deleted; keep their tracks (and
+    // lyricsfiles) offline like a scan does, until the directory is available again
+    let mut offline_directories: Vec<String> = Vec::new();
+    for removed_path in &removed_paths {
+        let directory = directories
+            .iter()
+            .find(|directory| Path::new(removed_path).starts_with(directory));
+        if let Some(directory) = directory {
+            if !offline_directories.contains(directory)
+                && !is_directory_available(Path::new(directory))
+            {
+                offline_directories.push(directory.clone());
+            }
+        }
     }
+    removed_paths.retain(|removed_path| {
+        !offline_directories
+            .iter()
+            .any(|directory| Path::new(removed_path).starts_with(directory))
+    });
+
+    progress_callback(ScanProgress::updating());
+    let offline = db::mark_tracks_offline_by_paths(&offline_directories, conn)?;
+    let deleted = db::delete_tracks_by_paths(&removed_paths, conn)?;
+    update_album_artwork(conn, options, &pool, &changed_paths)?;
 
     let duration_ms = start_time.elapsed().as_millis() as u64;
 
     Ok(ScanResult {
         total_files,
         added,
-        modified: 0,
+        modified,
         deleted,
         moved,
         unchanged,
-        is_initial_scan,
+        errors,
+        ignored,
+        offline,
+        offline_directories,
+        is_initial_scan: false,
+        cancelled: false,
         duration_ms,
     })
 }
 
+/// A change to the artwork of an album, found by `update_album_artwork`
+enum ArtworkUpdate {
+    /// Looked up artwork (None if there is none) and the file it came from, with the file's
+    /// quick hash and fingerprint
+    Artwork {
+        album_id: i64,
+        image_path: Option<String>,
+        source_path: String,
+        source_hash: String,
+        source_fingerprint: (i64, i64),
+    },
+    /// The source changed its size or mtime but not its content
+    SourceFingerprint {
+        album_id: i64,
+        source_fingerprint: (i64, i64),
+    },
+}
+
+/// Look up the artwork of albums that need it and write their thumbnails to the cache
+/// (in parallel), then remove cached thumbnails no album refers to anymore.
+///
+/// Only albums with a track or artwork source below `touched_paths` (the walked
+/// directories or the paths the watcher reported) are checked. Artwork comes from the first
+/// track of the album (embedded pictures, else a cover image next to it). The source's quick
+/// hash, size and mtime are stored with it: a modified source track resets it (see
+/// `update_modified_file`), and a cover image whose size or mtime changed is hashed again, so
+/// changed artwork gets a new thumbnail. A thumbnail that could not be written is looked up
+/// again on the next pass.
+fn update_album_artwork(
+    conn: &mut Connection,
+    options: &ScanOptions,
+    pool: &rayon::ThreadPool,
+    touched_paths: &[String],
+) -> Result<()> {
+    let Some(cache_dir) = &options.artwork_cache_dir else {
+        return Ok(());
+    };
+    std::fs::create_dir_all(cache_dir)?;
+
+    let is_touched = |path: &str| {
+        touched_paths
+            .iter()
+            .any(|touched_path| Path::new(path).starts_with(touched_path))
+    };
+    let candidates: Vec<db::AlbumArtworkCandidate> = db::get_album_artwork_candidates(conn)?
+        .into_iter()
+        .filter(|candidate| {
+            is_touched(&candidate.track_path)
+                || candidate
+                    .image_source_path
+                    .as_deref()
+                    .is_some_and(|source_path| is_touched(source_path))
+        })
+        .collect();
+
+    let updates: Vec<ArtworkUpdate> = pool.install(|| {
+        candidates
+            .par_iter()
+            .filter_map(|candidate| {
+                // A cover image that is still there and unchanged keeps its thumbnail
+                if let (Some(source_path), Some(source_hash)) =
+                    (&candidate.image_source_path, &candidate.image_source_hash)
+                {
+                    let source_path = Path::new(source_path);
+                    if let Some(source_fingerprint) = file_fingerprint(source_path) {
+                        let stored_fingerprint = candidate
+                            .image_source_size
+                            .zip(candidate.image_source_modified_time);
+                        if stored_fingerprint == Some(source_fingerprint) {
+                            return None;
+                        }
+                        // Only touched, e.g. copied with a new mtime
+                        if compute_quick_hash(source_path).ok().as_ref() == Some(source_hash) {
+                            return Some(ArtworkUpdate::SourceFingerprint {
+                                album_id: candidate.album_id,
+                                source_fingerprint,
+                            });
+                        }
+                    }
+                }
+
+                let track_path = Path::new(&candidate.track_path);
+                let Some(found) = artwork::find_artwork(track_path) else {
+                    // Remember the lookup, so it is only retried once the track changes
+                    return Some(ArtworkUpdate::Artwork {
+                        album_id: candidate.album_id,
+                        image_path: None,
+                        source_path: candidate.track_path.clone(),
+                        source_hash: compute_quick_hash(track_path).ok()?,
+                        source_fingerprint: file_fingerprint(track_path)?,
+                    });
+                };
+
+                let source_path = found.source_path.to_string_lossy().to_string();
+                let source_hash = compute_quick_hash(&found.source_path).ok()?;
+                let source_fingerprint = file_fingerprint(&found.source_path)?;
+                let thumbnail_path =
+                    artwork::thumbnail_path(cache_dir, candidate.album_id, &source_hash);
+
+                if !thumbnail_path.exists() {
+                    if let Err(e) = artwork::write_thumbnail(&found.data, &thumbnail_path) {
+                        eprintln!("Error writing artwork of {}: {}", source_path, e);
+                        return None;
+                    }
+                }
+
+                Some(ArtworkUpdate::Artwork {
+                    album_id: candidate.album_id,
+                    image_path: Some(thumbnail_path.to_string_lossy().to_string()),
+                    source_path,
+                    source_hash,
+                    source_fingerprint,
+                })
+            })
+            .collect()
+    });
+
+    let tx = db::write_transaction(conn)?;
+    for update in &updates {
+        match update {
+            ArtworkUpdate::Artwork {
+                album_id,
+                image_path,
+                source_path,
+                source_hash,
+                source_fingerprint: (source_size, source_modified_time),
+            } => db::set_album_artwork_tx(
+                *album_id,
+                image_path.as_deref(),
+                source_path,
+                source_hash,
+                *source_size,
+                *source_modified_time,
+                &tx,
+            )?,
+            ArtworkUpdate::SourceFingerprint {
+                album_id,
+                source_fingerprint: (source_size, source_modified_time),
+            } => db::set_album_artwork_source_fingerprint_tx(
+                *album_id,
+                *source_size,
+                *source_modified_time,
+                &tx,
+            )?,
+        }
+    }
+    tx.commit()?;
+
+    let image_paths: HashSet<PathBuf> = db::get_album_image_paths(conn)?
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+    for entry in std::fs::read_dir(cache_dir)?.filter_map(|entry| entry.ok()) {
+        if !image_paths.contains(&entry.path()) {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+
+    Ok(())
+}
+
+/// Size and mtime (in seconds) of a file, None if it cannot be read
+fn file_fingerprint(path: &Path) -> Option<(i64, i64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified_time = metadata
+        .modified()
+        .ok()?
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .ok()?
+        .as_secs() as i64;
+
+    Some((metadata.len() as i64, modified_time))
+}
+
+/// Record a directory entry the walker could not read
+fn record_walk_error(
+    directory: &str,
+    error: &globwalk::WalkError,
+    conn: &Connection,
+) -> Result<()> {
+    let path = error.path().unwrap_or(Path::new(directory));
+    let scan_error = ScanError::new(path, ScanErrorKind::Unreadable, error);
+
+    eprintln!("Error reading entry: {}", error);
+    db::insert_scan_error(&scan_error, conn)
+}
+
+/// Parse a comma or whitespace separated extension list (as stored in the config) into
+/// lowercase extensions without leading dots, falling back to the defaults when empty
+pub fn parse_audio_extensions(extensions: &str) -> Vec<String> {
+    let mut parsed: Vec<String> = Vec::new();
+
+    for extension in extensions.split(|c: char| c == ',' || c.is_whitespace()) {
+        let extension = extension.trim().trim_start_matches('.').to_lowercase();
+        if !extension.is_empty() && !parsed.contains(&extension) {
+            parsed.push(extension);
+        }
+    }
+
+    if parsed.is_empty() {
+        parsed = DEFAULT_AUDIO_EXTENSIONS
+            .split(',')
+            .map(|extension| extension.to_owned())
+            .collect();
+    }
+
+    parsed
+}
+
+/// Check whether a library directory can be scanned. A missing directory, or an empty one
+/// that is no mount point itself, usually is where a drive or network share gets mounted
+/// while it is not mounted. A mounted drive counts as available even when it is empty.
+pub fn is_directory_available(directory: &Path) -> bool {
+    let Ok(mut entries) = std::fs::read_dir(directory) else {
+        return false;
+    };
+
+    is_mount_point(directory) || entries.next().is_some()
+}
+
+/// Check whether a directory is the root of a mounted filesystem, i.e. on another device
+/// than its parent
+#[cfg(unix)]
+fn is_mount_point(directory: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let Some(parent) = directory.parent() else {
+        // The root of the filesystem
+        return true;
+    };
+
+    match (std::fs::metadata(directory), std::fs::metadata(parent)) {
+        (Ok(directory), Ok(parent)) => directory.dev() != parent.dev(),
+        _ => false,
+    }
+}
+
+/// Drives and network shares cannot be read at all while they are gone, so there is no
+/// mount point to tell apart from an empty directory
+#[cfg(not(unix))]
+fn is_mount_point(_directory: &Path) -> bool {
+    false
+}
+
+/// Normalize the paths of a partial scan: trailing separators are dropped and paths below
+/// another given path are skipped, so no file is walked twice
+fn normalize_scan_paths(paths: &[String]) -> Vec<String> {
+    let mut paths: Vec<PathBuf> = paths
+        .iter()
+        .map(|path| Path::new(path).components().collect())
+        .collect();
+    paths.sort();
+    paths.dedup();
+
+    let mut normalized: Vec<PathBuf> = Vec::new();
+    for path in paths {
+        // Sorted order puts a parent directory before anything below it
+        if !normalized.iter().any(|parent| path.starts_with(parent)) {
+            normalized.push(path);
+        }
+    }
+
+    normalized
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect()
+}
+
+/// Walk all files below a directory with one of the given extensions (matched case-insensitively)
+fn walk_audio_files(directory: &str, extensions: &[String]) -> Result<GlobWalker> {
+    let pattern = format!("{}/**/*.{{{}}}", directory, extensions.join(","));
+    let walker = glob_builder(pattern).case_insensitive(true).build()?;
+
+    Ok(walker)
+}
+
+/// Check whether a path has one of the given audio file extensions
+fn is_audio_file(path: &Path, extensions: &[String]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            extensions
+                .iter()
+                .any(|audio_ext| ext.eq_ignore_ascii_case(audio_ext))
+        })
+}
+
 #[derive(Default)]
 struct BatchResult {
     added: usize,
+    modified: usize,
     moved: usize,
     unchanged: usize,
+    errors: usize,
+}
+
+/// What the walker thread of a scan hands to the writer
+enum WalkedBatch {
+    /// The files of a batch, prepared on the worker pool, and the number of files
+    /// discovered so far
+    Prepared(Vec<std::result::Result<PreparedFile, ScanError>>, usize),
+    /// An entry below the given library directory that could not be read
+    WalkError(String, globwalk::WalkError),
+}
+
+/// A discovered file with its fingerprint, produced by the parallel stage
+struct PreparedFile {
+    path: PathBuf,
+    file_size: i64,
+    modified_time: i64,
+    /// Computed up front only with Hash detection
+    content_hash: Option<String>,
+    /// CUE sheet splitting the file into virtual tracks, if any
+    cue_sheet: Option<CueSheet>,
+}
+
+/// Content hash plus tags and lyrics of a new or modified file, one entry per track
+/// (several for a file split by a CUE sheet)
+struct ExtractedFile {
+    content_hash: String,
+    tracks: Vec<(TrackMetadata, LyricsInfo)>,
+}
+
+/// Build the worker pool used for hashing and tag extraction.
+/// A worker count of 0 lets rayon pick one worker per CPU core.
+fn build_worker_pool(options: &ScanOptions) -> Result<rayon::ThreadPool> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.worker_count)
+        .thread_name(|index| format!("scan-worker-{}", index))
+        .build()?;
+
+    Ok(pool)
+}
+
+/// Hash a track is stored with: the file's hash, salted for virtual tracks of a CUE sheet
+/// (see `compute_virtual_track_hash`)
+fn track_hash(file_hash: &str, track_index: usize, start_offset: Option<f64>) -> String {
+    match start_offset {
+        Some(start_offset) => compute_virtual_track_hash(file_hash, track_index, start_offset),
+        None => file_hash.to_owned(),
+    }
+}
+
+impl PreparedFile {
+    /// Hash the first track of this file is stored with, to look up where it moved from
+    fn first_track_hash(&self) -> Option<String> {
+        let file_hash = self.content_hash.as_ref()?;
+        let first_start = self
+            .cue_sheet
+            .as_ref()
+            .and_then(|sheet| sheet.tracks_for(&self.path))
+            .and_then(|tracks| tracks.first())
+            .map(|track| track.start);
+
+        Some(track_hash(file_hash, 0, first_start))
+    }
+}
+
+/// Check whether a file at an already known path still matches its stored fingerprint.
+/// The content hash is only compared when it was computed (Hash detection), and a file
+/// that gained or lost its CUE sheet always counts as modified. `existing` is the first
+/// track of the file.
+fn is_unchanged(existing: &DbTrack, file: &PreparedFile) -> bool {
+    let fingerprint_matches = existing.file_size == Some(file.file_size)
+        && existing.modified_time == Some(file.modified_time);
+    let hash_matches = file.content_hash.as_ref().map_or(true, |hash| {
+        existing.content_hash.as_ref() == Some(&track_hash(hash, 0, existing.start_offset))
+    });
+    let split_matches = existing.start_offset.is_some() == file.cue_sheet.is_some();
+
+    fingerprint_matches && hash_matches && split_matches
+}
+
+/// Read file size and mtime, and the content hash when using (full) hash detection.
+/// For a file split by one of `cue_sheets`, the sheet's mtime counts too, so editing
+/// the sheet re-reads the file.
+fn prepare_file(
+    path: &Path,
+    cue_sheets: &[CueSheet],
+    detection_method: DetectionMethod,
+) -> std::result::Result<PreparedFile, ScanError> {
+    let metadata = std::fs::metadata(path).map_err(|e| {
+        ScanError::new(
+            path,
+            ScanErrorKind::Unreadable,
+            format!("Error getting metadata: {}", e),
+        )
+    })?;
+
+    let modified_time = metadata
+        .modified()
+        .map_err(anyhow::Error::from)
+        .and_then(|time| Ok(time.duration_since(SystemTime::UNIX_EPOCH)?))
+        .map_err(|e| {
+            ScanError::new(
+                path,
+                ScanErrorKind::Unreadable,
+                format!("Error getting modified time: {}", e),
+            )
+        })?
+        .as_secs() as i64;
+
+    let cue_sheet = cue_sheets
+        .iter()
+        .find(|sheet| sheet.tracks_for(path).is_some())
+        .cloned();
+    let cue_modified_time = cue_sheet
+        .as_ref()
+        .and_then(|sheet| std::fs::metadata(&sheet.path).ok())
+        .and_then(|metadata| metadata.modified().ok())
+        .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_secs() as i64);
+
+    let content_hash = match detection_method {
+        DetectionMethod::Hash => Some(hash_file(path)?),
+        DetectionMethod::FullHash => Some(full_hash_file(path)?),
+        DetectionMethod::Metadata => None,
+    };
+
+    Ok(PreparedFile {
+        path: path.to_path_buf(),
+        file_size: metadata.len() as i64,
+        modified_time: modified_time.max(cue_modified_time),
+        content_hash,
+        cue_sheet,
+    })
+}
+
+/// Quick-hash a file, reporting failures as scan errors
+fn hash_file(path: &Path) -> std::result::Result<String, ScanError> {
+    compute_quick_hash(path).map_err(|e| {
+        ScanError::new(
+            path,
+            ScanErrorKind::HashFailed,
+            format!("Error hashing file: {}", e),
+        )
+    })
+}
+
+/// Hash a whole file, reporting failures as scan errors
+fn full_hash_file(path: &Path) -> std::result::Result<String, ScanError> {
+    compute_full_hash(path).map_err(|e| {
+        ScanError::new(
+            path,
+            ScanErrorKind::HashFailed,
+            format!("Error hashing file: {}", e),
+        )
+    })
+}
+
+/// Hash (if not done yet) and read tags plus sidecar/embedded lyrics of a file
+fn extract_file(
+    file: &PreparedFile,
+    options: &ScanOptions,
+) -> std::result::Result<ExtractedFile, ScanError> {
+    let content_hash = match &file.content_hash {
+        Some(hash) => hash.clone(),
+        None => hash_file(&file.path)?,
+    };
+    let tracks = match &file.cue_sheet {
+        Some(cue_sheet) => extract_cue_tracks(&file.path, cue_sheet),
+        None => extract_track_info(
+            &file.path,
+            options.lyrics_precedence,
+            &options.path_templates,
+        )
+        .map(|track| vec![track]),
+    }
+    .map_err(|e| ScanError::from((file.path.as_path(), e)))?;
+    let tracks = tracks
+        .into_iter()
+        .map(|(mut metadata, lyrics)| {
+            if !metadata.artists_tagged {
+                metadata.artists =
+                    split_artist_credit(&metadata.artists, &options.artist_separators);
+            }
+            (metadata, lyrics)
+        })
+        .collect();
+
+    Ok(ExtractedFile {
+        content_hash,
+        tracks,
+    })
 }
 
+/// Process one batch of files, see `prepare_batch` and `process_prepared_batch`
 fn process_batch(
-    batch: &[globwalk::DirEntry],
+    batch: &[PathBuf],
     conn: &mut Connection,
-    detection_method: DetectionMethod,
+    options: &ScanOptions,
+    pool: &rayon::ThreadPool,
+) -> Result<BatchResult> {
+    let prepared = prepare_batch(batch, options, pool);
+
+    process_prepared_batch(prepared, conn, options, pool)
+}
+
+/// Read the CUE sheets of the batch's directories, then stat and, with Hash detection, hash
+/// every file of the batch (in parallel on the worker pool)
+fn prepare_batch(
+    batch: &[PathBuf],
+    options: &ScanOptions,
+    pool: &rayon::ThreadPool,
+) -> Vec<std::result::Result<PreparedFile, ScanError>> {
+    let mut directories: Vec<&Path> = batch.iter().filter_map(|path| path.parent()).collect();
+    directories.sort();
+    directories.dedup();
+
+    pool.install(|| {
+        let cue_sheets: HashMap<&Path, Vec<CueSheet>> = directories
+            .par_iter()
+            .map(|directory| (*directory, read_cue_sheets(directory)))
+            .collect();
+
+        batch
+            .par_iter()
+            .map(|path| {
+                let sheets = path
+                    .parent()
+                    .and_then(|directory| cue_sheets.get(directory))
+                    .map_or(&[][..], |sheets| sheets.as_slice());
+                prepare_file(path, sheets, options.detection_method)
+            })
+            .collect()
+    })
+}
+
+/// Apply a prepared batch of files to the library in three stages:
+/// 1. (writer) resolve unchanged and moved files against the database, reading only
+/// 2. (parallel) read tags of new and modified files only
+/// 3. (writer) apply everything in one write transaction and commit the batch
+///
+/// Only the calling thread touches the database, and the write lock is only held for the
+/// last stage. Files that fail at any stage (including preparation) are recorded in
+/// `scan_errors` within that transaction.
+fn process_prepared_batch(
+    prepared: Vec<std::result::Result<PreparedFile, ScanError>>,
+    conn: &mut Connection,
+    options: &ScanOptions,
+    pool: &rayon::ThreadPool,
 ) -> Result<BatchResult> {
     let mut result = BatchResult::default();
-    let tx = conn.transaction()?;
+    let mut scan_errors: Vec<ScanError> = Vec::new();
 
-    for entry in batch {
-        let path = entry.path();
-        let metadata = match entry.metadata() {
-            Ok(m) => m,
+    // Paths of files that are unchanged
+    let mut unchanged: Vec<String> = Vec::new();
+    // Files found at a new path, with the path their tracks move from
+    let mut moved: Vec<(String, PreparedFile)> = Vec::new();
+    // Files that need their tags read, with their existing track (None for new files)
+    let mut pending: Vec<(PreparedFile, Option<DbTrack>)> = Vec::new();
+    // Paths already moved from in this batch, so two copies of a file can't both take them
+    let mut claimed: HashSet<String> = HashSet::new();
+
+    for file in prepared {
+        let file = match file {
+            Ok(file) => file,
             Err(e) => {
-                eprintln!("Error getting metadata for {:?}: {}", path, e);
+                scan_errors.push(e);
                 continue;
             }
         };
+        let path_str = file.path.to_string_lossy().to_string();
 
-        let file_size = metadata.len() as i64;
-        let modified_time = metadata
-            .modified()?
-            .duration_since(SystemTime::UNIX_EPOCH)?
-            .as_secs() as i64;
+        // Same path already in the library - either unchanged or modified in place
+        if let Some(existing) = db::find_track_by_path(&path_str, conn)? {
+            if is_unchanged(&existing, &file) {
+                unchanged.push(path_str);
+            } else {
+                pending.push((file, Some(existing)));
+            }
+            continue;
+        }
 
-        let path_str = path.to_string_lossy().to_string();
+        // Unknown path - look for a moved file
+        let candidates = match file.first_track_hash() {
+            // Hash-based detection (default)
+            Some(hash) => db::find_tracks_by_hash(&hash, conn)?,
+            // Metadata-based detection (mtime + size) - FAST but less accurate
+            None => db::find_tracks_by_fingerprint(file.modified_time, file.file_size, conn)?,
+        };
+        // A track whose file still exists is a copy of this one, not where it moved from
+        let moved_track = candidates.into_iter().find(|track| {
+            !claimed.contains(&track.file_path) && !Path::new(&track.file_path).exists()
+        });
 
-        match detection_method {
-            DetectionMethod::Hash => {
-                // Hash-based detection (default)
-                let hash = match compute_quick_hash(path) {
-                    Ok(h) => h,
-                    Err(e) => {
-                        eprintln!("Error hashing {:?}: {}", path, e);
-                        continue;
-                    }
-                };
+        match moved_track {
+            // Different path, same hash/fingerprint - moved! (with all its virtual tracks)
+            Some(ScanTrackInfo { file_path, .. }) => {
+                claimed.insert(file_path.clone());
+                moved.push((file_path, file));
+            }
+            // No match found - new file
+            None => pending.push((file, None)),
+        }
+    }
 
-                match db::find_track_by_hash_tx(&hash, &tx)? {
-                    Some(ScanTrackInfo { id, file_path }) => {
-                        if file_path == path_str {
-                            // Same path, same hash - unchanged
-                            db::mark_track_processed_tx(id, &tx)?;
-                            result.unchanged += 1;
-                        } else {
-                            // Different path, same hash - moved!
-                            db::update_track_path_and_fingerprint_tx(
-                                id,
-                                &path_str,
-                                file_size,
-                                modified_time,
-                                &hash,
-                                &tx,
-                            )?;
-                            result.moved += 1;
-                        }
-                    }
-                    None => {
-                        // No match found - new file
-                        match insert_new_track(path, file_size, modified_time, &hash, &tx) {
-                            Ok(_) => result.added += 1,
-                            Err(e) => {
-                                eprintln!("Error inserting track {:?}: {}", path, e)
-                            }
-                        }
-                    }
+    let extracted: Vec<std::result::Result<ExtractedFile, ScanError>> = pool.install(|| {
+        pending
+            .par_iter()
+            .map(|(file, _)| extract_file(file, options))
+            .collect()
+    });
+
+    let tx = db::write_transaction(conn)?;
+
+    for path in &unchanged {
+        db::mark_file_tracks_processed_tx(path, &tx)?;
+    }
+    result.unchanged = unchanged.len();
+
+    for (old_path, file) in &moved {
+        let path_str = file.path.to_string_lossy().to_string();
+        match &file.content_hash {
+            Some(_) => db::update_track_path_and_fingerprint_tx(
+                old_path,
+                &path_str,
+                file.file_size,
+                file.modified_time,
+                &tx,
+            )?,
+            None => db::update_track_path_tx(old_path, &path_str, &tx)?,
+        }
+    }
+    result.moved = moved.len();
+
+    for ((file, existing), extracted) in pending.iter().zip(extracted) {
+        let path_str = file.path.to_string_lossy().to_string();
+        let extracted = match extracted {
+            Ok(extracted) => extracted,
+            Err(e) => {
+                // Keep the existing rows (and their lyricsfiles) of a modified file; the stale
+                // fingerprint makes the next scan retry the re-read
+                if existing.is_some() {
+                    db::mark_file_tracks_processed_tx(&path_str, &tx)?;
                 }
+                scan_errors.push(e);
+                continue;
             }
-            DetectionMethod::Metadata => {
-                // Metadata-based detection (mtime + size) - FAST but less accurate
-                match db::find_track_by_fingerprint_tx(modified_time, file_size, &tx)? {
-                    Some(ScanTrackInfo { id, file_path }) => {
-                        if file_path == path_str {
-                            // Same path, same fingerprint - unchanged
-                            db::mark_track_processed_tx(id, &tx)?;
-                            result.unchanged += 1;
-                        } else {
-                            // Different path, same fingerprint - moved!
-                            db::update_track_path_tx(id, &path_str, &tx)?;
-                            result.moved += 1;
-                        }
-                    }
-                    None => {
-                        // No fingerprint match - treat as new file
-                        let hash = match compute_quick_hash(path) {
-                            Ok(h) => h,
-                            Err(e) => {
-                                eprintln!("Error hashing {:?}: {}", path, e);
-                                continue;
-                            }
-                        };
-                        match insert_new_track(path, file_size, modified_time, &hash, &tx) {
-                            Ok(_) => result.added += 1,
-                            Err(e) => {
-                                eprintln!("Error inserting track {:?}: {}", path, e)
-                            }
-                        }
-                    }
+        };
+
+        match existing {
+            Some(existing) => match update_modified_file(existing, file, &extracted, &tx) {
+                Ok(_) => result.modified += 1,
+                Err(e) => {
+                    db::mark_file_tracks_processed_tx(&path_str, &tx)?;
+                    scan_errors.push(ScanError::new(
+                        &file.path,
+                        ScanErrorKind::Database,
+                        format!("Error updating modified track: {}", e),
+                    ));
                 }
-            }
+            },
+            None => match insert_new_tracks(file, &extracted, &tx) {
+                Ok(_) => result.added += 1,
+                Err(e) => scan_errors.push(ScanError::new(
+                    &file.path,
+                    ScanErrorKind::Database,
+                    format!("Error inserting track: {}", e),
+                )),
+            },
         }
     }
 
+    for scan_error in &scan_errors {
+        eprintln!("{}: {}", scan_error.file_path, scan_error.message);
+        db::insert_scan_error(scan_error, &tx)?;
+    }
+    result.errors = scan_errors.len();
+
     tx.commit()?;
     Ok(result)
 }
 
-/// Helper to insert a new track with metadata extraction
-fn insert_new_track(
-    path: &std::path::Path,
-    file_size: i64,
-    modified_time: i64,
-    content_hash: &str,
+/// Get or create the rows of the credited artists (primary artist first) and the album
+/// for a track's metadata
+fn find_or_add_artists_and_album(
+    metadata: &TrackMetadata,
     tx: &rusqlite::Transaction,
-) -> Result<()> {
-    // Extract metadata and lyrics
-    let (metadata, lyrics) = extract_track_info(path)?;
-
-    // Get or create artist
-    let artist_id = match db::find_artist_tx(&metadata.artist, tx) {
-        Ok(id) => id,
-        Err(_) => db::add_artist_tx(&metadata.artist, tx)?,
-    };
+) -> Result<(Vec<i64>, i64)> {
+    // Get or create artists
+    let mut artist_ids = Vec::with_capacity(metadata.artists.len());
+    for artist in metadata.credited_artists() {
+        let artist_id = match db::find_artist_tx(artist, tx) {
+            Ok(id) => id,
+            Err(_) => db::add_artist_tx(artist, tx)?,
+        };
+        if !artist_ids.contains(&artist_id) {
+            artist_ids.push(artist_id);
+        }
+    }
 
     // Get or create album
     let album_id = match db::find_album_tx(&metadata.album, &metadata.album_artist, tx) {
         Ok(id) => id,
         Err(_) => db::add_album_tx(&metadata.album, &metadata.album_artist, tx)?,
     };
+    db::update_album_tags_tx(album_id, &metadata.extended, tx)?;
+
+    Ok((artist_ids, album_id))
+}
+
+/// Helper to apply the re-read tracks of a file modified in place.
+/// A plain file keeps its row, otherwise (a CUE sheet was added, changed or removed)
+/// the file's tracks are replaced. Album artwork taken from the file is looked up again.
+fn update_modified_file(
+    existing: &DbTrack,
+    file: &PreparedFile,
+    extracted: &ExtractedFile,
+    tx: &rusqlite::Transaction,
+) -> Result<()> {
+    db::reset_album_artwork_by_source_tx(&existing.file_path, tx)?;
+
+    match extracted.tracks.as_slice() {
+        [(metadata, _)] if existing.start_offset.is_none() => {
+            update_modified_track(existing.id, file, &extracted.content_hash, metadata, tx)
+        }
+        _ => {
+            db::delete_tracks_by_file_path_tx(&existing.file_path, tx)?;
+            insert_new_tracks(file, extracted, tx)
+        }
+    }
+}
+
+/// Helper to update the row of a track modified in place with its re-read tags.
+/// The attached lyricsfile is kept; only its denormalized track metadata is refreshed.
+/// Albums/artists left without tracks are cleaned up by `delete_unprocessed_tracks`.
+fn update_modified_track(
+    track_id: i64,
+    file: &PreparedFile,
+    content_hash: &str,
+    metadata: &TrackMetadata,
+    tx: &rusqlite::Transaction,
+) -> Result<()> {
+    let (artist_ids, album_id) = find_or_add_artists_and_album(metadata, tx)?;
+
+    db::update_track_from_metadata_tx(
+        track_id,
+        metadata,
+        file.file_size,
+        file.modified_time,
+        content_hash,
+        artist_ids[0],
+        album_id,
+        tx,
+    )?;
+    db::set_track_artists_tx(track_id, &artist_ids, tx)?;
+
+    // Re-sync FTS index
+    db::delete_tracks_fts_by_ids_tx(&[track_id], tx)?;
+    db::insert_track_fts_tx(
+        track_id,
+        &crate::utils::prepare_input(&metadata.title),
+        &crate::utils::prepare_input(&metadata.searchable_artists()),
+        &crate::utils::prepare_input(&metadata.album),
+        tx,
+    )?;
+
+    db::update_lyricsfile_track_metadata_tx(
+        track_id,
+        &metadata.title,
+        &metadata.album,
+        metadata.primary_artist(),
+        metadata.duration,
+        tx,
+    )?;
+
+    Ok(())
+}
+
+/// Helper to insert the tracks of a new file
+fn insert_new_tracks(
+    file: &PreparedFile,
+    extracted: &ExtractedFile,
+    tx: &rusqlite::Transaction,
+) -> Result<()> {
+    for (index, (metadata, lyrics)) in extracted.tracks.iter().enumerate() {
+        let content_hash = track_hash(&extracted.content_hash, index, metadata.start_offset);
+        insert_new_track(file, &content_hash, metadata, lyrics, tx)?;
+    }
+
+    Ok(())
+}
+
+/// Helper to insert a new track from its extracted metadata and lyrics
+fn insert_new_track(
+    file: &PreparedFile,
+    content_hash: &str,
+    metadata: &TrackMetadata,
+    lyrics: &LyricsInfo,
+    tx: &rusqlite::Transaction,
+) -> Result<()> {
+    let (artist_ids, album_id) = find_or_add_artists_and_album(metadata, tx)?;
 
     // Insert track
     let track_id = db::insert_track_from_metadata_tx(
-        &metadata,
-        &lyrics,
-        file_size,
-        modified_time,
+        metadata,
+        lyrics,
+        file.file_size,
+        file.modified_time,
         content_hash,
-        artist_id,
+        artist_ids[0],
         album_id,
         tx,
     )?;
+    db::set_track_artists_tx(track_id, &artist_ids, tx)?;
 
     // Sync FTS index
     db::insert_track_fts_tx(
         track_id,
         &crate::utils::prepare_input(&metadata.title),
-        &crate::utils::prepare_input(&metadata.artist),
+        &crate::utils::prepare_input(&metadata.searchable_artists()),
         &crate::utils::prepare_input(&metadata.album),
         tx,
     )?;
@@ -282,7 +1297,7 @@ fn insert_new_track(
     // Check for orphaned lyricsfile before importing embedded lyrics
     let orphaned_lyricsfile = db::find_orphaned_lyricsfile_tx(
         &metadata.title,
-        &metadata.artist,
+        metadata.primary_artist(),
         &metadata.album,
         metadata.duration,
         tx,
@@ -292,11 +1307,11 @@ fn insert_new_track(
         // Reattach orphaned lyricsfile to this track
         db::reattach_lyricsfile_to_track_tx(lyricsfile_id, track_id, tx)?;
     } else {
-        // No orphaned lyricsfile found, import embedded lyrics as usual
+        // No orphaned lyricsfile found, import sidecar/embedded lyrics as usual
         let lyricsfile_track_metadata = LyricsfileTrackMetadata::new(
             &metadata.title,
             &metadata.album,
-            &metadata.artist,
+            metadata.primary_artist(),
             metadata.duration,
         );
 
@@ -309,7 +1324,7 @@ fn insert_new_track(
                 track_id,
                 &metadata.title,
                 &metadata.album,
-                &metadata.artist,
+                metadata.primary_artist(),
                 metadata.duration,
                 &lyricsfile,
                 tx,
@@ -319,3 +1334,90 @@ fn insert_new_track(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_scan_paths_skips_nested_paths() {
+        let paths = vec![
+            "/music/Artist/Album/".to_owned(),
+            "/music/Artist".to_owned(),
+            "/music/Other".to_owned(),
+            "/music/Artist".to_owned(),
+            "/music/Artist Two".to_owned(),
+        ];
+
+        assert_eq!(
+            normalize_scan_paths(&paths),
+            vec!["/music/Artist", "/music/Artist Two", "/music/Other"]
+        );
+    }
+
+    #[test]
+    fn test_walk_and_prepare_takes_file_paths_as_is() {
+        let directory =
+            std::env::temp_dir().join(format!("lrcget_scan_file_path_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let song_path = directory.join("song.mp3");
+        std::fs::write(&song_path, b"not really audio").unwrap();
+        std::fs::write(directory.join("cover.jpg"), b"").unwrap();
+
+        let options = ScanOptions {
+            audio_extensions: vec!["mp3".to_owned()],
+            ..Default::default()
+        };
+        let pool = build_worker_pool(&options).unwrap();
+        let (sender, receiver) = mpsc::sync_channel(PIPELINE_DEPTH);
+        let paths = vec![
+            song_path.to_string_lossy().to_string(),
+            directory.join("cover.jpg").to_string_lossy().to_string(),
+        ];
+
+        let ignored = walk_and_prepare(&paths, &options, &pool, sender).unwrap();
+        let batches: Vec<WalkedBatch> = receiver.iter().collect();
+
+        assert_eq!(ignored, 0);
+        assert_eq!(batches.len(), 1);
+        match &batches[0] {
+            WalkedBatch::Prepared(prepared, discovered_files) => {
+                assert_eq!(*discovered_files, 1);
+                assert_eq!(prepared.len(), 1);
+                let file = prepared[0].as_ref().ok().unwrap();
+                assert_eq!(file.path, song_path);
+                assert!(file.content_hash.is_some());
+            }
+            WalkedBatch::WalkError(..) => panic!("unexpected walk error"),
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_is_directory_available() {
+        let directory =
+            std::env::temp_dir().join(format!("lrcget_scan_available_{}", std::process::id()));
+        assert!(!is_directory_available(&directory));
+
+        // An empty directory on the same device as its parent may be an unmounted drive
+        std::fs::create_dir_all(&directory).unwrap();
+        assert!(!is_directory_available(&directory));
+
+        std::fs::write(directory.join("song.mp3"), b"").unwrap();
+        assert!(is_directory_available(&directory));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_track_hash_salts_virtual_tracks() {
+        assert_eq!(track_hash("0123456789abcdef", 0, None), "0123456789abcdef");
+
+        let first = track_hash("0123456789abcdef", 0, Some(0.0));
+        let second = track_hash("0123456789abcdef", 1, Some(215.4));
+        assert_ne!(first, "0123456789abcdef");
+        assert_ne!(first, second);
+        assert_eq!(second, track_hash("0123456789abcdef", 1, Some(215.4)));
+    }
+}
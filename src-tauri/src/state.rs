@@ -1,7 +1,14 @@
 use rusqlite::Connection;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 use tauri::{AppHandle, Manager, State};
 
+use crate::lrclib::client::LrclibClient;
+use crate::mass_download::DownloadJob;
 use crate::player::Player;
+use crate::providers::local_folder::LocalFolderIndex;
+use crate::scanner::job::ScanJob;
+use crate::watcher::LibraryWatcher;
 
 use serde::Serialize;
 
@@ -28,8 +35,19 @@ pub struct Notify {
 
 pub struct AppState {
     pub db: std::sync::Mutex<Option<Connection>>,
+    /// Separate connection for library scans and the watcher, so long writes don't hold the
+    /// lock of `db` the UI reads through
+    pub scan_db: std::sync::Mutex<Option<Connection>>,
     pub player: std::sync::Mutex<Option<Player>>,
     pub queued_notifications: std::sync::Mutex<Vec<Notify>>,
+    pub watcher: std::sync::Mutex<Option<LibraryWatcher>>,
+    pub scan_job: std::sync::Mutex<Option<Arc<ScanJob>>>,
+    pub download_job: std::sync::Mutex<Option<Arc<DownloadJob>>>,
+    /// Set while publishing or flagging solves its challenge, to cancel it
+    pub challenge: std::sync::Mutex<Option<Arc<AtomicBool>>>,
+    pub lrclib: LrclibClient,
+    /// Index of the `local_lyrics_folder`, shared by downloads until it gets stale
+    pub local_lyrics_index: std::sync::Mutex<Option<Arc<LocalFolderIndex>>>,
 }
 
 pub trait ServiceAccess {
@@ -40,6 +58,10 @@ pub trait ServiceAccess {
     fn db_mut<F, TResult>(&self, operation: F) -> TResult
     where
         F: FnOnce(&mut Connection) -> TResult;
+
+    fn scan_db_mut<F, TResult>(&self, operation: F) -> TResult
+    where
+        F: FnOnce(&mut Connection) -> TResult;
 }
 
 impl ServiceAccess for AppHandle {
@@ -64,4 +86,15 @@ impl ServiceAccess for AppHandle {
 
         operation(db)
     }
+
+    fn scan_db_mut<F, TResult>(&self, operation: F) -> TResult
+    where
+        F: FnOnce(&mut Connection) -> TResult,
+    {
+        let app_state: State<AppState> = self.state();
+        let mut db_connection_guard = app_state.scan_db.lock().unwrap();
+        let db = db_connection_guard.as_mut().unwrap();
+
+        operation(db)
+    }
 }
@@ -0,0 +1,142 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+use notify_debouncer_full::notify::event::ModifyKind;
+use notify_debouncer_full::notify::{EventKind, RecommendedWatcher, RecursiveMode};
+use notify_debouncer_full::{
+    new_debouncer, DebounceEventResult, DebouncedEvent, Debouncer, RecommendedCache,
+};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::db;
+use crate::scanner;
+use crate::state::{AppState, ServiceAccess};
+
+/// How long filesystem events are collected before they are applied to the library
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Watches the library directories and applies created, renamed, modified and
+/// deleted audio files to the database. Watching stops when this is dropped.
+pub struct LibraryWatcher {
+    _debouncer: Debouncer<RecommendedWatcher, RecommendedCache>,
+}
+
+impl LibraryWatcher {
+    pub fn new(directories: &[String], app_handle: AppHandle) -> Result<Self> {
+        let mut debouncer = new_debouncer(
+            DEBOUNCE_TIMEOUT,
+            None,
+            move |result: DebounceEventResult| match result {
+                Ok(events) => handle_events(&app_handle, events),
+                Err(errors) => {
+                    for error in errors {
+                        eprintln!("Library watcher error: {}", error);
+                    }
+                }
+            },
+        )?;
+
+        for directory in directories {
+            if let Err(e) = debouncer.watch(directory, RecursiveMode::Recursive) {
+                eprintln!("Failed to watch directory {}: {}", directory, e);
+            }
+        }
+
+        Ok(Self {
+            _debouncer: debouncer,
+        })
+    }
+}
+
+/// Start, restart or stop the library watcher according to the current config and directories
+pub fn refresh_library_watcher(app_handle: &AppHandle) -> Result<()> {
+    let app_state: State<AppState> = app_handle.state();
+
+    // Drop the running watcher first so directories are never watched twice
+    app_state.watcher.lock().unwrap().take();
+
+    let (config, directories) =
+        app_handle.db(|db| -> Result<_> { Ok((db::get_config(db)?, db::get_directories(db)?)) })?;
+
+    if !config.watch_library || directories.is_empty() {
+        return Ok(());
+    }
+
+    let watcher = LibraryWatcher::new(&directories, app_handle.clone())?;
+    *app_state.watcher.lock().unwrap() = Some(watcher);
+
+    Ok(())
+}
+
+fn handle_events(app_handle: &AppHandle, events: Vec<DebouncedEvent>) {
+    let changed_paths: BTreeSet<PathBuf> = events
+        .into_iter()
+        .filter(|event| is_relevant_event(&event.kind))
+        .flat_map(|event| event.event.paths)
+        .collect();
+
+    if changed_paths.is_empty() {
+        return;
+    }
+
+    let changed_paths: Vec<PathBuf> = changed_paths.into_iter().collect();
+
+    // Waits for a running scan to finish; a paused scan keeps the connection until resumed
+    let scan_result = app_handle.scan_db_mut(|conn| -> Result<Option<scanner::models::ScanResult>> {
+        // Changes are picked up by the first scan while the library is not initialized
+        if !db::get_init(conn)? {
+            return Ok(None);
+        }
+
+        let config = db::get_config(conn)?;
+        let artwork_cache_dir = app_handle
+            .path()
+            .app_data_dir()?
+            .join(scanner::artwork::CACHE_DIR_NAME);
+        let scan_options = scanner::models::ScanOptions::from_config(&config, None)?
+            .with_ignore_patterns(db::get_directory_ignore_patterns(conn)?)
+            .with_artwork_cache_dir(artwork_cache_dir);
+
+        let scan_result = scanner::scan::apply_file_changes(
+            &changed_paths,
+            &db::get_directories(conn)?,
+            conn,
+            &|progress| {
+                let _ = app_handle.emit("watch-scan-progress", progress);
+            },
+            &scan_options,
+        )?;
+
+        Ok(Some(scan_result))
+    });
+
+    match scan_result {
+        Ok(Some(scan_result)) => {
+            let changed_count = scan_result.added
+                + scan_result.modified
+                + scan_result.moved
+                + scan_result.deleted
+                + scan_result.offline;
+
+            if changed_count > 0 {
+                let _ = app_handle.emit("watch-scan-complete", &scan_result);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("Failed to apply library changes: {}", e),
+    }
+}
+
+/// Only create, rename, content change and delete events can affect the library
+fn is_relevant_event(kind: &EventKind) -> bool {
+    matches!(
+        kind,
+        EventKind::Create(_)
+            | EventKind::Remove(_)
+            | EventKind::Modify(ModifyKind::Name(_))
+            | EventKind::Modify(ModifyKind::Data(_))
+            | EventKind::Modify(ModifyKind::Any)
+    )
+}
@@ -1,14 +1,14 @@
 import { computed, markRaw, ref } from 'vue'
 import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
 
-const delay = time => new Promise((resolve, reject) => setTimeout(resolve, time))
-
-const downloadQueue = ref([])
+// Downloads run as a job in the backend (see mass_download.rs); this mirrors its progress
 const log = ref([])
 const successCount = ref(0)
 const failureCount = ref(0)
 const downloadedCount = ref(0)
 const isDownloading = ref(false)
+const isPaused = ref(false)
 const totalCount = ref(0)
 
 const addLog = logObj => {
@@ -18,45 +18,51 @@ const addLog = logObj => {
   }
 }
 
-const downloadLyrics = async track => {
-  try {
-    const result = await invoke('download_lyrics', { trackId: track.id })
-
-    if (!isDownloading.value) {
-      return
-    }
-
-    addLog({
-      status: 'success',
-      title: track.title,
-      artistName: track.artist_name,
-      message: result,
-    })
-    successCount.value++
-  } catch (error) {
-    if (!isDownloading.value) {
-      return
-    }
+const applyStatus = status => {
+  successCount.value = status.downloaded
+  failureCount.value = status.failed
+  downloadedCount.value = status.downloaded + status.failed
+  totalCount.value = status.total
+  isPaused.value = status.state === 'paused'
+}
+
+listen('mass-download-progress', event => {
+  const progress = event.payload
 
-    addLog({ status: 'failure', title: track.title, artistName: track.artist_name, message: error })
-    failureCount.value++
+  if (!isDownloading.value) {
+    return
   }
 
-  downloadedCount.value++
-}
+  addLog({
+    status: progress.success ? 'success' : 'failure',
+    title: progress.title ?? `Track #${progress.trackId}`,
+    artistName: progress.artistName ?? '',
+    message: progress.message,
+  })
+  applyStatus(progress.status)
+})
 
-const downloadNext = async () => {
-  while (true) {
-    if (downloadQueue.value.length === 0) {
-      await delay(1000)
-      continue
-    }
+listen('mass-download-state', event => {
+  if (isDownloading.value) {
+    applyStatus(event.payload)
+  }
+})
 
-    const trackId = downloadQueue.value.shift()
-    const track = await invoke('get_track', { trackId: trackId })
-    await downloadLyrics(track)
+listen('mass-download-complete', event => {
+  if (isDownloading.value) {
+    applyStatus(event.payload)
+    // A cancelled job never reaches its total, so count it as done
+    totalCount.value = downloadedCount.value
+  }
+})
+
+// Pick up a download left unfinished by the previous session
+const restoreDownloads = async () => {
+  const status = await invoke('get_mass_download_status')
 
-    await delay(1)
+  if (status) {
+    isDownloading.value = true
+    applyStatus(status)
   }
 }
 
@@ -76,36 +82,50 @@ const downloadProgress = computed(() => {
   return downloadedCount.value / totalCount.value
 })
 
-const addToQueue = trackIds => {
+// Without track IDs, the backend picks the tracks of the whole library according to the
+// skip_tracks_with_* config. With bypassCache, cached lookups are asked again.
+const addToQueue = async (trackIds, { bypassCache = false } = {}) => {
+  const status = await invoke('start_mass_download', { trackIds: trackIds ?? null, bypassCache })
+
   isDownloading.value = true
+  applyStatus(status)
 
-  for (let i = 0; i < trackIds.length; i++) {
-    downloadQueue.value.push(trackIds[i])
-  }
+  console.log(`Added tracks to download queue, ${status.total} in total`)
+}
 
-  totalCount.value += trackIds.length
+const pauseDownloading = async () => {
+  applyStatus(await invoke('pause_mass_download'))
+}
 
-  console.log(`Added ${totalCount.value} tracks to download queue`)
+const resumeDownloading = async () => {
+  applyStatus(await invoke('resume_mass_download'))
 }
 
 const startOver = () => {
-  downloadQueue.value = []
   log.value = []
   successCount.value = 0
   failureCount.value = 0
   downloadedCount.value = 0
   totalCount.value = 0
   isDownloading.value = false
+  isPaused.value = false
 }
 
-const stopDownloading = () => {
+const stopDownloading = async () => {
+  try {
+    await invoke('cancel_mass_download')
+  } catch (error) {
+    // The job finished in the meantime
+    console.error(error)
+  }
+
   startOver()
 }
 
 export function useDownloader() {
   return {
     isDownloading,
-    downloadQueue,
+    isPaused,
     downloadProgress,
     successCount,
     failureCount,
@@ -113,8 +133,10 @@ export function useDownloader() {
     downloadedCount,
     log,
     addToQueue,
+    pauseDownloading,
+    resumeDownloading,
     startOver,
     stopDownloading,
-    downloadNext,
+    restoreDownloads,
   }
 }